@@ -9,7 +9,7 @@ use std::sync::LazyLock;
 use std::fmt::Display;
 use std::borrow::Cow;
 
-const PERCENT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"%s").unwrap());
+static PERCENT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"%s").unwrap());
 
 #[derive(Debug, Clone, Error)]
 pub enum FormatterError {
@@ -0,0 +1,264 @@
+//! The AI agents which can control each player in a card game.
+
+use crate::interpreter::eval::EvaluatorState;
+use crate::interpreter::error::EvalError;
+use crate::interpreter::value::{Value, HashKey};
+use crate::interpreter::class::Class;
+use super::yaml_file::{YamlFile, YamlFileError};
+
+use clap::{Args, ValueEnum};
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+use thiserror::Error;
+use ordermap::OrderMap;
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub const LOOKAHEAD_AI_AGENT_PATH: &str = "res://card_game/playing_field/player_agent/lookahead_ai_agent/lookahead_ai_agent.gd";
+pub const LOOKAHEAD_PRIORITIES_PATH: &str = "res://card_game/playing_field/player_agent/lookahead_ai_agent/lookahead_priorities.gd";
+pub const MONTE_CARLO_AI_AGENT_PATH: &str = "res://card_game/playing_field/player_agent/monte_carlo_ai_agent/monte_carlo_ai_agent.gd";
+pub const MONTE_CARLO_AI_AGENT_SCENE_PATH: &str = "res://card_game/playing_field/player_agent/monte_carlo_ai_agent/monte_carlo_ai_agent.tscn";
+pub const GREEDY_AI_AGENT_PATH: &str = "res://card_game/playing_field/player_agent/greedy_ai_agent/greedy_ai_agent.gd";
+pub const NULL_AI_AGENT_PATH: &str = "res://card_game/playing_field/player_agent/null_ai_agent.gd";
+
+/// A player agent that can be loaded into the simulator.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, ValueEnum, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum AgentSpec {
+  /// Plays the best card in hand according to a fixed set of
+  /// priorities, looking one card ahead.
+  #[default]
+  Lookahead,
+  /// Plays whichever move wins the most games when the rest of the
+  /// game is played out by greedy agents. Much slower than the other
+  /// agents.
+  #[value(name = "monte-carlo")]
+  #[strum(serialize = "monte-carlo")]
+  MonteCarlo,
+  /// Plays random playable cards until none remain.
+  Greedy,
+  /// Passes every turn without playing anything.
+  Null,
+}
+
+/// The names of the constants in `LookaheadPriorities`, which are the
+/// only valid keys of [`LookaheadPriorities`].
+pub const PRIORITY_NAMES: &[&str] = &[
+  "EVIL_POINT", "FORT_DEFENSE", "DESTINYS_SONG", "HAND_LIMIT_UP", "EFFECT_DRAW",
+  "FIRST_DRAW", "NORMAL_DRAW", "EVIL_POINT_OPPORTUNITY", "IMMUNITY", "UNDEAD",
+  "UNDEAD_DESTRUCTION", "UNDEAD_BONUS_ATTACK", "CLOWNING", "BEDEVILING", "ROBOTING",
+  "WILDING", "SPIKY", "ELIMINATE_HERO_CHECK", "HERO_SCRY", "HOSTAGE",
+  "CARD_IN_HAND", "SINGLE_USE_EXILE", "BLIND_EXILE", "DOOMED_EXILE", "FARM_RECOVERY",
+  "RIGHT_ORDER", "MINOR_RIGHT_ORDER", "DEVIL_OPPORTUNITY", "RULE_22", "THROWING_DICE",
+];
+
+/// Overrides for the priorities of a lookahead agent, keyed by the
+/// constant names in `LookaheadPriorities` (such as `UNDEAD` or
+/// `DESTINYS_SONG`). Any priority not specified keeps the default
+/// value from the GDScript side.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct LookaheadPriorities(pub BTreeMap<String, f64>);
+
+/// A single player's agent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerAgent {
+  pub spec: AgentSpec,
+  /// Only meaningful for [`AgentSpec::Lookahead`].
+  pub priorities: Option<LookaheadPriorities>,
+}
+
+/// The agents controlling each player in a card game.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerAgents {
+  pub bottom: PlayerAgent,
+  pub top: PlayerAgent,
+}
+
+/// Command line arguments for selecting the agents controlling each
+/// player.
+#[derive(Debug, Clone, Default, Args)]
+pub struct PlayerAgentsArgs {
+  /// Agent controlling the bottom player.
+  #[arg(long, value_enum, default_value_t)]
+  pub bottom_agent: AgentSpec,
+  /// Agent controlling the top player.
+  #[arg(long, value_enum, default_value_t)]
+  pub top_agent: AgentSpec,
+  /// YAML or JSON file of priorities for the bottom player's
+  /// lookahead agent.
+  #[arg(long)]
+  pub bottom_priorities: Option<PathBuf>,
+  /// YAML or JSON file of priorities for the top player's lookahead
+  /// agent.
+  #[arg(long)]
+  pub top_priorities: Option<PathBuf>,
+}
+
+#[derive(Debug, Error)]
+pub enum AgentLoadError {
+  #[error("{0}")]
+  FileError(#[from] YamlFileError),
+  #[error("Priorities were supplied for a {0} agent, but only lookahead agents have priorities")]
+  UnexpectedPriorities(AgentSpec),
+  #[error("Unknown lookahead priority '{0}'")]
+  UnknownPriority(String),
+}
+
+impl AgentSpec {
+  /// Path to the GDScript file implementing this agent.
+  pub fn script_path(self) -> &'static str {
+    match self {
+      AgentSpec::Lookahead => LOOKAHEAD_AI_AGENT_PATH,
+      AgentSpec::MonteCarlo => MONTE_CARLO_AI_AGENT_PATH,
+      AgentSpec::Greedy => GREEDY_AI_AGENT_PATH,
+      AgentSpec::Null => NULL_AI_AGENT_PATH,
+    }
+  }
+
+  /// Path to the scene whose root node is this agent, if the agent
+  /// must be instantiated from its scene (in order to get the scene's
+  /// child nodes).
+  pub fn scene_path(self) -> Option<&'static str> {
+    match self {
+      AgentSpec::MonteCarlo => Some(MONTE_CARLO_AI_AGENT_SCENE_PATH),
+      AgentSpec::Lookahead | AgentSpec::Greedy | AgentSpec::Null => None,
+    }
+  }
+}
+
+impl YamlFile for LookaheadPriorities {}
+
+impl LookaheadPriorities {
+  /// Reads priorities from a YAML or JSON file, and checks them with
+  /// [`validate`](Self::validate).
+  pub fn load(path: impl AsRef<Path>) -> Result<Self, AgentLoadError> {
+    let priorities = Self::read_from_path(path)?;
+    priorities.validate()?;
+    Ok(priorities)
+  }
+
+  /// Checks that every key names a constant in `LookaheadPriorities`.
+  /// GDScript would silently ignore a misspelled priority.
+  pub fn validate(&self) -> Result<(), AgentLoadError> {
+    match self.0.keys().find(|key| !PRIORITY_NAMES.contains(&key.as_str())) {
+      Some(key) => Err(AgentLoadError::UnknownPriority(key.to_owned())),
+      None => Ok(()),
+    }
+  }
+
+  fn to_gdscript_dict(&self) -> Value {
+    let dict = self.0.iter()
+      .map(|(key, value)| (HashKey::String(key.to_owned()), Value::from(*value)))
+      .collect::<OrderMap<_, _>>();
+    Value::new_dict(dict)
+  }
+}
+
+impl PlayerAgent {
+  pub(super) fn create_agent(&self, state: &EvaluatorState) -> Result<Value, EvalError> {
+    let mut args = Vec::new();
+    if let Some(priorities) = &self.priorities {
+      let priorities_class = get_class(state, LOOKAHEAD_PRIORITIES_PATH)?;
+      args.push(state.call_function_on_class(&priorities_class, "new", vec![priorities.to_gdscript_dict()])?);
+    }
+    if let Some(scene_path) = self.spec.scene_path() {
+      let scene = get_class(state, scene_path)?;
+      return state.call_function_on_class(&scene, "instantiate", args);
+    }
+    let class = get_class(state, self.spec.script_path())?;
+    state.call_function_on_class(&class, "new", args)
+  }
+
+  fn resolve(spec: AgentSpec, priorities_file: Option<&Path>) -> Result<Self, AgentLoadError> {
+    let priorities = match priorities_file {
+      Some(_) if spec != AgentSpec::Lookahead => { return Err(AgentLoadError::UnexpectedPriorities(spec)); }
+      Some(path) => Some(LookaheadPriorities::load(path)?),
+      None => None,
+    };
+    Ok(PlayerAgent { spec, priorities })
+  }
+}
+
+impl PlayerAgents {
+  /// Exchanges the two agents. Agents are attached to decks, so this
+  /// should be done alongside [`CardGameEnv::swap_sides`](super::CardGameEnv::swap_sides).
+  pub fn swap_sides(self) -> Self {
+    PlayerAgents {
+      bottom: self.top,
+      top: self.bottom,
+    }
+  }
+}
+
+impl PlayerAgentsArgs {
+  pub fn resolve(&self) -> Result<PlayerAgents, AgentLoadError> {
+    Ok(PlayerAgents {
+      bottom: PlayerAgent::resolve(self.bottom_agent, self.bottom_priorities.as_deref())?,
+      top: PlayerAgent::resolve(self.top_agent, self.top_priorities.as_deref())?,
+    })
+  }
+}
+
+impl fmt::Display for PlayerAgent {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    write!(f, "{}", self.spec)?;
+    if let Some(priorities) = &self.priorities {
+      let overrides = priorities.0.iter().map(|(k, v)| format!("{k}={v}")).collect::<Vec<_>>();
+      write!(f, " (priorities: {})", overrides.join(", "))?;
+    }
+    Ok(())
+  }
+}
+
+fn get_class(state: &EvaluatorState, path: &str) -> Result<Arc<Class>, EvalError> {
+  state.superglobal_state().get_file(path)
+    .ok_or_else(|| EvalError::UndefinedClass(path.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_read_priorities_yaml_and_json() {
+    let yaml = LookaheadPriorities::read_from_file("UNDEAD: 1.5\nCLOWNING: 2\n".as_bytes()).unwrap();
+    let json = LookaheadPriorities::read_from_file(r#"{"UNDEAD": 1.5, "CLOWNING": 2.0}"#.as_bytes()).unwrap();
+    assert_eq!(yaml, json);
+    assert_eq!(yaml.0.get("CLOWNING"), Some(&2.0));
+  }
+
+  #[test]
+  fn test_validate_priorities() {
+    let priorities = LookaheadPriorities::read_from_file("UNDEAD: 1.5\nCLOWNING: 2\n".as_bytes()).unwrap();
+    assert!(priorities.validate().is_ok());
+    let priorities = LookaheadPriorities::read_from_file("UNDEAD: 1.5\nCLOWNS: 2\n".as_bytes()).unwrap();
+    assert!(matches!(priorities.validate(), Err(AgentLoadError::UnknownPriority(key)) if key == "CLOWNS"));
+  }
+
+  #[test]
+  fn test_priority_names_match_gdscript() {
+    let path = format!("{}/../card_game/playing_field/player_agent/lookahead_ai_agent/lookahead_priorities.gd", env!("CARGO_MANIFEST_DIR"));
+    let source = std::fs::read_to_string(path).unwrap();
+    let gdscript_names = source.lines()
+      .filter(|line| line.starts_with("const ") && line.contains("&\""))
+      .map(|line| line["const ".len()..].split([' ', ':', '=']).next().unwrap())
+      .collect::<Vec<_>>();
+    assert_eq!(gdscript_names, PRIORITY_NAMES);
+  }
+
+  #[test]
+  fn test_monte_carlo_agent_name() {
+    assert_eq!(AgentSpec::MonteCarlo.to_string(), "monte-carlo");
+    assert_eq!(AgentSpec::from_str("monte-carlo", false), Ok(AgentSpec::MonteCarlo));
+  }
+
+  #[test]
+  fn test_priorities_require_lookahead_agent() {
+    let err = PlayerAgent::resolve(AgentSpec::Greedy, Some(Path::new("priorities.yml"))).unwrap_err();
+    assert!(matches!(err, AgentLoadError::UnexpectedPriorities(AgentSpec::Greedy)));
+  }
+}
@@ -0,0 +1,132 @@
+//! Static information about the playable cards, for the parts of the
+//! simulator that reason about deck contents rather than play games.
+//!
+//! Rarity and limited status come from the codex metadata file. Star
+//! cost and archetypes are not part of the metadata, so they are read
+//! from the cards' GDScript definitions.
+
+use super::{CardId, GameEngine};
+use crate::interpreter::eval::EvaluatorState;
+use crate::interpreter::error::EvalError;
+use crate::interpreter::value::{SimpleValue, Value};
+use crate::interpreter::operator::expect_int;
+use crate::interpreter::mocking::codex::{CodexDataFile, Rarity};
+
+use rand_chacha::ChaCha8Rng;
+use rand::SeedableRng;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+const ARCHETYPE_GD_NAME: &str = "Archetype";
+
+/// Every card which can appear in a deck, indexed by ID.
+#[derive(Debug, Clone)]
+pub struct CardPool {
+  cards: BTreeMap<CardId, CardInfo>,
+  archetype_names: BTreeMap<i64, String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CardInfo {
+  pub id: CardId,
+  pub name: String,
+  pub rarity: Rarity,
+  pub limited: bool,
+  pub star_cost: i64,
+  /// Whether this is a Minion card, as opposed to an Effect card.
+  pub is_minion: bool,
+  /// The base archetypes of a Minion card. Always empty for Effect
+  /// cards.
+  pub archetypes: Vec<i64>,
+}
+
+impl CardInfo {
+  /// The most copies of this card that a reasonable deck may
+  /// contain. Limited cards and Ultra Rares are limited to one copy,
+  /// and every other card to three, as checked by
+  /// [`DeckValidator`](super::deck::validator::DeckValidator).
+  pub fn copy_limit(&self) -> usize {
+    if self.limited || self.rarity == Rarity::UltraRare { 1 } else { 3 }
+  }
+}
+
+impl CardPool {
+  /// Loads information about every card in the codex, except for the
+  /// null Minion (ID 0), which cannot appear in decks.
+  pub fn load(engine: &GameEngine, codex: &CodexDataFile) -> Result<Self, EvalError> {
+    // Card metadata methods are pure, but the evaluator needs a
+    // source of randomness regardless.
+    let state = EvaluatorState::new(Arc::clone(engine.superglobals()), ChaCha8Rng::seed_from_u64(0));
+    let cards = codex.cards.iter()
+      .filter(|entry| entry.id > 0)
+      .map(|entry| {
+        let card_class = state.get_file(&entry.path)
+          .ok_or_else(|| EvalError::UndefinedClass(entry.path.to_owned()))?;
+        let card = state.call_function_on_class(&card_class, "new", Vec::new())?;
+        let star_cost = expect_int("CardPool", &state.call_function_on(&card, "get_star_cost", Vec::new())?)?;
+        // Only Minion card types define get_base_archetypes.
+        let is_minion = card.get_func("get_base_archetypes", state.superglobal_state().bootstrapped_classes()).is_ok();
+        let archetypes = if is_minion {
+          let archetypes = state.call_function_on(&card, "get_base_archetypes", Vec::new())?;
+          archetypes.try_iter()?
+            .map(|archetype| expect_int("CardPool", &archetype))
+            .collect::<Result<Vec<_>, _>>()?
+        } else {
+          Vec::new()
+        };
+        let info = CardInfo {
+          id: CardId(entry.id),
+          name: entry.name.to_owned(),
+          rarity: entry.rarity,
+          limited: entry.limited,
+          star_cost,
+          is_minion,
+          archetypes,
+        };
+        Ok((info.id, info))
+      })
+      .collect::<Result<BTreeMap<_, _>, EvalError>>()?;
+    let archetype_names = load_archetype_names(&state)?;
+    Ok(CardPool { cards, archetype_names })
+  }
+
+  pub fn get(&self, id: CardId) -> Option<&CardInfo> {
+    self.cards.get(&id)
+  }
+
+  pub fn cards(&self) -> impl Iterator<Item = &CardInfo> {
+    self.cards.values()
+  }
+
+  pub fn ids(&self) -> impl Iterator<Item = CardId> + '_ {
+    self.cards.keys().copied()
+  }
+
+  /// Looks up an archetype by its constant name in `Archetype`, such
+  /// as `CLOWN`.
+  pub fn archetype_id(&self, name: &str) -> Option<i64> {
+    self.archetype_names.iter()
+      .find(|(_, archetype_name)| archetype_name.as_str() == name)
+      .map(|(id, _)| *id)
+  }
+
+  pub fn archetype_name(&self, id: i64) -> Option<&str> {
+    self.archetype_names.get(&id).map(String::as_str)
+  }
+}
+
+/// Reads the archetype constants from the GDScript `Archetype` class.
+fn load_archetype_names(state: &EvaluatorState) -> Result<BTreeMap<i64, String>, EvalError> {
+  let Some(SimpleValue::ClassRef(archetype_class)) = state.superglobal_state().get_var(ARCHETYPE_GD_NAME) else {
+    return Err(EvalError::UnknownClass(ARCHETYPE_GD_NAME.to_string()));
+  };
+  let archetype_value = Value::ClassRef(Arc::clone(archetype_class));
+  let mut names = BTreeMap::new();
+  for name in archetype_class.get_constants_table().keys() {
+    if let Value::Int(id) = archetype_value.get_value(name.as_ref(), state.superglobal_state())? {
+      names.insert(id, name.to_string());
+    }
+  }
+  Ok(names)
+}
@@ -0,0 +1,364 @@
+//! Per-card performance statistics across a batch of games,
+//! aggregated from the events reported to game observers.
+//!
+//! Changes to fort defense are attributed to the card whose hook was
+//! executing at the time (see [`StatSource`]), which separates damage
+//! dealt by a Minion's attacks from damage dealt by its effects.
+//!
+//! The win rates when played and when not played compare player-games
+//! which started with the card in the deck, so they only include such
+//! player-games. Cards that a player uses without having them in the
+//! starting deck (such as the outputs of Mystery Box) still count
+//! toward plays, destructions, and damage, and those player-games are
+//! counted separately in [`CardRecord::games_generated`].
+
+use super::{CardGameEnv, CardId, GameEngineError, GameOutcome, GameResult, GameWinner};
+use super::ladder::Record;
+use super::observer::{CardAction, CardHook, GameObserver, GameObserverFactory, ObservedCard, Stat, StatChange, StatSource};
+use crate::interpreter::mocking::codex::CodexDataFile;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+/// Statistics for every card seen in a batch of games. Each player in
+/// each game counts separately, so a card in both decks contributes
+/// two player-games per game.
+#[derive(Debug, Clone, Default)]
+pub struct CardStats {
+  /// Number of games aggregated, excluding games that ended in an
+  /// error.
+  pub games: u64,
+  pub cards: BTreeMap<CardId, CardRecord>,
+}
+
+/// Statistics for a single card.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CardRecord {
+  /// Player-games in which the card was drawn at least once.
+  pub games_drawn: u64,
+  pub times_played: u64,
+  /// Results of the player-games in which the card was in the
+  /// player's starting deck and was played at least once.
+  pub when_played: Record,
+  /// Results of the player-games in which the card was in the
+  /// player's deck but was never played.
+  pub when_not_played: Record,
+  /// Sum of the turn of first play over the player-games counted in
+  /// `when_played`.
+  pub total_first_play_turn: i64,
+  /// Player-games in which the card was used without being in the
+  /// player's starting deck.
+  pub games_generated: u64,
+  /// Times the card was destroyed, counting copies owned by either
+  /// player.
+  pub times_destroyed: u64,
+  pub times_exiled: u64,
+  /// Damage dealt to the enemy fort during the card's Attack Phase
+  /// hook.
+  pub attack_damage: i64,
+  /// Damage dealt to the enemy fort during any other hook of the
+  /// card.
+  pub effect_damage: i64,
+  /// Fort defense restored to the card owner's fort.
+  pub fort_healing: i64,
+}
+
+/// Collects [`CardStats`] from every game played by an engine. Register
+/// it on the engine with
+/// [`GameEngine::add_observer_factory`](super::GameEngine::add_observer_factory).
+#[derive(Debug, Clone, Default)]
+pub struct CardStatsCollector(Arc<Mutex<CardStats>>);
+
+/// Observes a single game on behalf of a [`CardStatsCollector`].
+#[derive(Debug)]
+struct CardStatsObserver {
+  collector: CardStatsCollector,
+  turn: i64,
+  decks: CardGameEnv<Vec<CardId>>,
+  bottom: BTreeMap<CardId, GameCardRecord>,
+  top: BTreeMap<CardId, GameCardRecord>,
+}
+
+/// What happened to one card of one player during a single game.
+#[derive(Debug, Clone, Default)]
+struct GameCardRecord {
+  drawn: bool,
+  plays: u64,
+  first_play_turn: Option<i64>,
+  destroyed: u64,
+  exiled: u64,
+  attack_damage: i64,
+  effect_damage: i64,
+  fort_healing: i64,
+}
+
+impl CardRecord {
+  /// The fraction of player-games won when the card was played,
+  /// counting draws as half a win.
+  pub fn win_rate_when_played(&self) -> Option<f64> {
+    win_rate(&self.when_played)
+  }
+
+  /// The fraction of player-games won when the card was in the deck
+  /// but not played, counting draws as half a win.
+  pub fn win_rate_when_not_played(&self) -> Option<f64> {
+    win_rate(&self.when_not_played)
+  }
+
+  pub fn average_first_play_turn(&self) -> Option<f64> {
+    let games = games(&self.when_played);
+    (games > 0).then(|| self.total_first_play_turn as f64 / games as f64)
+  }
+
+  pub fn fort_damage(&self) -> i64 {
+    self.attack_damage + self.effect_damage
+  }
+}
+
+impl CardStats {
+  /// Logs a table of every card, from most to least played, with
+  /// card names from the codex.
+  pub fn log(&self, codex: &CodexDataFile) {
+    let names = codex.cards.iter().map(|entry| (CardId(entry.id), entry.name.as_str())).collect::<BTreeMap<_, _>>();
+    let name_of = |id: &CardId| names.get(id).copied().unwrap_or("?");
+    let name_width = self.cards.keys().map(|id| name_of(id).len()).max().unwrap_or(0).max(4);
+    let mut cards = self.cards.iter().collect::<Vec<_>>();
+    cards.sort_by(|(a_id, a), (b_id, b)| b.times_played.cmp(&a.times_played).then(a_id.cmp(b_id)));
+
+    tracing::info!("Card statistics over {} game(s):", self.games);
+    tracing::info!("{:name_width$} {:>5} {:>6} {:>6} {:>8} {:>8} {:>10} {:>9} {:>6} {:>7} {:>7} {:>6} {:>9}",
+                   "Card", "ID", "Drawn", "Played", "Win% P", "Win% NP", "First turn", "Destroyed", "Exiled", "Atk dmg", "Eff dmg", "Healed", "Generated");
+    let percent = |rate: Option<f64>| rate.map_or_else(|| String::from("-"), |rate| format!("{:.1}%", rate * 100.0));
+    for (id, record) in cards {
+      let first_turn = record.average_first_play_turn().map_or_else(|| String::from("-"), |turn| format!("{turn:.2}"));
+      tracing::info!("{:name_width$} {:>5} {:>6} {:>6} {:>8} {:>8} {:>10} {:>9} {:>6} {:>7} {:>7} {:>6} {:>9}",
+                     name_of(id),
+                     id,
+                     record.games_drawn,
+                     record.times_played,
+                     percent(record.win_rate_when_played()),
+                     percent(record.win_rate_when_not_played()),
+                     first_turn,
+                     record.times_destroyed,
+                     record.times_exiled,
+                     record.attack_damage,
+                     record.effect_damage,
+                     record.fort_healing,
+                     record.games_generated);
+    }
+  }
+}
+
+impl CardStatsCollector {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// The statistics of every game that has finished so far.
+  pub fn stats(&self) -> CardStats {
+    self.0.lock().unwrap().clone()
+  }
+}
+
+impl GameObserverFactory for CardStatsCollector {
+  fn create_observer(&self) -> Box<dyn GameObserver> {
+    Box::new(CardStatsObserver {
+      collector: self.clone(),
+      turn: -1,
+      decks: CardGameEnv { bottom_deck: Vec::new(), top_deck: Vec::new() },
+      bottom: BTreeMap::new(),
+      top: BTreeMap::new(),
+    })
+  }
+}
+
+impl CardStatsObserver {
+  fn card_record(&mut self, player: GameWinner, card: CardId) -> &mut GameCardRecord {
+    let records = match player {
+      GameWinner::Bottom => &mut self.bottom,
+      GameWinner::Top => &mut self.top,
+    };
+    records.entry(card).or_default()
+  }
+}
+
+impl GameObserver for CardStatsObserver {
+  fn on_game_start(&mut self, decks: &CardGameEnv<&[CardId]>) {
+    self.decks = CardGameEnv { bottom_deck: decks.bottom_deck.to_vec(), top_deck: decks.top_deck.to_vec() };
+  }
+
+  fn on_turn_start(&mut self, turn: i64) {
+    self.turn = turn;
+  }
+
+  fn on_card_action(&mut self, player: GameWinner, action: CardAction, card: &ObservedCard) {
+    let turn = self.turn;
+    let record = self.card_record(player, card.id);
+    match action {
+      CardAction::Draw => {
+        record.drawn = true;
+      }
+      CardAction::Destroy => {
+        record.destroyed += 1;
+      }
+      CardAction::Exile => {
+        record.exiled += 1;
+      }
+      action if action.is_play() => {
+        record.plays += 1;
+        record.first_play_turn.get_or_insert(turn);
+      }
+      _ => {}
+    }
+  }
+
+  fn on_stat_changed(&mut self, change: &StatChange) {
+    let StatChange { stat: Stat::FortDefense, source: Some(source), .. } = change else {
+      return;
+    };
+    let StatSource { hook, card, owner } = source;
+    let delta = change.delta();
+    let record = self.card_record(*owner, card.id);
+    if change.player != *owner && delta < 0 {
+      if *hook == CardHook::OnAttackPhase {
+        record.attack_damage -= delta;
+      } else {
+        record.effect_damage -= delta;
+      }
+    } else if change.player == *owner && delta > 0 {
+      record.fort_healing += delta;
+    }
+  }
+
+  fn on_game_end(&mut self, outcome: Result<&GameOutcome, &GameEngineError>) {
+    // Games that errored are not counted.
+    let Ok(outcome) = outcome else {
+      return;
+    };
+    let mut stats = self.collector.0.lock().unwrap();
+    stats.games += 1;
+    for player in [GameWinner::Bottom, GameWinner::Top] {
+      let (deck, records) = match player {
+        GameWinner::Bottom => (&self.decks.bottom_deck, &self.bottom),
+        GameWinner::Top => (&self.decks.top_deck, &self.top),
+      };
+      let deck_cards = deck.iter().copied().collect::<BTreeSet<_>>();
+      let all_cards = deck_cards.iter().chain(records.keys()).copied().collect::<BTreeSet<_>>();
+      for card in all_cards {
+        let game_record = records.get(&card).cloned().unwrap_or_default();
+        let record = stats.cards.entry(card).or_default();
+        if game_record.drawn {
+          record.games_drawn += 1;
+        }
+        record.times_played += game_record.plays;
+        record.times_destroyed += game_record.destroyed;
+        record.times_exiled += game_record.exiled;
+        record.attack_damage += game_record.attack_damage;
+        record.effect_damage += game_record.effect_damage;
+        record.fort_healing += game_record.fort_healing;
+        if !deck_cards.contains(&card) {
+          record.games_generated += 1;
+        } else if let Some(turn) = game_record.first_play_turn {
+          add_result(&mut record.when_played, player, outcome.result);
+          record.total_first_play_turn += turn;
+        } else {
+          add_result(&mut record.when_not_played, player, outcome.result);
+        }
+      }
+    }
+  }
+}
+
+fn add_result(record: &mut Record, player: GameWinner, result: GameResult) {
+  match result {
+    GameResult::Win(winner) if winner == player => record.wins += 1,
+    GameResult::Win(_) => record.losses += 1,
+    GameResult::Draw { .. } => record.draws += 1,
+  }
+}
+
+fn games(record: &Record) -> u64 {
+  record.wins + record.losses + record.draws
+}
+
+fn win_rate(record: &Record) -> Option<f64> {
+  let games = games(record);
+  (games > 0).then(|| (record.wins as f64 + record.draws as f64 / 2.0) / games as f64)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cardgame::outcome::{EndCondition, PlayerOutcome};
+
+  fn card(id: i64) -> ObservedCard {
+    ObservedCard { id: CardId(id), name: format!("Card {id}") }
+  }
+
+  fn outcome(result: GameResult) -> GameOutcome {
+    GameOutcome {
+      result,
+      turn_count: 3,
+      end_condition: EndCondition::FortDestroyed,
+      bottom: PlayerOutcome::default(),
+      top: PlayerOutcome::default(),
+    }
+  }
+
+  fn fort_change(player: GameWinner, delta: i64, hook: CardHook, owner: GameWinner) -> StatChange {
+    StatChange {
+      player,
+      stat: Stat::FortDefense,
+      card: None,
+      old_value: 20,
+      new_value: 20 + delta,
+      source: Some(StatSource { hook, card: card(1), owner }),
+    }
+  }
+
+  #[test]
+  fn test_card_stats_aggregation() {
+    let collector = CardStatsCollector::new();
+    let bottom_deck = [CardId(1), CardId(2)];
+    let top_deck = [CardId(1), CardId(3)];
+    for result in [GameResult::Win(GameWinner::Bottom), GameResult::Win(GameWinner::Top)] {
+      let mut observer = collector.create_observer();
+      observer.on_game_start(&CardGameEnv { bottom_deck: &bottom_deck, top_deck: &top_deck });
+      observer.on_card_action(GameWinner::Bottom, CardAction::Draw, &card(1));
+      observer.on_turn_start(0);
+      observer.on_card_action(GameWinner::Bottom, CardAction::PlayFromHand, &card(1));
+      observer.on_turn_start(1);
+      observer.on_card_action(GameWinner::Bottom, CardAction::Resurrect, &card(1));
+      observer.on_card_action(GameWinner::Bottom, CardAction::Destroy, &card(1));
+      observer.on_card_action(GameWinner::Top, CardAction::PlayFromNowhere, &card(4));
+      observer.on_stat_changed(&fort_change(GameWinner::Top, -3, CardHook::OnAttackPhase, GameWinner::Bottom));
+      observer.on_stat_changed(&fort_change(GameWinner::Top, -1, CardHook::OnPlay, GameWinner::Bottom));
+      observer.on_stat_changed(&fort_change(GameWinner::Bottom, 2, CardHook::OnPlay, GameWinner::Bottom));
+      // Damage to the card owner's own fort is not counted.
+      observer.on_stat_changed(&fort_change(GameWinner::Bottom, -2, CardHook::OnPlay, GameWinner::Bottom));
+      observer.on_game_end(Ok(&outcome(result)));
+    }
+
+    let stats = collector.stats();
+    assert_eq!(stats.games, 2);
+    let card1 = &stats.cards[&CardId(1)];
+    assert_eq!(card1.games_drawn, 2);
+    assert_eq!(card1.times_played, 4);
+    assert_eq!(card1.times_destroyed, 2);
+    assert_eq!(card1.win_rate_when_played(), Some(0.5));
+    // TOP never played card 1, and won one of two games.
+    assert_eq!(card1.when_not_played, Record { wins: 1, losses: 1, draws: 0 });
+    assert_eq!(card1.average_first_play_turn(), Some(0.0));
+    // Card 4 was never in a deck, so it has no win rates, and is
+    // counted as generated instead.
+    let card4 = &stats.cards[&CardId(4)];
+    assert_eq!(card4.times_played, 2);
+    assert_eq!(card4.win_rate_when_played(), None);
+    assert_eq!(card4.win_rate_when_not_played(), None);
+    assert_eq!(card4.average_first_play_turn(), None);
+    assert_eq!(card4.games_generated, 2);
+    assert_eq!(card1.games_generated, 0);
+    assert_eq!(stats.cards[&CardId(2)].win_rate_when_not_played(), Some(0.5));
+    assert_eq!((card1.attack_damage, card1.effect_damage, card1.fort_healing), (6, 2, 4));
+  }
+}
@@ -1,8 +1,12 @@
-
 //! A game code consists of a random seed (for ChaCha8) and a card
 //! game environment (i.e. the two players' decks). Game codes can be
 //! serialized and deserialized to base64 for easy logging and
 //! reproducibility.
+//!
+//! Version 1 codes record the deck length explicitly, so that games
+//! played under non-default rules can be reproduced. Version 0 codes
+//! (which always hold two decks of [`DECK_SIZE`] cards) are still
+//! accepted when deserializing.
 
 use super::{CardId, CardGameEnv, Deck, DECK_SIZE};
 
@@ -10,11 +14,15 @@ use base64::Engine;
 use base64::prelude::BASE64_STANDARD;
 use thiserror::Error;
 
+const SEED_BYTES: usize = 8;
+
 #[derive(Debug, Clone, Error)]
 #[non_exhaustive]
 pub enum SerializeError {
-  #[error("Bad deck size")]
-  BadDeckSize,
+  #[error("Decks have different sizes: bottom has {bottom}, top has {top}")]
+  MismatchedDeckSizes { bottom: usize, top: usize },
+  #[error("Deck of size {0} is too large for a game code")]
+  DeckTooLarge(usize),
 }
 
 #[derive(Debug, Clone, Error)]
@@ -22,8 +30,8 @@ pub enum SerializeError {
 pub enum DeserializeError {
   #[error("{0}")]
   Base64DecodeError(#[from] base64::DecodeError),
-  #[error("Expected zero byte at beginning")]
-  InvalidVersionByte,
+  #[error("Unknown version byte {0}")]
+  InvalidVersionByte(u8),
   #[error("Bad input length")]
   BadInputLength,
 }
@@ -34,17 +42,19 @@ pub fn serialize_game_code<T: AsRef<[CardId]>>(seed: u64, env: &CardGameEnv<T>)
   // that we exceed 255 at some point. This function shall panic if it
   // encounters an ID above 255, in order to catch that as soon as
   // possible if it happens.
-  if env.bottom_deck.as_ref().len() != DECK_SIZE || env.top_deck.as_ref().len() != DECK_SIZE {
-    return Err(SerializeError::BadDeckSize);
+  let bottom_deck = env.bottom_deck.as_ref();
+  let top_deck = env.top_deck.as_ref();
+  if bottom_deck.len() != top_deck.len() {
+    return Err(SerializeError::MismatchedDeckSizes { bottom: bottom_deck.len(), top: top_deck.len() });
   }
-  let mut bytes = Vec::with_capacity(48);
-  bytes.push(0u8); // Version code, always zero currently
+  let Ok(deck_size) = u8::try_from(bottom_deck.len()) else {
+    return Err(SerializeError::DeckTooLarge(bottom_deck.len()));
+  };
+  let mut bytes = Vec::with_capacity(2 + SEED_BYTES + 2 * bottom_deck.len());
+  bytes.push(1u8); // Version code
   bytes.extend(seed.to_be_bytes());
-  for card_id in env.bottom_deck.as_ref() {
-    assert!((0..=255).contains(&card_id.0), "Card ID {} is out of range", card_id.0);
-    bytes.push(card_id.0 as u8);
-  }
-  for card_id in env.top_deck.as_ref() {
+  bytes.push(deck_size);
+  for card_id in bottom_deck.iter().chain(top_deck) {
     assert!((0..=255).contains(&card_id.0), "Card ID {} is out of range", card_id.0);
     bytes.push(card_id.0 as u8);
   }
@@ -53,15 +63,26 @@ pub fn serialize_game_code<T: AsRef<[CardId]>>(seed: u64, env: &CardGameEnv<T>)
 
 pub fn deserialize_game_code(s: &str) -> Result<(u64, CardGameEnv<Deck>), DeserializeError> {
   let bytes = BASE64_STANDARD.decode(s)?;
-  if bytes.len() != 49 {
+  let (version, rest) = bytes.split_first().ok_or(DeserializeError::BadInputLength)?;
+  if rest.len() < SEED_BYTES {
     return Err(DeserializeError::BadInputLength);
   }
-  if bytes[0] != 0 {
-    return Err(DeserializeError::InvalidVersionByte);
+  let (seed_bytes, rest) = rest.split_at(SEED_BYTES);
+  let seed = u64::from_be_bytes(seed_bytes.try_into().unwrap());
+  let (deck_size, cards) = match version {
+    0 => (DECK_SIZE, rest),
+    1 => {
+      let (deck_size, cards) = rest.split_first().ok_or(DeserializeError::BadInputLength)?;
+      (*deck_size as usize, cards)
+    }
+    _ => { return Err(DeserializeError::InvalidVersionByte(*version)); }
+  };
+  if cards.len() != 2 * deck_size {
+    return Err(DeserializeError::BadInputLength);
   }
-  let seed = u64::from_be_bytes(bytes[1..9].try_into().unwrap());
-  let bottom_deck = bytes[9..29].iter().map(|x| CardId(*x as i64)).collect();
-  let top_deck = bytes[29..49].iter().map(|x| CardId(*x as i64)).collect();
+  let (bottom_deck, top_deck) = cards.split_at(deck_size);
+  let bottom_deck = bottom_deck.iter().map(|x| CardId(*x as i64)).collect();
+  let top_deck = top_deck.iter().map(|x| CardId(*x as i64)).collect();
   Ok((seed, CardGameEnv { bottom_deck, top_deck }))
 }
 
@@ -72,12 +93,43 @@ mod tests {
   #[test]
   fn test_roundtrip_game_code() {
     let example_seed = 123456u64;
-    let bottom_deck = (0..20).map(|x| CardId(x)).collect();
-    let top_deck = (51..71).map(|x| CardId(x)).collect();
+    let bottom_deck = (0..20).map(CardId).collect();
+    let top_deck = (51..71).map(CardId).collect();
     let input_env = CardGameEnv { bottom_deck, top_deck };
     let b64_str = serialize_game_code(example_seed, &input_env).unwrap();
     let (out_seed, out_env) = deserialize_game_code(&b64_str).unwrap();
     assert_eq!(example_seed, out_seed);
     assert_eq!(input_env, out_env);
   }
+
+  #[test]
+  fn test_roundtrip_game_code_nonstandard_size() {
+    let bottom_deck = (0..25).map(CardId).collect();
+    let top_deck = (100..125).map(CardId).collect();
+    let input_env = CardGameEnv { bottom_deck, top_deck };
+    let b64_str = serialize_game_code(99, &input_env).unwrap();
+    let (out_seed, out_env) = deserialize_game_code(&b64_str).unwrap();
+    assert_eq!(out_seed, 99);
+    assert_eq!(out_env, input_env);
+  }
+
+  #[test]
+  fn test_deserialize_version_zero_game_code() {
+    let mut bytes = vec![0u8];
+    bytes.extend(7u64.to_be_bytes());
+    bytes.extend(0..40u8);
+    let (seed, env) = deserialize_game_code(&BASE64_STANDARD.encode(bytes)).unwrap();
+    assert_eq!(seed, 7);
+    assert_eq!(env.bottom_deck.as_ref(), (0..20).map(CardId).collect::<Vec<_>>());
+    assert_eq!(env.top_deck.as_ref(), (20..40).map(CardId).collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn test_serialize_mismatched_decks() {
+    let input_env = CardGameEnv { bottom_deck: vec![CardId(1); 20], top_deck: vec![CardId(1); 19] };
+    assert!(matches!(
+      serialize_game_code(0, &input_env),
+      Err(SerializeError::MismatchedDeckSizes { bottom: 20, top: 19 }),
+    ));
+  }
 }
@@ -4,20 +4,26 @@ pub mod validator;
 pub use validator::DeckValidator;
 
 use thiserror::Error;
+use serde::{Deserialize, Serialize};
 
 use std::str::FromStr;
 use std::num::ParseIntError;
+use std::io::{self, BufRead, BufReader};
+use std::fs::File;
+use std::path::Path;
 use std::fmt::{self, Debug, Display, Formatter};
 use std::ops::{Index, IndexMut};
 
 pub const DECK_SIZE: usize = 20;
 
 /// Newtype wrapper around a vector of [`CardId`].
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct Deck(pub Vec<CardId>);
 
 /// The ID of a playing card.
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct CardId(pub i64);
 
 #[derive(Debug, Clone, Error)]
@@ -27,6 +33,71 @@ pub enum DeckFromStrError {
   ParseIntError(#[from] ParseIntError),
 }
 
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum DeckListError {
+  #[error("{0}")]
+  IoError(#[from] io::Error),
+  #[error("Line {line}: {error}")]
+  ParseError { line: usize, error: DeckFromStrError },
+}
+
+/// A deck together with a human-readable name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedDeck {
+  pub name: String,
+  pub deck: Deck,
+}
+
+/// Reads a list of decks, one per line, in the same comma-separated
+/// format accepted on the command line. Blank lines and lines
+/// beginning with `#` are ignored.
+pub fn read_deck_list<R: BufRead>(reader: R) -> Result<Vec<Deck>, DeckListError> {
+  let mut decks = Vec::new();
+  for (index, line) in reader.lines().enumerate() {
+    let line = line?;
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+    let deck = line.parse().map_err(|error| DeckListError::ParseError { line: index + 1, error })?;
+    decks.push(deck);
+  }
+  Ok(decks)
+}
+
+pub fn read_deck_list_from_path(path: impl AsRef<Path>) -> Result<Vec<Deck>, DeckListError> {
+  let file = File::open(path)?;
+  read_deck_list(BufReader::new(file))
+}
+
+/// Reads a list of decks in the format of [`read_deck_list`], except
+/// that each deck may be preceded by a name and a colon, as in
+/// `Clowns: 1, 2, 3`. Unnamed decks are named after their position in
+/// the list (`Deck 1`, `Deck 2`, and so on).
+pub fn read_named_deck_list<R: BufRead>(reader: R) -> Result<Vec<NamedDeck>, DeckListError> {
+  let mut decks = Vec::new();
+  for (index, line) in reader.lines().enumerate() {
+    let line = line?;
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+    let (name, cards) = match line.split_once(':') {
+      Some((name, cards)) => (name.trim().to_owned(), cards),
+      None => (format!("Deck {}", decks.len() + 1), line),
+    };
+    let deck = cards.parse().map_err(|error| DeckListError::ParseError { line: index + 1, error })?;
+    decks.push(NamedDeck { name, deck });
+  }
+  Ok(decks)
+}
+
+pub fn read_named_deck_list_from_path(path: impl AsRef<Path>) -> Result<Vec<NamedDeck>, DeckListError> {
+  let file = File::open(path)?;
+  read_named_deck_list(BufReader::new(file))
+}
+
 impl Deck {
   pub fn is_empty(&self) -> bool {
     self.0.is_empty()
@@ -130,4 +201,19 @@ mod tests {
   fn test_deck_from_str_invalid() {
     "a".parse::<Deck>().unwrap_err();
   }
+
+  #[test]
+  fn test_read_deck_list() {
+    let decks = read_deck_list("# Character decks\n1, 2, 3\n\n4, 5\n".as_bytes()).unwrap();
+    assert_eq!(decks, vec![Deck(vec![CardId(1), CardId(2), CardId(3)]), Deck(vec![CardId(4), CardId(5)])]);
+  }
+
+  #[test]
+  fn test_read_named_deck_list() {
+    let decks = read_named_deck_list("# Tournament\nClowns: 1, 2, 3\n4, 5\n".as_bytes()).unwrap();
+    assert_eq!(decks, vec![
+      NamedDeck { name: String::from("Clowns"), deck: Deck(vec![CardId(1), CardId(2), CardId(3)]) },
+      NamedDeck { name: String::from("Deck 2"), deck: Deck(vec![CardId(4), CardId(5)]) },
+    ]);
+  }
 }
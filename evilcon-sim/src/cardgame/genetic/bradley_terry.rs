@@ -1,91 +1,293 @@
 
-//! Bradley-Terry model.
+//! Bradley-Terry model, with a home advantage term for the BOTTOM
+//! seat.
+//!
+//! The model is fit with the minorization-maximization (MM) algorithm
+//! of Hunter (2004), "MM algorithms for generalized Bradley-Terry
+//! models".
+
+use crate::cardgame::GameWinner;
 
 use std::ops::{Index, IndexMut};
 
-pub(super) const BRADLEY_TERRY_ITERS: usize = 1_000;
-pub(super) const LEARNING_RATE: f64 = 0.1;
+/// Maximum number of MM iterations before giving up on convergence.
+pub(super) const MAX_ITERS: usize = 10_000;
+/// The fit has converged when no score changes by more than this
+/// amount in an iteration.
+pub(super) const TOLERANCE: f64 = 1e-6;
+/// Number of virtual games each deck plays against a fixed opponent
+/// of score zero, half of which it wins. This prior keeps the scores
+/// of decks that never won (or never lost) finite, ties together
+/// decks that share no opponents, and anchors the scale. The seat
+/// advantage gets the same number of virtual games between two decks
+/// of score zero.
+pub(super) const PRIOR_GAMES: f64 = 1.0;
 
-/// Matrix of win records, as a square matrix. Should be stored in
-/// row-major format, so that an entry `(x, y)` (meaning "how many
-/// times did `x` beat `y`) is at `y * width + x`.
+/// Matrix of win records, as a square matrix for each seat. Should
+/// be stored in row-major format, so that an entry `(x, y, seat)`
+/// (meaning "how many times did `x`, seated at `seat`, beat `y`") is
+/// at `y * width + x` of that seat's matrix.
+///
+/// Entries are fractional so that a draw can be scored as half of a
+/// win for each player.
 ///
 /// This is a pure data structure; no preconditions are validated. In
-/// principle, it should be a square matrix of size `width * width`.
+/// principle, each matrix should be a square matrix of size `width *
+/// width`.
 #[derive(Debug, Clone)]
-pub(super) struct WinMatrix {
+pub(crate) struct WinMatrix {
   pub width: usize,
-  pub data: Vec<u64>,
+  pub bottom_data: Vec<f64>,
+  pub top_data: Vec<f64>,
 }
 
 impl WinMatrix {
-  pub(super) fn zeroes(width: usize) -> Self {
+  pub(crate) fn zeroes(width: usize) -> Self {
     Self {
       width,
-      data: vec![0; width * width],
+      bottom_data: vec![0.0; width * width],
+      top_data: vec![0.0; width * width],
     }
   }
-}
 
-impl Index<(usize, usize)> for WinMatrix {
-  type Output = u64;
+  /// Number of games played with `bottom` seated at BOTTOM and `top`
+  /// seated at TOP.
+  pub(crate) fn games(&self, bottom: usize, top: usize) -> f64 {
+    self[(bottom, top, GameWinner::Bottom)] + self[(top, bottom, GameWinner::Top)]
+  }
 
-  fn index(&self, (x, y): (usize, usize)) -> &u64 {
-    &self.data[y * self.width + x]
+  /// Total number of wins by `x` over `y`, from either seat.
+  pub(crate) fn wins(&self, x: usize, y: usize) -> f64 {
+    self[(x, y, GameWinner::Bottom)] + self[(x, y, GameWinner::Top)]
   }
 }
 
-impl IndexMut<(usize, usize)> for WinMatrix {
-  fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut u64 {
-    &mut self.data[y * self.width + x]
+impl Index<(usize, usize, GameWinner)> for WinMatrix {
+  type Output = f64;
+
+  fn index(&self, (x, y, seat): (usize, usize, GameWinner)) -> &f64 {
+    match seat {
+      GameWinner::Bottom => &self.bottom_data[y * self.width + x],
+      GameWinner::Top => &self.top_data[y * self.width + x],
+    }
   }
 }
 
-/// Compute scores given the result of random matchups.
-pub(super) fn compute_scores(wins: &WinMatrix) -> Vec<f64> {
-  let mut scores = vec![0.0; wins.width];
-  for _ in 0..BRADLEY_TERRY_ITERS {
-    let gradients = compute_gradients(&scores, wins);
-    for i in 0..wins.width {
-      scores[i] += LEARNING_RATE * gradients[i];
+impl IndexMut<(usize, usize, GameWinner)> for WinMatrix {
+  fn index_mut(&mut self, (x, y, seat): (usize, usize, GameWinner)) -> &mut f64 {
+    match seat {
+      GameWinner::Bottom => &mut self.bottom_data[y * self.width + x],
+      GameWinner::Top => &mut self.top_data[y * self.width + x],
     }
   }
-  scores
 }
 
-/// Gradients for Bradley-Terry model function `P(i > j) = e^bi /
-/// (e^bi + e^bj)`
-pub(super) fn compute_gradients(scores: &[f64], wins: &WinMatrix) -> Vec<f64> {
+/// The result of fitting the Bradley-Terry model.
+#[derive(Debug, Clone)]
+pub(crate) struct BradleyTerryFit {
+  /// The log-strength of each player. The probability that `i`,
+  /// seated at BOTTOM, beats `j` is `e^(si + h) / (e^(si + h) +
+  /// e^sj)`, where `h` is the seat advantage.
+  pub scores: Vec<f64>,
+  /// Asymptotic standard error of each score.
+  pub standard_errors: Vec<f64>,
+  /// The log-odds advantage of the BOTTOM seat over the TOP seat. A
+  /// negative value means the TOP seat is favored.
+  pub seat_advantage: f64,
+  pub seat_advantage_standard_error: f64,
+  pub iterations: usize,
+  pub converged: bool,
+}
+
+/// Fits the Bradley-Terry model to the result of random matchups.
+pub(crate) fn fit(wins: &WinMatrix) -> BradleyTerryFit {
   let width = wins.width;
-  assert!(width == scores.len());
+  // Total wins of each player, including half of its prior games.
+  let total_wins = (0..width)
+    .map(|i| (0..width).filter(|&j| j != i).map(|j| wins.wins(i, j)).sum::<f64>() + PRIOR_GAMES / 2.0)
+    .collect::<Vec<_>>();
+  let total_bottom_wins = (0..width)
+    .flat_map(|i| (0..width).map(move |j| (i, j)))
+    .filter(|(i, j)| i != j)
+    .map(|(i, j)| wins[(i, j, GameWinner::Bottom)])
+    .sum::<f64>() + PRIOR_GAMES / 2.0;
 
-  let mut grads = vec![0.0; width];
-  for i in 0..width {
-    for j in 0..width {
+  let mut strengths = vec![1.0; width];
+  let mut advantage = 1.0;
+  let mut iterations = 0;
+  let mut converged = false;
+  while iterations < MAX_ITERS && !converged {
+    iterations += 1;
+    let new_strengths = (0..width)
+      .map(|i| {
+        let denominator = (0..width)
+          .filter(|&j| j != i)
+          .map(|j| {
+            wins.games(i, j) * advantage / (advantage * strengths[i] + strengths[j]) +
+              wins.games(j, i) / (advantage * strengths[j] + strengths[i])
+          })
+          .sum::<f64>() + PRIOR_GAMES / (strengths[i] + 1.0);
+        total_wins[i] / denominator
+      })
+      .collect::<Vec<_>>();
+    let advantage_denominator = (0..width)
+      .flat_map(|i| (0..width).map(move |j| (i, j)))
+      .filter(|(i, j)| i != j)
+      .map(|(i, j)| wins.games(i, j) * new_strengths[i] / (advantage * new_strengths[i] + new_strengths[j]))
+      .sum::<f64>() + PRIOR_GAMES / (advantage + 1.0);
+    let new_advantage = total_bottom_wins / advantage_denominator;
+    converged = strengths.iter()
+      .zip(&new_strengths)
+      .chain([(&advantage, &new_advantage)])
+      .all(|(old, new)| (new.ln() - old.ln()).abs() < TOLERANCE);
+    strengths = new_strengths;
+    advantage = new_advantage;
+  }
+
+  let scores = strengths.iter().map(|s| s.ln()).collect::<Vec<_>>();
+  let seat_advantage = advantage.ln();
+  let mut standard_errors = standard_errors(&scores, seat_advantage, wins);
+  let seat_advantage_standard_error = standard_errors.pop().unwrap_or(f64::INFINITY);
+  BradleyTerryFit { scores, standard_errors, seat_advantage, seat_advantage_standard_error, iterations, converged }
+}
+
+/// Standard errors of the scores, followed by the standard error of
+/// the seat advantage, from the inverse of the Fisher information
+/// matrix (including the prior games).
+fn standard_errors(scores: &[f64], seat_advantage: f64, wins: &WinMatrix) -> Vec<f64> {
+  let width = scores.len() + 1;
+  let h = scores.len();
+  let mut information = vec![0.0; width * width];
+  for i in 0..scores.len() {
+    let p = logistic(scores[i]);
+    information[i * width + i] += PRIOR_GAMES * p * (1.0 - p);
+    for j in 0..scores.len() {
       if i == j {
         continue;
       }
+      // Games with i at BOTTOM and j at TOP. The log-odds of a BOTTOM
+      // win are `si + h - sj`.
+      let p = logistic(scores[i] + seat_advantage - scores[j]);
+      let weight = wins.games(i, j) * p * (1.0 - p);
+      for (a, b, sign) in [(i, i, 1.0), (j, j, 1.0), (h, h, 1.0), (i, j, -1.0), (j, i, -1.0), (i, h, 1.0), (h, i, 1.0), (j, h, -1.0), (h, j, -1.0)] {
+        information[a * width + b] += sign * weight;
+      }
+    }
+  }
+  let p = logistic(seat_advantage);
+  information[h * width + h] += PRIOR_GAMES * p * (1.0 - p);
 
-      let s_i = scores[i];
-      let s_j = scores[j];
-      let p_ij = (s_i - s_j).exp() / (1.0 + (s_i - s_j).exp()); // logistic (sigmoid)
+  let Some(lower) = cholesky(&information, width) else {
+    return vec![f64::INFINITY; width];
+  };
+  (0..width)
+    .map(|i| {
+      let mut unit = vec![0.0; width];
+      unit[i] = 1.0;
+      let column = cholesky_solve(&lower, width, unit);
+      column[i].sqrt()
+    })
+    .collect()
+}
 
-      let w_ij = wins[(i, j)] as f64;
-      let w_ji = wins[(j, i)] as f64;
-      let total = w_ij + w_ji;
+fn logistic(x: f64) -> f64 {
+  1.0 / (1.0 + (-x).exp())
+}
 
-      if total == 0.0 {
-        // No data; skip.
-        continue;
+/// Cholesky decomposition of a symmetric positive-definite matrix, in
+/// row-major order. Returns the lower triangular factor, or `None` if
+/// the matrix is not positive definite.
+fn cholesky(matrix: &[f64], width: usize) -> Option<Vec<f64>> {
+  let mut lower = vec![0.0; width * width];
+  for i in 0..width {
+    for j in 0..=i {
+      let sum = (0..j).map(|k| lower[i * width + k] * lower[j * width + k]).sum::<f64>();
+      if i == j {
+        let diagonal = matrix[i * width + i] - sum;
+        if diagonal <= 0.0 {
+          return None;
+        }
+        lower[i * width + i] = diagonal.sqrt();
+      } else {
+        lower[i * width + j] = (matrix[i * width + j] - sum) / lower[j * width + j];
       }
+    }
+  }
+  Some(lower)
+}
 
-      let observed = w_ij / total;
-      let error = observed - p_ij;
+/// Solves `L L^T x = b`, given the Cholesky factor `L`.
+fn cholesky_solve(lower: &[f64], width: usize, mut b: Vec<f64>) -> Vec<f64> {
+  for i in 0..width {
+    let sum = (0..i).map(|k| lower[i * width + k] * b[k]).sum::<f64>();
+    b[i] = (b[i] - sum) / lower[i * width + i];
+  }
+  for i in (0..width).rev() {
+    let sum = ((i + 1)..width).map(|k| lower[k * width + i] * b[k]).sum::<f64>();
+    b[i] = (b[i] - sum) / lower[i * width + i];
+  }
+  b
+}
 
-      // Derivative of sigmoid is `p * (1 - p)`
-      let grad = error * p_ij * (1.0 - p_ij);
-      grads[i] += grad;
-    }
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Records `count` wins of `x` over `y`, split evenly between the
+  /// two seats.
+  fn record_wins(wins: &mut WinMatrix, x: usize, y: usize, count: f64) {
+    wins[(x, y, GameWinner::Bottom)] += count / 2.0;
+    wins[(x, y, GameWinner::Top)] += count / 2.0;
+  }
+
+  #[test]
+  fn test_fit_orders_players() {
+    let mut wins = WinMatrix::zeroes(3);
+    record_wins(&mut wins, 0, 1, 8.0);
+    record_wins(&mut wins, 1, 0, 2.0);
+    record_wins(&mut wins, 1, 2, 8.0);
+    record_wins(&mut wins, 2, 1, 2.0);
+    let fit = fit(&wins);
+    assert!(fit.converged);
+    assert!(fit.scores[0] > fit.scores[1]);
+    assert!(fit.scores[1] > fit.scores[2]);
+    assert!(fit.seat_advantage.abs() < 1e-3);
+  }
+
+  #[test]
+  fn test_fit_stays_finite_without_losses() {
+    let mut wins = WinMatrix::zeroes(3);
+    record_wins(&mut wins, 0, 1, 6.0);
+    record_wins(&mut wins, 0, 2, 6.0);
+    let fit = fit(&wins);
+    assert!(fit.converged);
+    assert!(fit.scores.iter().all(|s| s.is_finite()));
+    assert!(fit.standard_errors.iter().all(|se| se.is_finite() && *se > 0.0));
+  }
+
+  #[test]
+  fn test_fewer_games_give_larger_standard_errors() {
+    let mut wins = WinMatrix::zeroes(3);
+    record_wins(&mut wins, 0, 1, 10.0);
+    record_wins(&mut wins, 1, 0, 10.0);
+    record_wins(&mut wins, 2, 0, 1.0);
+    record_wins(&mut wins, 0, 2, 1.0);
+    let fit = fit(&wins);
+    assert!(fit.standard_errors[2] > fit.standard_errors[1]);
+  }
+
+  #[test]
+  fn test_fit_seat_advantage() {
+    // Two equal decks; the BOTTOM seat wins three games in four.
+    let mut wins = WinMatrix::zeroes(2);
+    wins[(0, 1, GameWinner::Bottom)] = 30.0;
+    wins[(1, 0, GameWinner::Top)] = 10.0;
+    wins[(1, 0, GameWinner::Bottom)] = 30.0;
+    wins[(0, 1, GameWinner::Top)] = 10.0;
+    let fit = fit(&wins);
+    assert!(fit.converged);
+    assert!((fit.scores[0] - fit.scores[1]).abs() < 1e-3);
+    assert!((fit.seat_advantage - 3f64.ln()).abs() < 0.1);
+    assert!(fit.seat_advantage_standard_error.is_finite());
   }
-  grads
 }
@@ -0,0 +1,124 @@
+//! Checkpoints of a genetic algorithm run, so that a long run can be
+//! resumed after it is interrupted.
+
+use super::GeneticAlgorithmArgs;
+use super::constraints::DeckConstraints;
+use crate::cardgame::{Deck, GameRules};
+use crate::cardgame::yaml_file::YamlFile;
+
+use itertools::Itertools;
+use rand_chacha::ChaCha8Rng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+/// The full state of a genetic algorithm run after some number of
+/// generations have completed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+  /// The number of generations that have completed.
+  pub completed_generations: usize,
+  pub args: GeneticAlgorithmArgs,
+  pub rules: GameRules,
+  /// The deck constraints, as loaded when the run began. Later edits
+  /// to the constraints file do not affect a resumed run.
+  #[serde(default)]
+  pub constraints: DeckConstraints,
+  /// The gauntlet decks, as loaded when the run began. Later edits to
+  /// the gauntlet file do not affect a resumed run.
+  #[serde(default)]
+  pub gauntlet: Vec<Deck>,
+  /// The decks of the last completed generation, together with their
+  /// scores, from highest to lowest score.
+  pub ranked_decks: Vec<RankedDeck>,
+  /// The pool of decks for the next generation.
+  pub pool: Vec<Deck>,
+  pub random: RngState,
+  pub matchup_random: RngState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankedDeck {
+  pub deck: Deck,
+  pub score: f64,
+}
+
+/// Serializable position of a ChaCha8 random number generator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RngState {
+  seed: [u8; 32],
+  stream: u64,
+  word_pos: u128,
+}
+
+impl YamlFile for Checkpoint {}
+
+impl Checkpoint {
+  /// The settings (as `args.<name>`, `rules.<name>`, or
+  /// `constraints.<name>`) for which the given values differ from
+  /// those stored in this checkpoint.
+  pub fn differing_settings(&self, args: &GeneticAlgorithmArgs, rules: &GameRules, constraints: &DeckConstraints) -> Vec<String> {
+    let mut differences = differing_fields("args", &self.args, args);
+    differences.extend(differing_fields("rules", &self.rules, rules));
+    differences.extend(differing_fields("constraints", &self.constraints, constraints));
+    differences
+  }
+}
+
+/// Compares two values field by field, via their serialized forms.
+fn differing_fields<T: Serialize>(prefix: &str, a: &T, b: &T) -> Vec<String> {
+  let (Ok(serde_yaml::Value::Mapping(a)), Ok(serde_yaml::Value::Mapping(b))) = (serde_yaml::to_value(a), serde_yaml::to_value(b)) else {
+    return vec![prefix.to_owned()];
+  };
+  a.keys().chain(b.keys())
+    .filter(|key| a.get(key) != b.get(key))
+    .map(|key| format!("{prefix}.{}", key.as_str().unwrap_or("?")))
+    .unique()
+    .collect()
+}
+
+impl From<&ChaCha8Rng> for RngState {
+  fn from(rng: &ChaCha8Rng) -> Self {
+    RngState {
+      seed: rng.get_seed(),
+      stream: rng.get_stream(),
+      word_pos: rng.get_word_pos(),
+    }
+  }
+}
+
+impl From<&RngState> for ChaCha8Rng {
+  fn from(state: &RngState) -> Self {
+    let mut rng = ChaCha8Rng::from_seed(state.seed);
+    rng.set_stream(state.stream);
+    rng.set_word_pos(state.word_pos);
+    rng
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use rand::Rng;
+
+  #[test]
+  fn test_differing_fields() {
+    let a = GameRules::default();
+    let b = GameRules { turn_limit: a.turn_limit + 1, ..GameRules::default() };
+    assert_eq!(differing_fields("rules", &a, &a), Vec::<String>::new());
+    assert_eq!(differing_fields("rules", &a, &b), vec!["rules.turn_limit"]);
+    let constraints = DeckConstraints { banned: vec![crate::cardgame::CardId(3)], ..DeckConstraints::default() };
+    assert_eq!(differing_fields("constraints", &DeckConstraints::default(), &constraints), vec!["constraints.banned"]);
+  }
+
+  #[test]
+  fn test_rng_state_roundtrip() {
+    let mut rng = ChaCha8Rng::seed_from_u64(10);
+    rng.set_stream(1);
+    let _: [u64; 3] = rng.random();
+    let state = RngState::from(&rng);
+    let state: RngState = serde_yaml::from_str(&serde_yaml::to_string(&state).unwrap()).unwrap();
+    let mut restored = ChaCha8Rng::from(&state);
+    assert_eq!(rng.random::<u64>(), restored.random::<u64>());
+  }
+}
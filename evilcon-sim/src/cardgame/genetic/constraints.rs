@@ -0,0 +1,314 @@
+//! Constraints on the decks that the genetic algorithm may consider.
+
+use crate::cardgame::CardId;
+use crate::cardgame::card_pool::{CardInfo, CardPool};
+use crate::cardgame::yaml_file::{YamlFile, YamlFileError};
+use crate::interpreter::mocking::codex::Rarity;
+
+use itertools::Itertools;
+use rand::Rng;
+use rand::seq::IndexedRandom;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Deck constraints, as loaded from a YAML file. Every field is
+/// optional.
+///
+/// If neither `allowed_ids` nor `allowed_archetypes` is given, every
+/// card is allowed. Otherwise, a card is allowed if its ID is listed
+/// in `allowed_ids` or if it is a Minion with one of the
+/// `allowed_archetypes`. Effect cards have no archetypes, so
+/// `allowed_archetypes` does not exclude them. Locked cards are
+/// always allowed, and banned cards never are.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeckConstraints {
+  /// Cards that must appear in every deck. A card listed twice must
+  /// appear at least twice.
+  pub locked: Vec<CardId>,
+  pub banned: Vec<CardId>,
+  pub allowed_ids: Vec<CardId>,
+  /// Archetype names, as in the GDScript `Archetype` class (such as
+  /// `CLOWN`).
+  pub allowed_archetypes: Vec<String>,
+  /// Maximum number of cards of each rarity in a deck.
+  pub rarity_caps: BTreeMap<Rarity, usize>,
+}
+
+/// Deck constraints which have been checked against the card pool.
+#[derive(Debug, Clone)]
+pub struct ResolvedConstraints {
+  locked: Vec<CardId>,
+  locked_counts: HashMap<CardId, usize>,
+  /// Every card which may appear in a deck, other than by being
+  /// locked.
+  allowed: Vec<CardId>,
+  allowed_set: HashSet<CardId>,
+  rarities: HashMap<CardId, Rarity>,
+  rarity_caps: BTreeMap<Rarity, usize>,
+  /// The most copies of each card allowed in a deck.
+  copy_limits: HashMap<CardId, usize>,
+}
+
+#[derive(Debug, Error)]
+pub enum ConstraintsError {
+  #[error("{0}")]
+  FileError(#[from] YamlFileError),
+  #[error("Unknown card ID {0} in constraints")]
+  UnknownCard(CardId),
+  #[error("Unknown archetype {0} in constraints")]
+  UnknownArchetype(String),
+  #[error("Card {0} is both locked and banned")]
+  LockedAndBanned(CardId),
+  #[error("Locked cards exceed the deck size of {0}")]
+  TooManyLocked(usize),
+  #[error("Locked cards exceed the cap on {0:?} cards")]
+  LockedExceedsRarityCap(Rarity),
+  #[error("Card {0} is locked more times than its copy limit")]
+  LockedExceedsCopyLimit(CardId),
+  #[error("Constraints do not allow any cards")]
+  NoAllowedCards,
+  #[error("Constraints allow at most {max_cards} cards in a deck, but decks have {deck_size} cards")]
+  DeckSizeUnreachable { deck_size: usize, max_cards: usize },
+}
+
+impl YamlFile for DeckConstraints {}
+
+impl DeckConstraints {
+  pub fn resolve(&self, card_pool: &CardPool, deck_size: usize) -> Result<ResolvedConstraints, ConstraintsError> {
+    for &id in self.locked.iter().chain(&self.banned).chain(&self.allowed_ids) {
+      if card_pool.get(id).is_none() {
+        return Err(ConstraintsError::UnknownCard(id));
+      }
+    }
+    let allowed_archetypes = self.allowed_archetypes.iter()
+      .map(|name| card_pool.archetype_id(name).ok_or_else(|| ConstraintsError::UnknownArchetype(name.to_owned())))
+      .collect::<Result<HashSet<_>, _>>()?;
+    if let Some(&id) = self.locked.iter().find(|id| self.banned.contains(id)) {
+      return Err(ConstraintsError::LockedAndBanned(id));
+    }
+    if self.locked.len() > deck_size {
+      return Err(ConstraintsError::TooManyLocked(deck_size));
+    }
+
+    let allowed = card_pool.cards()
+      .filter(|card| self.is_allowed(card, &allowed_archetypes))
+      .map(|card| card.id)
+      .collect::<Vec<_>>();
+    if allowed.is_empty() && self.locked.len() < deck_size {
+      return Err(ConstraintsError::NoAllowedCards);
+    }
+
+    let rarities = card_pool.cards().map(|card| (card.id, card.rarity)).collect::<HashMap<_, _>>();
+    let locked_rarities = self.locked.iter().map(|id| rarities[id]).counts();
+    for (rarity, cap) in &self.rarity_caps {
+      if locked_rarities.get(rarity).copied().unwrap_or(0) > *cap {
+        return Err(ConstraintsError::LockedExceedsRarityCap(*rarity));
+      }
+    }
+
+    let copy_limits = card_pool.cards().map(|card| (card.id, card.copy_limit())).collect::<HashMap<_, _>>();
+    let locked_counts = self.locked.iter().copied().counts();
+    if let Some((&id, _)) = locked_counts.iter().find(|(id, count)| **count > copy_limits[id]) {
+      return Err(ConstraintsError::LockedExceedsCopyLimit(id));
+    }
+
+    let constraints = ResolvedConstraints {
+      locked: self.locked.clone(),
+      locked_counts,
+      allowed_set: allowed.iter().copied().collect(),
+      allowed,
+      rarities,
+      rarity_caps: self.rarity_caps.clone(),
+      copy_limits,
+    };
+    let max_cards = constraints.max_deck_size();
+    if max_cards < deck_size {
+      return Err(ConstraintsError::DeckSizeUnreachable { deck_size, max_cards });
+    }
+    Ok(constraints)
+  }
+
+  /// Whether `card` may appear in a deck other than by being locked,
+  /// given the resolved IDs of `allowed_archetypes`.
+  fn is_allowed(&self, card: &CardInfo, allowed_archetypes: &HashSet<i64>) -> bool {
+    if self.banned.contains(&card.id) {
+      return false;
+    }
+    let unrestricted = self.allowed_ids.is_empty() && self.allowed_archetypes.is_empty();
+    unrestricted ||
+      self.allowed_ids.contains(&card.id) ||
+      (!allowed_archetypes.is_empty() && !card.is_minion) ||
+      card.archetypes.iter().any(|archetype| allowed_archetypes.contains(archetype))
+  }
+}
+
+impl ResolvedConstraints {
+  /// Cards that must appear in every deck.
+  pub fn locked(&self) -> &[CardId] {
+    &self.locked
+  }
+
+  /// Cards which may be freely chosen for the non-locked portion of a
+  /// deck.
+  pub fn allowed(&self) -> &[CardId] {
+    &self.allowed
+  }
+
+  /// The size of the largest deck satisfying these constraints: the
+  /// locked cards, plus every remaining copy of each allowed card,
+  /// with each rarity held to its cap.
+  pub fn max_deck_size(&self) -> usize {
+    let locked_rarity_counts = self.locked.iter().map(|id| self.rarities[id]).counts();
+    let mut free_copies = HashMap::<Rarity, usize>::new();
+    for id in &self.allowed {
+      let locked_count = self.locked_counts.get(id).copied().unwrap_or(0);
+      *free_copies.entry(self.rarities[id]).or_default() += self.copy_limits[id].saturating_sub(locked_count);
+    }
+    let free_cards = free_copies.into_iter()
+      .map(|(rarity, copies)| {
+        match self.rarity_caps.get(&rarity) {
+          Some(cap) => copies.min(cap.saturating_sub(locked_rarity_counts.get(&rarity).copied().unwrap_or(0))),
+          None => copies,
+        }
+      })
+      .sum::<usize>();
+    self.locked.len() + free_cards
+  }
+
+  /// Draws a random deck of `deck_size` cards: the locked cards,
+  /// followed by allowed cards chosen among those which have not yet
+  /// reached their copy limit or the cap on their rarity. Returns
+  /// `None` if the deck cannot be filled, which never happens when
+  /// `deck_size` is at most [`max_deck_size`](Self::max_deck_size).
+  pub fn random_deck<R: Rng + ?Sized>(&self, deck_size: usize, random: &mut R) -> Option<Vec<CardId>> {
+    let mut deck = Vec::with_capacity(deck_size);
+    deck.extend_from_slice(&self.locked);
+    let mut counts = self.locked.iter().copied().counts();
+    let mut rarity_counts = self.locked.iter().map(|id| self.rarities[id]).counts();
+    while deck.len() < deck_size {
+      let candidates = self.allowed.iter()
+        .copied()
+        .filter(|id| counts.get(id).copied().unwrap_or(0) < self.copy_limits[id])
+        .filter(|id| {
+          let rarity = self.rarities[id];
+          self.rarity_caps.get(&rarity).is_none_or(|cap| rarity_counts.get(&rarity).copied().unwrap_or(0) < *cap)
+        })
+        .collect::<Vec<_>>();
+      let &id = candidates.choose(random)?;
+      deck.push(id);
+      *counts.entry(id).or_default() += 1;
+      *rarity_counts.entry(self.rarities[&id]).or_default() += 1;
+    }
+    Some(deck)
+  }
+
+  /// The cards in the deck that are not accounted for by locked
+  /// cards, in their original order.
+  pub fn free_cards(&self, deck: &[CardId]) -> Vec<CardId> {
+    let mut remaining_locked = self.locked_counts.clone();
+    deck.iter()
+      .copied()
+      .filter(|id| {
+        match remaining_locked.get_mut(id) {
+          Some(count) if *count > 0 => {
+            *count -= 1;
+            false
+          }
+          _ => true,
+        }
+      })
+      .collect()
+  }
+
+  pub fn is_satisfied_by(&self, deck: &[CardId]) -> bool {
+    let counts = deck.iter().copied().counts();
+    let all_locked_present = self.locked_counts.iter()
+      .all(|(id, locked_count)| counts.get(id).copied().unwrap_or(0) >= *locked_count);
+    if !all_locked_present {
+      return false;
+    }
+    let all_allowed = self.free_cards(deck).iter().all(|id| self.allowed_set.contains(id));
+    if !all_allowed {
+      return false;
+    }
+    let rarity_counts = deck.iter().filter_map(|id| self.rarities.get(id)).counts();
+    self.rarity_caps.iter()
+      .all(|(rarity, cap)| rarity_counts.get(rarity).copied().unwrap_or(0) <= *cap)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use rand::SeedableRng;
+  use rand_chacha::ChaCha8Rng;
+
+  /// Five cards: three Commons (one of them limited), a Rare, and an
+  /// Ultra Rare.
+  fn sample_constraints(locked: Vec<CardId>, rarity_caps: BTreeMap<Rarity, usize>) -> ResolvedConstraints {
+    let cards = [
+      (CardId(1), Rarity::Common, 3),
+      (CardId(2), Rarity::Common, 3),
+      (CardId(3), Rarity::Common, 1),
+      (CardId(4), Rarity::Rare, 3),
+      (CardId(5), Rarity::UltraRare, 1),
+    ];
+    let allowed = cards.iter().map(|(id, _, _)| *id).collect::<Vec<_>>();
+    ResolvedConstraints {
+      locked_counts: locked.iter().copied().counts(),
+      locked,
+      allowed_set: allowed.iter().copied().collect(),
+      allowed,
+      rarities: cards.iter().map(|(id, rarity, _)| (*id, *rarity)).collect(),
+      rarity_caps,
+      copy_limits: cards.iter().map(|(id, _, limit)| (*id, *limit)).collect(),
+    }
+  }
+
+  #[test]
+  fn test_max_deck_size() {
+    assert_eq!(sample_constraints(Vec::new(), BTreeMap::new()).max_deck_size(), 11);
+    assert_eq!(sample_constraints(vec![CardId(1), CardId(4)], BTreeMap::new()).max_deck_size(), 11);
+    let caps = BTreeMap::from([(Rarity::Common, 4), (Rarity::UltraRare, 0)]);
+    assert_eq!(sample_constraints(Vec::new(), caps.clone()).max_deck_size(), 7);
+    assert_eq!(sample_constraints(vec![CardId(3)], caps).max_deck_size(), 7);
+  }
+
+  #[test]
+  fn test_random_deck_respects_limits() {
+    let caps = BTreeMap::from([(Rarity::Common, 4)]);
+    let constraints = sample_constraints(vec![CardId(5)], caps);
+    let mut random = ChaCha8Rng::seed_from_u64(0);
+    for _ in 0..20 {
+      let deck = constraints.random_deck(8, &mut random).unwrap();
+      assert_eq!(deck.len(), 8);
+      assert!(constraints.is_satisfied_by(&deck));
+      let counts = deck.iter().copied().counts();
+      assert!(counts.iter().all(|(id, count)| *count <= constraints.copy_limits[id]));
+    }
+    assert!(constraints.random_deck(9, &mut random).is_none());
+  }
+
+  #[test]
+  fn test_allowed_archetypes_admit_only_effects_without_archetypes() {
+    let card = |id, is_minion, archetypes| CardInfo {
+      id: CardId(id),
+      name: format!("Card {id}"),
+      rarity: Rarity::Common,
+      limited: false,
+      star_cost: 1,
+      is_minion,
+      archetypes,
+    };
+    let constraints = DeckConstraints { allowed_archetypes: vec![String::from("CLOWN")], ..DeckConstraints::default() };
+    let allowed_archetypes = HashSet::from([1]);
+    assert!(constraints.is_allowed(&card(1, true, vec![1, 2]), &allowed_archetypes));
+    assert!(!constraints.is_allowed(&card(2, true, vec![2]), &allowed_archetypes));
+    assert!(!constraints.is_allowed(&card(3, true, Vec::new()), &allowed_archetypes));
+    assert!(constraints.is_allowed(&card(4, false, Vec::new()), &allowed_archetypes));
+  }
+}
@@ -0,0 +1,164 @@
+//! Measures of how varied a generation of decks is, and fitness
+//! sharing to keep it varied.
+
+use super::operators::card_counts;
+use crate::cardgame::{CardId, Deck};
+use crate::cardgame::card_pool::CardPool;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Number of the most common cards to include in the log.
+const LOGGED_CARD_FREQUENCIES: usize = 10;
+
+/// Distances between every pair of decks in a generation.
+#[derive(Debug, Clone)]
+pub(super) struct DistanceMatrix {
+  width: usize,
+  data: Vec<usize>,
+}
+
+/// Diversity statistics for a single generation.
+#[derive(Debug, Clone)]
+pub(super) struct DiversityStats {
+  pub mean_pairwise_distance: f64,
+  pub distinct_decks: usize,
+  /// Number of distinct sets of archetypes among the decks.
+  pub distinct_archetype_profiles: usize,
+  /// Fraction of decks containing each card at least once.
+  pub card_frequencies: BTreeMap<CardId, f64>,
+}
+
+impl DistanceMatrix {
+  pub(super) fn new(decks: &[Deck]) -> Self {
+    let counts = decks.iter().map(|deck| card_counts(deck.as_ref())).collect::<Vec<_>>();
+    let width = decks.len();
+    let mut data = vec![0; width * width];
+    for i in 0..width {
+      for j in (i + 1)..width {
+        let distance = multiset_distance(&counts[i], &counts[j]);
+        data[i * width + j] = distance;
+        data[j * width + i] = distance;
+      }
+    }
+    DistanceMatrix { width, data }
+  }
+
+  pub(super) fn get(&self, i: usize, j: usize) -> usize {
+    self.data[i * self.width + j]
+  }
+}
+
+impl DiversityStats {
+  pub(super) fn new(decks: &[Deck], distances: &DistanceMatrix, card_pool: &CardPool) -> Self {
+    let pair_count = decks.len() * decks.len().saturating_sub(1) / 2;
+    let total_distance = (0..decks.len())
+      .flat_map(|i| ((i + 1)..decks.len()).map(move |j| (i, j)))
+      .map(|(i, j)| distances.get(i, j))
+      .sum::<usize>();
+    let mean_pairwise_distance = if pair_count == 0 { 0.0 } else { total_distance as f64 / pair_count as f64 };
+
+    let distinct_decks = decks.iter()
+      .map(|deck| card_counts(deck.as_ref()))
+      .collect::<BTreeSet<_>>()
+      .len();
+    let distinct_archetype_profiles = decks.iter()
+      .map(|deck| archetype_profile(deck.as_ref(), card_pool))
+      .collect::<BTreeSet<_>>()
+      .len();
+
+    let mut card_frequencies = BTreeMap::new();
+    for deck in decks {
+      for card in card_counts(deck.as_ref()).keys() {
+        *card_frequencies.entry(*card).or_insert(0.0) += 1.0 / decks.len() as f64;
+      }
+    }
+
+    DiversityStats { mean_pairwise_distance, distinct_decks, distinct_archetype_profiles, card_frequencies }
+  }
+
+  pub(super) fn log(&self, card_pool: &CardPool) {
+    tracing::info!("Diversity: mean pairwise distance = {:.2}, distinct decks = {}, distinct archetype profiles = {}",
+                   self.mean_pairwise_distance,
+                   self.distinct_decks,
+                   self.distinct_archetype_profiles);
+    let mut frequencies = self.card_frequencies.iter().collect::<Vec<_>>();
+    frequencies.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap());
+    let describe = |(card, frequency): &(&CardId, &f64)| {
+      let name = card_pool.get(**card).map_or("?", |info| info.name.as_str());
+      format!("{name} ({card}): {:.0}%", *frequency * 100.0)
+    };
+    let most_common = frequencies.iter().take(LOGGED_CARD_FREQUENCIES).map(describe).collect::<Vec<_>>();
+    tracing::info!("Most common cards: {}", most_common.join(", "));
+    let all = frequencies.iter().map(describe).collect::<Vec<_>>();
+    tracing::debug!("Card frequencies: {}", all.join(", "));
+  }
+}
+
+/// The number of cards in one deck that have no counterpart in the
+/// other. For decks of equal size, this is the number of card
+/// replacements needed to turn one into the other.
+fn multiset_distance(a: &BTreeMap<CardId, usize>, b: &BTreeMap<CardId, usize>) -> usize {
+  let a_size = a.values().sum::<usize>();
+  let b_size = b.values().sum::<usize>();
+  let shared = a.iter()
+    .map(|(card, count)| usize::min(*count, b.get(card).copied().unwrap_or(0)))
+    .sum::<usize>();
+  usize::max(a_size, b_size) - shared
+}
+
+/// The set of archetypes among a deck's Minions.
+fn archetype_profile(deck: &[CardId], card_pool: &CardPool) -> BTreeSet<i64> {
+  deck.iter()
+    .filter_map(|card| card_pool.get(*card))
+    .flat_map(|info| info.archetypes.iter().copied())
+    .collect()
+}
+
+/// Fitness sharing. Each score is made positive and then divided by
+/// the niche count of its deck, so that decks with many near
+/// duplicates in the generation are penalized. Decks closer than
+/// `radius` cards to one another share fitness, with a triangular
+/// sharing function.
+pub(super) fn shared_fitness(scores: &[f64], distances: &DistanceMatrix, radius: usize) -> Vec<f64> {
+  let scores = super::normalize_scores_to_positive(scores);
+  (0..scores.len())
+    .map(|i| {
+      let niche_count = (0..scores.len())
+        .map(|j| distances.get(i, j))
+        .filter(|&distance| distance < radius)
+        .map(|distance| 1.0 - distance as f64 / radius as f64)
+        .sum::<f64>();
+      // The deck itself is always in its own niche, so the niche
+      // count is at least 1 whenever radius > 0.
+      scores[i] / niche_count.max(1.0)
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn deck(ids: &[i64]) -> Deck {
+    ids.iter().map(|id| CardId(*id)).collect()
+  }
+
+  #[test]
+  fn test_multiset_distance() {
+    let decks = [deck(&[1, 1, 2, 3]), deck(&[1, 2, 2, 4]), deck(&[3, 2, 1, 1])];
+    let distances = DistanceMatrix::new(&decks);
+    assert_eq!(distances.get(0, 1), 2);
+    assert_eq!(distances.get(1, 0), 2);
+    assert_eq!(distances.get(0, 2), 0);
+    assert_eq!(distances.get(1, 1), 0);
+  }
+
+  #[test]
+  fn test_shared_fitness_penalizes_duplicates() {
+    let decks = [deck(&[1, 2, 3, 4]), deck(&[1, 2, 3, 4]), deck(&[5, 6, 7, 8])];
+    let distances = DistanceMatrix::new(&decks);
+    let shared = shared_fitness(&[1.0, 1.0, 1.0], &distances, 2);
+    assert!(shared[2] > shared[0]);
+    assert_eq!(shared[0], shared[1]);
+  }
+}
@@ -1,19 +1,32 @@
 
 //! Genetic algorithm for identifying good decks in the card game.
 
-mod bradley_terry;
+pub(crate) mod bradley_terry;
+pub mod checkpoint;
+pub mod constraints;
+mod diversity;
+pub mod operators;
+pub mod stats;
 
 use crate::driver;
-use crate::cardgame::{GameEngine, CardGameEnv, GameWinner, Deck, CardId, DECK_SIZE};
+use crate::cardgame::{GameEngine, CardGameEnv, GameWinner, GameResult, GameRules, PlayerAgents, Deck, CardId, derive_game_seed};
 use crate::cardgame::deck::validator::DeckValidator;
+use crate::cardgame::deck::read_deck_list_from_path;
+use crate::cardgame::card_pool::CardPool;
 use crate::cardgame::code::serialize_game_code;
+use crate::cardgame::yaml_file::YamlFile;
 use crate::interpreter::mocking::codex::CodexDataFile;
-use bradley_terry::{WinMatrix, compute_scores};
+use bradley_terry::WinMatrix;
+use checkpoint::{Checkpoint, RankedDeck, RngState};
+use constraints::{ConstraintsError, DeckConstraints, ResolvedConstraints};
+use diversity::{DistanceMatrix, DiversityStats, shared_fitness};
+use operators::{CrossoverKind, MutationKind};
+use stats::{GenerationStats, ScoreDistribution, StatsWriter};
 
 use clap::Args;
-use rand::Rng;
-use rand::rngs::ThreadRng;
-use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use rand::distr::Distribution;
 use rand::distr::weighted::WeightedIndex;
 use threadpool::ThreadPool;
@@ -21,18 +34,49 @@ use threadpool::ThreadPool;
 use std::sync::Arc;
 use std::sync::mpsc::{self, Sender};
 use std::thread;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Number of top decks to include in the statistics for each
+/// generation.
+const STATS_TOP_DECK_COUNT: usize = 10;
+
+/// Number of times to try generating a reasonable deck, by splicing
+/// or at random, before falling back or giving up.
+const MAX_DECK_ATTEMPTS: usize = 100;
 
 #[derive(Debug)]
 pub struct GeneticAlgorithm<'a> {
-  random: ThreadRng,
+  /// Source of randomness for selection, crossover, and mutation.
+  random: ChaCha8Rng,
+  /// Source of randomness for pairing decks and seeding games. This
+  /// is kept separate from `random` so that changing the number of
+  /// games played does not perturb the evolution itself.
+  matchup_random: ChaCha8Rng,
   codex: CodexDataFile,
   validator: DeckValidator,
+  card_pool: CardPool,
+  /// The constraints as loaded, which are saved in checkpoints.
+  deck_constraints: DeckConstraints,
+  constraints: ResolvedConstraints,
   thread_pool: &'a ThreadPool,
   engine: Arc<GameEngine>,
+  rules: Arc<GameRules>,
   args: GeneticAlgorithmArgs,
+  /// Where to write a checkpoint after each generation, if anywhere.
+  checkpoint_path: Option<PathBuf>,
+  /// Where to write statistics after each generation, if anywhere.
+  stats_writer: Option<StatsWriter>,
+  /// A checkpoint to continue from, consumed when the run begins.
+  resume_from: Option<Checkpoint>,
+  /// Known decks to include in the initial population.
+  initial_decks: Vec<Deck>,
+  /// Reference decks that every individual plays against in each
+  /// generation.
+  gauntlet: Vec<Deck>,
 }
 
-#[derive(Debug, Clone, Args)]
+#[derive(Debug, Clone, Args, Serialize, Deserialize)]
 pub struct GeneticAlgorithmArgs {
   /// Number of individuals in each generation. (Default = 125)
   #[arg(long, default_value_t = 125)]
@@ -56,36 +100,214 @@ pub struct GeneticAlgorithmArgs {
   /// Mutation rate, as a fraction from 0 to 1. (default = 0.03)
   #[arg(long, default_value_t = 0.03)]
   pub mutation_rate: f64,
+  /// Operator used to combine two parent decks. (default =
+  /// positional)
+  #[arg(long, value_enum, default_value_t)]
+  pub crossover: CrossoverKind,
+  /// Operator used to mutate a deck. (default = random)
+  #[arg(long, value_enum, default_value_t)]
+  pub mutation: MutationKind,
+  /// Always seat the first deck of each matchup at BOTTOM, rather
+  /// than alternating seats from game to game.
+  #[arg(long)]
+  pub fixed_sides: bool,
+  /// Random seed as a u64. All randomness in the algorithm is
+  /// derived from this seed, so runs with the same seed and
+  /// arguments produce the same decks regardless of thread count. If
+  /// not provided, generator will be randomly seeded.
+  #[arg(long)]
+  pub seed: Option<u64>,
+  /// File of known decks (one comma-separated deck per line) to
+  /// include in the initial population. The remainder of the
+  /// population is generated randomly.
+  #[arg(long)]
+  pub initial_decks: Option<PathBuf>,
+  /// Maximum fraction of the initial population, from 0 to 1, taken
+  /// up by decks from the initial decks file and their mutated
+  /// copies. (default = 0.5)
+  #[arg(long, default_value_t = 0.5)]
+  pub initial_deck_ratio: f64,
+  /// Number of mutated copies of each initial deck to add to the
+  /// initial population, in addition to the deck itself. (default =
+  /// 0)
+  #[arg(long, default_value_t = 0)]
+  pub initial_deck_copies: usize,
+  /// YAML file of constraints on the decks to search, such as locked
+  /// cards, banned cards, allowed archetypes, and rarity caps.
+  #[arg(long)]
+  pub constraints: Option<PathBuf>,
+  /// File of reference decks (one comma-separated deck per line)
+  /// which every individual plays against in each generation, in
+  /// addition to its matchups within the generation.
+  #[arg(long)]
+  pub gauntlet: Option<PathBuf>,
+  /// Number of games each individual plays against each gauntlet
+  /// deck. (default = 4)
+  #[arg(long, default_value_t = 4)]
+  pub gauntlet_games: usize,
+  /// Weight of the gauntlet win rate in an individual's fitness, as a
+  /// fraction from 0 to 1. The remainder of the fitness comes from
+  /// the Bradley-Terry score within the generation. Ignored if there
+  /// is no gauntlet. (default = 0.5)
+  #[arg(long, default_value_t = 0.5)]
+  pub gauntlet_weight: f64,
+  /// Enables fitness sharing, so that decks within this many cards of
+  /// one another share their fitness. This penalizes near-duplicate
+  /// decks, so that several distinct strong decks survive rather than
+  /// copies of a single one. If not provided, fitness is not shared.
+  #[arg(long)]
+  pub sharing_radius: Option<usize>,
+  /// Number of standard errors subtracted from each deck's score when
+  /// choosing elite decks, so that a deck which scored well over only
+  /// a few games is less likely to be carried over on luck. Use 0 to
+  /// rank elite decks by score alone. (default = 1)
+  #[arg(long, default_value_t = 1.0)]
+  pub elite_uncertainty_penalty: f64,
 }
 
+/// Results of the games between two decks. Unless sides are fixed,
+/// the two decks alternate seats, so results are tracked separately
+/// for each seating.
 #[derive(Debug, Clone, Default)]
 struct MatchupsResult {
-  bottom_index: usize,
-  top_index: usize,
+  matchup: Matchup,
+  /// Games with the first deck at BOTTOM.
+  first_at_bottom: SeatingResult,
+  /// Games with the second deck at BOTTOM.
+  second_at_bottom: SeatingResult,
+  error_outcomes: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct SeatingResult {
   bottom_wins: u64,
   top_wins: u64,
-  error_outcomes: u64,
+  draws: u64,
+}
+
+/// Totals over every game played in a generation.
+#[derive(Debug, Clone, Default)]
+struct GenerationOutcome {
+  /// The fitness of each individual.
+  scores: Vec<f64>,
+  /// The standard error of the Bradley-Terry part of each
+  /// individual's fitness.
+  standard_errors: Vec<f64>,
+  /// The estimated log-odds advantage of the BOTTOM seat.
+  seat_advantage: f64,
+  seat_advantage_standard_error: f64,
+  bottom_seat_wins: u64,
+  top_seat_wins: u64,
+  draws: u64,
+  errors: u64,
+}
+
+/// A pairing of two decks within a generation.
+#[derive(Debug, Clone, Copy, Default)]
+struct Matchup {
+  /// Position of this matchup within the generation, used to collect
+  /// results in a deterministic order.
+  index: usize,
+  first_index: usize,
+  second_index: usize,
+  /// Seed from which the seeds of the individual games are derived.
+  seed: u64,
 }
 
 impl<'a> GeneticAlgorithm<'a> {
   pub fn new(
     thread_pool: &'a ThreadPool,
     args: GeneticAlgorithmArgs,
+    rules: GameRules,
+  ) -> anyhow::Result<Self> {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    tracing::info!("Genetic algorithm seed: {seed}");
+    let random = ChaCha8Rng::seed_from_u64(seed);
+    let mut matchup_random = ChaCha8Rng::seed_from_u64(seed);
+    matchup_random.set_stream(1);
+    let initial_decks = match &args.initial_decks {
+      Some(path) => read_deck_list_from_path(path)?,
+      None => Vec::new(),
+    };
+    let constraints = args.load_constraints()?;
+    let mut genetic_algorithm = Self::with_state(thread_pool, args, rules, constraints, random, matchup_random)?;
+    genetic_algorithm.initial_decks = initial_decks;
+    if let Some(path) = &genetic_algorithm.args.gauntlet {
+      genetic_algorithm.gauntlet = load_gauntlet(path, &genetic_algorithm.validator, genetic_algorithm.rules.deck_size)?;
+    }
+    Ok(genetic_algorithm)
+  }
+
+  /// Continues a previous run from a checkpoint. The arguments,
+  /// rules, constraints, and gauntlet of the previous run are used.
+  pub fn resume(thread_pool: &'a ThreadPool, checkpoint: Checkpoint) -> anyhow::Result<Self> {
+    tracing::info!("Resuming genetic algorithm after generation {}", checkpoint.completed_generations);
+    let random = ChaCha8Rng::from(&checkpoint.random);
+    let matchup_random = ChaCha8Rng::from(&checkpoint.matchup_random);
+    let mut genetic_algorithm = Self::with_state(
+      thread_pool,
+      checkpoint.args.clone(),
+      checkpoint.rules.clone(),
+      checkpoint.constraints.clone(),
+      random,
+      matchup_random,
+    )?;
+    genetic_algorithm.gauntlet = checkpoint.gauntlet.clone();
+    genetic_algorithm.resume_from = Some(checkpoint);
+    Ok(genetic_algorithm)
+  }
+
+  fn with_state(
+    thread_pool: &'a ThreadPool,
+    args: GeneticAlgorithmArgs,
+    rules: GameRules,
+    deck_constraints: DeckConstraints,
+    random: ChaCha8Rng,
+    matchup_random: ChaCha8Rng,
   ) -> anyhow::Result<Self> {
     let codex = CodexDataFile::read_from_default_file()?;
     let validator = DeckValidator::new(codex.clone());
     let superglobals = driver::load_all_files()?;
     let engine = Arc::new(GameEngine::new(superglobals));
+    let card_pool = CardPool::load(&engine, &codex)?;
+    let constraints = deck_constraints.resolve(&card_pool, rules.deck_size)?;
+    tracing::info!("Searching decks with {} locked card(s) and {} allowed card(s)", constraints.locked().len(), constraints.allowed().len());
     Ok(GeneticAlgorithm {
-      random: rand::rng(),
+      random,
+      matchup_random,
       codex,
       validator,
+      card_pool,
+      deck_constraints,
+      constraints,
       thread_pool,
       engine,
+      rules: Arc::new(rules),
       args,
+      checkpoint_path: None,
+      stats_writer: None,
+      resume_from: None,
+      initial_decks: Vec::new(),
+      gauntlet: Vec::new(),
     })
   }
 
+  /// Writes a checkpoint to the given path after every generation.
+  pub fn set_checkpoint_path(&mut self, path: impl Into<PathBuf>) {
+    let path = path.into();
+    tracing::info!("Writing checkpoints to {}", path.display());
+    self.checkpoint_path = Some(path);
+  }
+
+  /// Writes statistics for every generation to the given writer.
+  pub fn set_stats_writer(&mut self, writer: StatsWriter) {
+    self.stats_writer = Some(writer);
+  }
+
+  pub fn args(&self) -> &GeneticAlgorithmArgs {
+    &self.args
+  }
+
   pub fn validator(&self) -> &DeckValidator {
     &self.validator
   }
@@ -94,10 +316,18 @@ impl<'a> GeneticAlgorithm<'a> {
     &self.codex
   }
 
+  pub fn card_pool(&self) -> &CardPool {
+    &self.card_pool
+  }
+
   /// Runs the genetic algorithm with the given parameters. Returned
   /// decks include the "top" elite decks at the beginning, followed
   /// by final generation splices.
-  pub fn run_genetic_algorithm(&mut self, generation_count: usize) -> Vec<Deck> {
+  ///
+  /// When resuming from a checkpoint, `generation_count` is the total
+  /// number of generations, including those that have already
+  /// completed.
+  pub fn run_genetic_algorithm(&mut self, generation_count: usize) -> anyhow::Result<Vec<Deck>> {
     // Resolve default values for fields
     let generation_size = self.args.generation_size;
     let elite_deck_count = self.args.elite_deck_count();
@@ -105,26 +335,56 @@ impl<'a> GeneticAlgorithm<'a> {
 
     tracing::info!("Genetic Algorithm initiated");
     tracing::info!("Running {} generations of {} individuals each", generation_count, generation_size);
-    tracing::info!("Additional parameters: total_matchups_per_individual = {}, total_games_per_matchup = {}, elite_deck_count = {}, candidate_parent_deck_count = {}, mutation_rate = {}",
+    tracing::info!("Additional parameters: total_matchups_per_individual = {}, total_games_per_matchup = {}, elite_deck_count = {}, candidate_parent_deck_count = {}, mutation_rate = {}, crossover = {:?}, mutation = {:?}, fixed_sides = {}, sharing_radius = {:?}, elite_uncertainty_penalty = {}",
                    self.args.total_matchups_per_individual,
                    self.args.total_games_per_matchup,
                    elite_deck_count,
                    candidate_parent_deck_count,
-                   self.args.mutation_rate);
+                   self.args.mutation_rate,
+                   self.args.crossover,
+                   self.args.mutation,
+                   self.args.fixed_sides,
+                   self.args.sharing_radius,
+                   self.args.elite_uncertainty_penalty);
+    if !self.gauntlet.is_empty() {
+      tracing::info!("Gauntlet of {} deck(s), with {} game(s) against each and gauntlet_weight = {}",
+                     self.gauntlet.len(),
+                     self.args.gauntlet_games,
+                     self.args.gauntlet_weight);
+    }
 
-    let mut generation_pool = Arc::new(self.generate_initial_generation_pool());
-    for index in 1..=generation_count {
+    let (completed_generations, initial_pool) = match self.resume_from.take() {
+      Some(checkpoint) => (checkpoint.completed_generations, checkpoint.pool),
+      None => (0, self.generate_initial_generation_pool()?),
+    };
+    let mut generation_pool = Arc::new(initial_pool);
+    for index in (completed_generations + 1)..=generation_count {
       let span = tracing::info_span!("generation", index = index);
       let _span_guard = span.enter();
       tracing::info!("Running generation {} of {}", index, generation_count);
-      let scores = self.run_one_generation(&generation_pool, &span);
+      let start_time = Instant::now();
+      let distances = DistanceMatrix::new(&generation_pool);
+      let diversity = DiversityStats::new(&generation_pool, &distances, &self.card_pool);
+      diversity.log(&self.card_pool);
+      let mut outcome = self.run_one_generation(&generation_pool, &span);
+      let mut scores = std::mem::take(&mut outcome.scores);
+      let mut elite_scores = scores.iter()
+        .zip(&outcome.standard_errors)
+        .map(|(score, standard_error)| score - self.args.elite_uncertainty_penalty * standard_error)
+        .collect::<Vec<_>>();
+      if let Some(radius) = self.args.sharing_radius {
+        scores = shared_fitness(&scores, &distances, radius);
+        elite_scores = shared_fitness(&elite_scores, &distances, radius);
+      }
       let mut deck_indices_by_rank = (0..generation_size).collect::<Vec<_>>();
       deck_indices_by_rank.sort_by(|&a, &b| scores[b].partial_cmp(&scores[a]).unwrap());
+      let mut deck_indices_by_elite_rank = (0..generation_size).collect::<Vec<_>>();
+      deck_indices_by_elite_rank.sort_by(|&a, &b| elite_scores[b].partial_cmp(&elite_scores[a]).unwrap());
 
       let mut new_generation_pool = Vec::with_capacity(generation_pool.len());
       // Copy the first few elite decks over verbatim
       for i in 0..elite_deck_count {
-        new_generation_pool.push(generation_pool[deck_indices_by_rank[i]].clone());
+        new_generation_pool.push(generation_pool[deck_indices_by_elite_rank[i]].clone());
       }
 
       // Build weights (lowest-scoring should be set to zero)
@@ -136,17 +396,8 @@ impl<'a> GeneticAlgorithm<'a> {
       // Generate the rest by splicing genes
       let weighted = WeightedIndex::new(weights).unwrap();
       while new_generation_pool.len() < generation_size {
-        let i = weighted.sample(&mut self.random);
-        let j = weighted.sample(&mut self.random);
-        if i == j {
-          continue;
-        }
-        let deck_i = generation_pool[deck_indices_by_rank[i]].as_ref();
-        let deck_j = generation_pool[deck_indices_by_rank[j]].as_ref();
-        let new_deck = self.splice(deck_i, deck_j);
-        if self.is_reasonable_deck(new_deck.as_ref()) {
-          new_generation_pool.push(new_deck);
-        }
+        let new_deck = self.breed(&weighted, &generation_pool, &deck_indices_by_rank)?;
+        new_generation_pool.push(new_deck);
       }
 
       // Mutate some of the individuals.
@@ -159,99 +410,282 @@ impl<'a> GeneticAlgorithm<'a> {
       // Final check; if any of the decks are invalid, replace them
       // with a new splice.
       for deck in &mut new_generation_pool {
-        while !self.is_reasonable_deck(deck.as_ref()) {
-          let i = weighted.sample(&mut self.random);
-          let j = weighted.sample(&mut self.random);
-          if i == j {
-            continue;
-          }
-          *deck = self.splice(generation_pool[deck_indices_by_rank[i]].as_ref(), generation_pool[deck_indices_by_rank[j]].as_ref());
+        if !self.is_reasonable_deck(deck.as_ref()) {
+          *deck = self.breed(&weighted, &generation_pool, &deck_indices_by_rank)?;
+        }
+      }
+
+      if let Some(checkpoint_path) = &self.checkpoint_path {
+        let checkpoint = Checkpoint {
+          completed_generations: index,
+          args: self.args.clone(),
+          rules: GameRules::clone(&self.rules),
+          constraints: self.deck_constraints.clone(),
+          gauntlet: self.gauntlet.clone(),
+          ranked_decks: deck_indices_by_rank.iter()
+            .map(|&i| RankedDeck { deck: generation_pool[i].clone(), score: scores[i] })
+            .collect(),
+          pool: new_generation_pool.clone(),
+          random: RngState::from(&self.random),
+          matchup_random: RngState::from(&self.matchup_random),
+        };
+        match checkpoint.write_to_path(checkpoint_path) {
+          Ok(()) => tracing::info!("Wrote checkpoint to {}", checkpoint_path.display()),
+          Err(err) => tracing::error!("Could not write checkpoint to {}: {err}", checkpoint_path.display()),
+        }
+      }
+
+      if let Some(stats_writer) = &mut self.stats_writer {
+        let stats = GenerationStats {
+          generation: index,
+          wall_time_secs: start_time.elapsed().as_secs_f64(),
+          draws: outcome.draws,
+          errors: outcome.errors,
+          bottom_seat_wins: outcome.bottom_seat_wins,
+          top_seat_wins: outcome.top_seat_wins,
+          seat_advantage: outcome.seat_advantage,
+          seat_advantage_standard_error: outcome.seat_advantage_standard_error,
+          scores: ScoreDistribution::new(&scores),
+          top_decks: deck_indices_by_rank.iter()
+            .take(STATS_TOP_DECK_COUNT)
+            .map(|&i| RankedDeck { deck: generation_pool[i].clone(), score: scores[i] })
+            .collect(),
+          mean_pairwise_distance: diversity.mean_pairwise_distance,
+          distinct_decks: diversity.distinct_decks,
+          distinct_archetype_profiles: diversity.distinct_archetype_profiles,
+          card_frequencies: diversity.card_frequencies,
+        };
+        if let Err(err) = stats_writer.write(&stats) {
+          tracing::error!("Could not write generation statistics: {err}");
         }
       }
 
       generation_pool = Arc::new(new_generation_pool);
     }
-    Arc::unwrap_or_clone(generation_pool)
+    Ok(Arc::unwrap_or_clone(generation_pool))
+  }
+
+  /// Splices two parents chosen by weight, retrying until the result
+  /// is a reasonable deck. If no splice is reasonable after
+  /// [`MAX_DECK_ATTEMPTS`] tries (for instance, because only one
+  /// parent has nonzero weight), falls back to a random deck.
+  fn breed(
+    &mut self,
+    weighted: &WeightedIndex<f64>,
+    generation_pool: &[Deck],
+    deck_indices_by_rank: &[usize],
+  ) -> anyhow::Result<Deck> {
+    for _ in 0..MAX_DECK_ATTEMPTS {
+      let i = weighted.sample(&mut self.random);
+      let j = weighted.sample(&mut self.random);
+      if i == j {
+        continue;
+      }
+      let deck_i = generation_pool[deck_indices_by_rank[i]].as_ref();
+      let deck_j = generation_pool[deck_indices_by_rank[j]].as_ref();
+      let new_deck = self.splice(deck_i, deck_j);
+      if self.is_reasonable_deck(new_deck.as_ref()) {
+        return Ok(new_deck);
+      }
+    }
+    tracing::warn!("No reasonable splice found after {MAX_DECK_ATTEMPTS} attempts; using a random deck instead");
+    self.generate_reasonable_random_deck()
   }
 
-  fn generate_initial_generation_pool(&mut self) -> Vec<Deck> {
-    let mut decks = Vec::with_capacity(self.args.generation_size);
+  fn generate_initial_generation_pool(&mut self) -> anyhow::Result<Vec<Deck>> {
+    let mut decks = self.seeded_initial_decks();
+    tracing::info!("Initial generation contains {} deck(s) from the initial decks file", decks.len());
     while decks.len() < self.args.generation_size {
-      let deck = self.generate_random_deck();
-      if self.is_reasonable_deck(deck.as_ref()) {
-        decks.push(deck);
+      decks.push(self.generate_reasonable_random_deck()?);
+    }
+    Ok(decks)
+  }
+
+  /// Generates random decks until one is reasonable, giving up after
+  /// [`MAX_DECK_ATTEMPTS`] tries.
+  fn generate_reasonable_random_deck(&mut self) -> anyhow::Result<Deck> {
+    for _ in 0..MAX_DECK_ATTEMPTS {
+      if let Some(deck) = self.generate_random_deck() && self.is_reasonable_deck(deck.as_ref()) {
+        return Ok(deck);
       }
     }
-    decks
+    anyhow::bail!("Could not generate a deck satisfying the constraints after {MAX_DECK_ATTEMPTS} attempts")
   }
 
-  /// Generates a completely random deck. Note that this deck MIGHT
-  /// NOT be valid.
-  fn generate_random_deck(&mut self) -> Deck {
-    let mut new_deck = Vec::with_capacity(DECK_SIZE);
-    for _ in 0..DECK_SIZE {
-      let new_card_id = self.random.random_range(1..=self.codex.max_id);
-      new_deck.push(CardId(new_card_id));
+  /// Decks from the initial decks file, followed by mutated copies of
+  /// those decks, up to the configured fraction of the generation.
+  /// Unreasonable decks are skipped.
+  fn seeded_initial_decks(&mut self) -> Vec<Deck> {
+    const MAX_MUTATION_ATTEMPTS: usize = 100;
+
+    let max_count = (self.args.generation_size as f64 * self.args.initial_deck_ratio.clamp(0.0, 1.0)) as usize;
+    let initial_decks = std::mem::take(&mut self.initial_decks);
+    let mut valid_decks = Vec::with_capacity(initial_decks.len());
+    for deck in initial_decks {
+      if deck.len() != self.rules.deck_size {
+        tracing::warn!("Skipping initial deck of size {} (expected {}): {deck}", deck.len(), self.rules.deck_size);
+      } else if !self.is_reasonable_deck(deck.as_ref()) {
+        tracing::warn!("Skipping unreasonable initial deck: {deck}");
+      } else {
+        valid_decks.push(deck);
+      }
+    }
+
+    let mut decks = valid_decks.iter().take(max_count).cloned().collect::<Vec<_>>();
+    'copies: for _ in 0..self.args.initial_deck_copies {
+      for deck in &valid_decks {
+        if decks.len() >= max_count {
+          break 'copies;
+        }
+        for _ in 0..MAX_MUTATION_ATTEMPTS {
+          let mut copy = deck.clone();
+          self.mutate(&mut copy);
+          if self.is_reasonable_deck(copy.as_ref()) {
+            decks.push(copy);
+            break;
+          }
+        }
+      }
     }
-    Deck(new_deck)
+    decks
   }
 
-  fn run_one_generation(&mut self, generation: &Arc<Vec<Deck>>, span: &tracing::Span) -> Vec<f64> {
-    let (sender, receiver) = mpsc::channel();
-    let mut total_matches = 0;
-    for bottom_index in 0..generation.len() {
+  /// Generates a completely random deck, consisting of the locked
+  /// cards and then random allowed cards, within the copy limits and
+  /// rarity caps. Returns `None` if the deck cannot be filled.
+  fn generate_random_deck(&mut self) -> Option<Deck> {
+    self.constraints.random_deck(self.rules.deck_size, &mut self.random).map(Deck)
+  }
+
+  /// Plays one generation's games and returns the fitness of each
+  /// individual. Fitness is the Bradley-Terry score within the
+  /// generation, blended with the gauntlet win rate if there is a
+  /// gauntlet.
+  fn run_one_generation(&mut self, generation: &Arc<Vec<Deck>>, span: &tracing::Span) -> GenerationOutcome {
+    // Gauntlet decks are appended after the individuals, so a
+    // matchup's second index at or beyond the generation size refers
+    // to a gauntlet deck.
+    let generation_size = generation.len();
+    let decks = Arc::new(generation.iter().chain(&self.gauntlet).cloned().collect::<Vec<_>>());
+    let mut matchups = Vec::new();
+    for first_index in 0..generation_size {
       for _ in 0..self.args.total_matchups_per_individual {
-        let top_index = self.random.random_range(0..generation.len());
-        if top_index == bottom_index {
+        let second_index = self.matchup_random.random_range(0..generation_size);
+        if second_index == first_index {
           // Don't do self-matchups; it'll confuse the logistic
           // regression.
           continue
         }
-        total_matches += 1;
-        let sender = sender.clone();
-        let engine = Arc::clone(&self.engine);
-        let generation = Arc::clone(&generation);
-        let enclosing_span = span.clone();
-        let total_matchups_per_individual = self.args.total_matchups_per_individual;
-        self.thread_pool.execute(move || {
-          let _span_guard = enclosing_span.enter();
-          let _span_guard = tracing::info_span!("thread", thread_id = ?thread::current().id()).entered();
-          play_games(sender, engine, generation, total_matchups_per_individual, bottom_index, top_index);
-        });
+        let matchup = Matchup {
+          index: matchups.len(),
+          first_index,
+          second_index,
+          seed: self.matchup_random.random(),
+        };
+        matchups.push((matchup, self.args.total_games_per_matchup));
       }
     }
-
-    // Collect results
-    let mut win_matrix = WinMatrix::zeroes(generation.len());
-    for _ in 0..total_matches {
-      let outcome = receiver.recv().unwrap();
-      win_matrix[(outcome.bottom_index, outcome.top_index)] += outcome.bottom_wins;
-      win_matrix[(outcome.top_index, outcome.bottom_index)] += outcome.top_wins;
+    for first_index in 0..generation_size {
+      for gauntlet_index in 0..self.gauntlet.len() {
+        let matchup = Matchup {
+          index: matchups.len(),
+          first_index,
+          second_index: generation_size + gauntlet_index,
+          seed: self.matchup_random.random(),
+        };
+        matchups.push((matchup, self.args.gauntlet_games));
+      }
     }
 
-    // Logistic regression
-    compute_scores(&win_matrix)
-  }
+    let (sender, receiver) = mpsc::channel();
+    let total_matches = matchups.len();
+    for (matchup, games_count) in matchups {
+      let sender = sender.clone();
+      let engine = Arc::clone(&self.engine);
+      let rules = Arc::clone(&self.rules);
+      let decks = Arc::clone(&decks);
+      let enclosing_span = span.clone();
+      let swap_sides = !self.args.fixed_sides;
+      self.thread_pool.execute(move || {
+        let _span_guard = enclosing_span.enter();
+        let _span_guard = tracing::info_span!("thread", thread_id = ?thread::current().id()).entered();
+        play_games(sender, engine, rules, decks, games_count, matchup, swap_sides);
+      });
+    }
 
-  fn splice(&mut self, deck1: &[CardId], deck2: &[CardId]) -> Deck {
-    let mut new_deck = Vec::with_capacity(DECK_SIZE);
-    for i in 0..DECK_SIZE {
-      let deck_to_pull_from = if self.random.random() { deck1 } else { deck2 };
-      new_deck.push(deck_to_pull_from[i]);
+    // Collect results. Threads finish in an arbitrary order, so sort
+    // the results before accumulating them.
+    let mut outcomes = receiver.iter().take(total_matches).collect::<Vec<_>>();
+    outcomes.sort_by_key(|outcome| outcome.matchup.index);
+    let mut win_matrix = WinMatrix::zeroes(generation_size);
+    let mut gauntlet_wins = vec![0.0; generation_size];
+    let mut gauntlet_games = vec![0u64; generation_size];
+    let mut total_bottom_seat_wins = 0;
+    let mut total_top_seat_wins = 0;
+    let mut total_draws = 0;
+    let mut total_errors = 0;
+    for outcome in outcomes {
+      let Matchup { first_index, second_index, .. } = outcome.matchup;
+      if second_index >= generation_size {
+        gauntlet_wins[first_index] += outcome.first_score();
+        gauntlet_games[first_index] += outcome.first_at_bottom.games() + outcome.second_at_bottom.games();
+      } else {
+        outcome.first_at_bottom.record(&mut win_matrix, first_index, second_index);
+        outcome.second_at_bottom.record(&mut win_matrix, second_index, first_index);
+      }
+      for seating in [outcome.first_at_bottom, outcome.second_at_bottom] {
+        total_bottom_seat_wins += seating.bottom_wins;
+        total_top_seat_wins += seating.top_wins;
+        total_draws += seating.draws;
+      }
+      total_errors += outcome.error_outcomes;
+    }
+    tracing::info!("Generation finished with {total_draws} draw(s) and {total_errors} error(s)");
+    tracing::info!("Seat BOTTOM won {total_bottom_seat_wins} game(s) and seat TOP won {total_top_seat_wins} game(s)");
+
+    let fit = bradley_terry::fit(&win_matrix);
+    if fit.converged {
+      tracing::info!("Bradley-Terry fit converged after {} iteration(s)", fit.iterations);
+    } else {
+      tracing::warn!("Bradley-Terry fit did not converge after {} iteration(s)", fit.iterations);
+    }
+    tracing::info!("Estimated BOTTOM seat advantage: {:.3} ± {:.3} log-odds (BOTTOM wins {:.1}% of games between equal decks)",
+                   fit.seat_advantage,
+                   fit.seat_advantage_standard_error,
+                   100.0 / (1.0 + (-fit.seat_advantage).exp()));
+    let mut outcome = GenerationOutcome {
+      scores: fit.scores,
+      standard_errors: fit.standard_errors,
+      seat_advantage: fit.seat_advantage,
+      seat_advantage_standard_error: fit.seat_advantage_standard_error,
+      bottom_seat_wins: total_bottom_seat_wins,
+      top_seat_wins: total_top_seat_wins,
+      draws: total_draws,
+      errors: total_errors,
+    };
+    if self.gauntlet.is_empty() {
+      return outcome;
     }
-    new_deck.shuffle(&mut self.random);
-    Deck(new_deck)
-  }
 
-  fn mutate(&mut self, deck: &mut Deck) {
-    let index = self.random.random_range(0..DECK_SIZE);
-    let new_card_id = self.random.random_range(1..=self.codex.max_id);
-    deck[index] = CardId(new_card_id);
+    let total_gauntlet_wins = gauntlet_wins.iter().sum::<f64>();
+    let total_gauntlet_games = gauntlet_games.iter().sum::<u64>();
+    if total_gauntlet_games > 0 {
+      tracing::info!("Mean gauntlet win rate: {:.3}", total_gauntlet_wins / total_gauntlet_games as f64);
+    }
+    let gauntlet_weight = self.args.gauntlet_weight.clamp(0.0, 1.0);
+    outcome.scores = outcome.scores.iter()
+      .zip(gauntlet_wins.iter().zip(&gauntlet_games))
+      .map(|(score, (wins, games))| blend_fitness(*score, *wins, *games, gauntlet_weight))
+      .collect();
+    for standard_error in &mut outcome.standard_errors {
+      *standard_error *= 1.0 - gauntlet_weight;
+    }
+    outcome
   }
 
   pub fn is_reasonable_deck(&self, deck: &[CardId]) -> bool {
     let errors = self.validator.validate_deck(deck);
-    errors.is_empty()
+    errors.is_empty() && self.constraints.is_satisfied_by(deck)
   }
 }
 
@@ -259,36 +693,74 @@ impl GeneticAlgorithmArgs {
   pub fn elite_deck_count(&self) -> usize {
     self.elite_deck_count.unwrap_or(self.generation_size / 10)
   }
+
+  /// Loads the constraints file, if one was given.
+  pub fn load_constraints(&self) -> Result<DeckConstraints, ConstraintsError> {
+    match &self.constraints {
+      Some(path) => Ok(DeckConstraints::read_from_path(path)?),
+      None => Ok(DeckConstraints::default()),
+    }
+  }
+}
+
+impl MatchupsResult {
+  /// The first deck's wins, counting each draw as half of a win.
+  fn first_score(&self) -> f64 {
+    let draws = self.first_at_bottom.draws + self.second_at_bottom.draws;
+    (self.first_at_bottom.bottom_wins + self.second_at_bottom.top_wins) as f64 + draws as f64 / 2.0
+  }
+}
+
+impl SeatingResult {
+  fn games(&self) -> u64 {
+    self.bottom_wins + self.top_wins + self.draws
+  }
+
+  /// Adds these results to the win matrix, for games with `bottom` at
+  /// BOTTOM and `top` at TOP. A draw counts as half of a win for each
+  /// player.
+  fn record(&self, win_matrix: &mut WinMatrix, bottom: usize, top: usize) {
+    let half_draws = self.draws as f64 / 2.0;
+    win_matrix[(bottom, top, GameWinner::Bottom)] += self.bottom_wins as f64 + half_draws;
+    win_matrix[(top, bottom, GameWinner::Top)] += self.top_wins as f64 + half_draws;
+  }
 }
 
 fn play_games(
   out_channel: Sender<MatchupsResult>,
   engine: Arc<GameEngine>,
-  generation: Arc<Vec<Deck>>,
+  rules: Arc<GameRules>,
+  decks: Arc<Vec<Deck>>,
   games_count: usize,
-  bottom_index: usize,
-  top_index: usize,
+  matchup: Matchup,
+  swap_sides: bool,
 ) {
   let env = CardGameEnv {
-    bottom_deck: &generation[bottom_index],
-    top_deck: &generation[top_index],
+    bottom_deck: &decks[matchup.first_index],
+    top_deck: &decks[matchup.second_index],
   };
-
-  let mut results = MatchupsResult::default();
-  results.bottom_index = bottom_index;
-  results.top_index = top_index;
-  for _ in 0..games_count {
-    let seed = rand::rng().random::<u64>();
-    match engine.play_game_seeded(&env, seed) {
-      Ok(GameWinner::Top) => {
-        results.top_wins += 1;
-      }
-      Ok(GameWinner::Bottom) => {
-        results.bottom_wins += 1;
-      }
+  let swapped_env = env.clone().swap_sides();
+
+  // Decks are always evaluated with the default agents.
+  let agents = PlayerAgents::default();
+
+  let mut results = MatchupsResult { matchup, ..MatchupsResult::default() };
+  for game_index in 0..games_count {
+    // Alternate seats so that neither deck benefits from always
+    // going first (or from the second player's fort advantage).
+    let swapped = swap_sides && game_index % 2 == 1;
+    let env = if swapped { &swapped_env } else { &env };
+    let seating = if swapped { &mut results.second_at_bottom } else { &mut results.first_at_bottom };
+    let seed = derive_game_seed(matchup.seed, game_index as u64);
+    match engine.play_game_seeded(env, &agents, &rules, seed) {
+      Ok(outcome) => match outcome.result {
+        GameResult::Win(GameWinner::Bottom) => seating.bottom_wins += 1,
+        GameResult::Win(GameWinner::Top) => seating.top_wins += 1,
+        GameResult::Draw { .. } => seating.draws += 1,
+      },
       Err(err) => {
         results.error_outcomes += 1;
-        let game_code = serialize_game_code(seed, &env).unwrap_or_else(|_| "(failed to get game code)".to_owned());
+        let game_code = serialize_game_code(seed, env).unwrap_or_else(|_| "(failed to get game code)".to_owned());
         tracing::error!(%game_code, "Error during game: {}", err.root_cause());
       }
     }
@@ -296,6 +768,39 @@ fn play_games(
   out_channel.send(results).unwrap();
 }
 
+/// Loads the gauntlet decks from a file. Decks of the wrong size or
+/// which fail validation are skipped.
+fn load_gauntlet(path: &Path, validator: &DeckValidator, deck_size: usize) -> anyhow::Result<Vec<Deck>> {
+  let mut gauntlet = read_deck_list_from_path(path)?;
+  gauntlet.retain(|deck| {
+    if deck.len() != deck_size {
+      tracing::warn!("Skipping gauntlet deck of size {} (expected {deck_size}): {deck}", deck.len());
+      false
+    } else if !validator.validate_deck(deck.as_ref()).is_empty() {
+      tracing::warn!("Skipping invalid gauntlet deck: {deck}");
+      false
+    } else {
+      true
+    }
+  });
+  if gauntlet.is_empty() {
+    anyhow::bail!("Gauntlet file {} contains no usable decks", path.display());
+  }
+  Ok(gauntlet)
+}
+
+/// Blends a Bradley-Terry score with a gauntlet win rate.
+///
+/// Bradley-Terry scores are log-odds, so the win rate is converted to
+/// log-odds as well before blending. Half a win and half a loss are
+/// added to the record so that a perfect or winless record stays
+/// finite.
+fn blend_fitness(score: f64, gauntlet_wins: f64, gauntlet_games: u64, gauntlet_weight: f64) -> f64 {
+  let win_rate = (gauntlet_wins + 0.5) / (gauntlet_games as f64 + 1.0);
+  let gauntlet_score = (win_rate / (1.0 - win_rate)).ln();
+  (1.0 - gauntlet_weight) * score + gauntlet_weight * gauntlet_score
+}
+
 /// Add a constant to all scores to force them all to be greater than
 /// zero.
 fn normalize_scores_to_positive(scores: &[f64]) -> Vec<f64> {
@@ -304,3 +809,16 @@ fn normalize_scores_to_positive(scores: &[f64]) -> Vec<f64> {
   let min = scores.iter().copied().min_by(|a, b| a.partial_cmp(b).unwrap()).unwrap();
   scores.iter().map(|s| s - min + EPSILON).collect()
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_blend_fitness() {
+    assert_eq!(blend_fitness(1.5, 3.0, 4, 0.0), 1.5);
+    assert!(blend_fitness(0.0, 2.0, 4, 1.0).abs() < 1e-9);
+    assert!(blend_fitness(0.0, 4.0, 4, 1.0).is_finite());
+    assert!(blend_fitness(0.0, 4.0, 4, 0.5) > blend_fitness(0.0, 3.0, 4, 0.5));
+  }
+}
@@ -0,0 +1,187 @@
+//! Crossover and mutation operators for the genetic algorithm.
+//!
+//! All operators work on the free (non-locked) cards of a deck and
+//! carry locked cards over unchanged.
+
+use super::GeneticAlgorithm;
+use crate::cardgame::{CardId, Deck};
+
+use clap::ValueEnum;
+use itertools::Itertools;
+use rand::Rng;
+use rand::seq::{IndexedRandom, SliceRandom};
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+
+/// How two parent decks are combined into a child deck.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CrossoverKind {
+  /// Takes each position of the deck from one parent or the other.
+  #[default]
+  Positional,
+  /// Takes the number of copies of each card from one parent or the
+  /// other.
+  Uniform,
+  /// Takes all of the Minions of each archetype (and all of the
+  /// Effects) from one parent or the other.
+  ArchetypeBlock,
+}
+
+/// How a single card in a deck is replaced during mutation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MutationKind {
+  /// Replaces the card with any allowed card. The resulting deck
+  /// might not be valid.
+  #[default]
+  Random,
+  /// Replaces the card with any allowed card that keeps the deck
+  /// valid.
+  Valid,
+  /// Replaces the card with a card sharing one of its archetypes
+  /// (or, for Effects, another Effect) that keeps the deck valid.
+  SameArchetype,
+  /// Replaces the card with a card of the same star cost that keeps
+  /// the deck valid.
+  SameStarCost,
+}
+
+impl GeneticAlgorithm<'_> {
+  /// Combines the non-locked cards of two decks, according to the
+  /// configured crossover operator. The result might not be valid.
+  pub(super) fn splice(&mut self, deck1: &[CardId], deck2: &[CardId]) -> Deck {
+    let free1 = self.constraints.free_cards(deck1);
+    let free2 = self.constraints.free_cards(deck2);
+    let target_len = usize::min(free1.len(), free2.len());
+    let mut free_cards = match self.args.crossover {
+      CrossoverKind::Positional => {
+        (0..target_len)
+          .map(|i| if self.random.random() { free1[i] } else { free2[i] })
+          .collect()
+      }
+      CrossoverKind::Uniform => {
+        let counts1 = card_counts(&free1);
+        let counts2 = card_counts(&free2);
+        let mut child = Vec::with_capacity(target_len);
+        for card in counts1.keys().chain(counts2.keys()).unique() {
+          let counts = if self.random.random() { &counts1 } else { &counts2 };
+          let count = counts.get(card).copied().unwrap_or(0);
+          child.extend(std::iter::repeat_n(*card, count));
+        }
+        self.fix_length(child, &free1, &free2, target_len)
+      }
+      CrossoverKind::ArchetypeBlock => {
+        let blocks1 = self.archetype_blocks(&free1);
+        let blocks2 = self.archetype_blocks(&free2);
+        let mut child = Vec::with_capacity(target_len);
+        for block in blocks1.keys().chain(blocks2.keys()).unique() {
+          let blocks = if self.random.random() { &blocks1 } else { &blocks2 };
+          if let Some(cards) = blocks.get(block) {
+            child.extend_from_slice(cards);
+          }
+        }
+        self.fix_length(child, &free1, &free2, target_len)
+      }
+    };
+    free_cards.shuffle(&mut self.random);
+    self.constraints.locked().iter().copied().chain(free_cards).collect()
+  }
+
+  /// Replaces one non-locked card, according to the configured
+  /// mutation operator. Validity-preserving operators leave the deck
+  /// unchanged if no suitable replacement exists.
+  pub(super) fn mutate(&mut self, deck: &mut Deck) {
+    let mut free_cards = self.constraints.free_cards(deck.as_ref());
+    if free_cards.is_empty() {
+      return;
+    }
+    let index = self.random.random_range(0..free_cards.len());
+    let old_card = free_cards[index];
+    if self.args.mutation == MutationKind::Random {
+      let Some(new_card_id) = self.constraints.allowed().choose(&mut self.random) else { return; };
+      free_cards[index] = *new_card_id;
+      *deck = self.constraints.locked().iter().copied().chain(free_cards).collect();
+      return;
+    }
+
+    let mut candidates = self.constraints.allowed().iter()
+      .copied()
+      .filter(|&card| card != old_card && self.is_similar_card(old_card, card))
+      .collect::<Vec<_>>();
+    candidates.shuffle(&mut self.random);
+    for candidate in candidates {
+      free_cards[index] = candidate;
+      let new_deck = self.constraints.locked().iter().copied().chain(free_cards.iter().copied()).collect::<Deck>();
+      if self.is_reasonable_deck(new_deck.as_ref()) {
+        *deck = new_deck;
+        return;
+      }
+    }
+  }
+
+  fn is_similar_card(&self, old_card: CardId, new_card: CardId) -> bool {
+    let (Some(old_info), Some(new_info)) = (self.card_pool.get(old_card), self.card_pool.get(new_card)) else {
+      return false;
+    };
+    match self.args.mutation {
+      MutationKind::Random | MutationKind::Valid => true,
+      MutationKind::SameArchetype => {
+        if old_info.archetypes.is_empty() {
+          new_info.archetypes.is_empty()
+        } else {
+          old_info.archetypes.iter().any(|archetype| new_info.archetypes.contains(archetype))
+        }
+      }
+      MutationKind::SameStarCost => old_info.star_cost == new_info.star_cost,
+    }
+  }
+
+  /// Groups cards by their first archetype. Effects, which have no
+  /// archetypes, are grouped under `None`.
+  fn archetype_blocks(&self, cards: &[CardId]) -> BTreeMap<Option<i64>, Vec<CardId>> {
+    let mut blocks: BTreeMap<Option<i64>, Vec<CardId>> = BTreeMap::new();
+    for card in cards {
+      let archetype = self.card_pool.get(*card).and_then(|info| info.archetypes.first().copied());
+      blocks.entry(archetype).or_default().push(*card);
+    }
+    blocks
+  }
+
+  /// Brings a child produced by a multiset crossover to the target
+  /// length, by dropping random cards or by adding random cards from
+  /// the parents. Cards are only added up to the larger of the two
+  /// parents' counts.
+  fn fix_length(&mut self, mut child: Vec<CardId>, parent1: &[CardId], parent2: &[CardId], target_len: usize) -> Vec<CardId> {
+    child.shuffle(&mut self.random);
+    child.truncate(target_len);
+    if child.len() < target_len {
+      let child_counts = card_counts(&child);
+      let counts1 = card_counts(parent1);
+      let counts2 = card_counts(parent2);
+      let mut remaining = counts1.keys().chain(counts2.keys())
+        .unique()
+        .flat_map(|card| {
+          let max_count = usize::max(counts1.get(card).copied().unwrap_or(0), counts2.get(card).copied().unwrap_or(0));
+          let extra = max_count.saturating_sub(child_counts.get(card).copied().unwrap_or(0));
+          std::iter::repeat_n(*card, extra)
+        })
+        .collect::<Vec<_>>();
+      remaining.shuffle(&mut self.random);
+      let needed = target_len - child.len();
+      child.extend(remaining.into_iter().take(needed));
+    }
+    child
+  }
+}
+
+/// Card counts, ordered by card ID so that iteration is
+/// deterministic.
+pub(super) fn card_counts(cards: &[CardId]) -> BTreeMap<CardId, usize> {
+  let mut counts = BTreeMap::new();
+  for card in cards {
+    *counts.entry(*card).or_insert(0) += 1;
+  }
+  counts
+}
@@ -0,0 +1,204 @@
+//! Machine-readable per-generation statistics of a genetic algorithm
+//! run, for plotting convergence after the fact.
+
+use super::checkpoint::RankedDeck;
+use crate::cardgame::CardId;
+
+use itertools::Itertools;
+use serde::Serialize;
+use thiserror::Error;
+
+use std::collections::BTreeMap;
+use std::io::{self, BufWriter, Write};
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+const CSV_HEADER: &str = "generation,wall_time_secs,draws,errors,bottom_seat_wins,top_seat_wins,seat_advantage,seat_advantage_standard_error,score_min,score_max,score_mean,score_std_dev,score_median,mean_pairwise_distance,distinct_decks,distinct_archetype_profiles,top_decks,card_frequencies";
+
+/// Statistics for a single generation.
+#[derive(Debug, Clone, Serialize)]
+pub struct GenerationStats {
+  pub generation: usize,
+  pub wall_time_secs: f64,
+  pub draws: u64,
+  pub errors: u64,
+  pub bottom_seat_wins: u64,
+  pub top_seat_wins: u64,
+  /// The estimated log-odds advantage of the BOTTOM seat.
+  pub seat_advantage: f64,
+  pub seat_advantage_standard_error: f64,
+  pub scores: ScoreDistribution,
+  /// The highest-scoring decks, from highest to lowest score.
+  pub top_decks: Vec<RankedDeck>,
+  pub mean_pairwise_distance: f64,
+  pub distinct_decks: usize,
+  pub distinct_archetype_profiles: usize,
+  /// Fraction of decks containing each card at least once.
+  pub card_frequencies: BTreeMap<CardId, f64>,
+}
+
+/// Summary of the scores of every deck in a generation.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ScoreDistribution {
+  pub min: f64,
+  pub max: f64,
+  pub mean: f64,
+  pub std_dev: f64,
+  pub median: f64,
+}
+
+/// The format of a statistics file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsFormat {
+  /// One JSON object per line.
+  JsonLines,
+  /// One row per generation, with nested fields flattened into
+  /// strings.
+  Csv,
+}
+
+/// Appends generation statistics to a file.
+#[derive(Debug)]
+pub struct StatsWriter {
+  file: BufWriter<File>,
+  format: StatsFormat,
+}
+
+#[derive(Debug, Error)]
+pub enum StatsError {
+  #[error("{0}")]
+  IoError(#[from] io::Error),
+  #[error("{0}")]
+  JsonError(#[from] serde_json::Error),
+}
+
+impl GenerationStats {
+  fn to_csv_row(&self) -> String {
+    let top_decks = self.top_decks.iter()
+      .map(|ranked| format!("{:.4}: {}", ranked.score, ranked.deck))
+      .join(" | ");
+    let card_frequencies = self.card_frequencies.iter()
+      .map(|(card, frequency)| format!("{card}={frequency:.4}"))
+      .join(" ");
+    let fields = [
+      self.generation.to_string(),
+      format!("{:.3}", self.wall_time_secs),
+      self.draws.to_string(),
+      self.errors.to_string(),
+      self.bottom_seat_wins.to_string(),
+      self.top_seat_wins.to_string(),
+      self.seat_advantage.to_string(),
+      self.seat_advantage_standard_error.to_string(),
+      self.scores.min.to_string(),
+      self.scores.max.to_string(),
+      self.scores.mean.to_string(),
+      self.scores.std_dev.to_string(),
+      self.scores.median.to_string(),
+      self.mean_pairwise_distance.to_string(),
+      self.distinct_decks.to_string(),
+      self.distinct_archetype_profiles.to_string(),
+      csv_quote(&top_decks),
+      csv_quote(&card_frequencies),
+    ];
+    fields.join(",")
+  }
+}
+
+impl ScoreDistribution {
+  pub fn new(scores: &[f64]) -> Self {
+    if scores.is_empty() {
+      return ScoreDistribution::default();
+    }
+    let mut sorted = scores.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let len = sorted.len();
+    let mean = sorted.iter().sum::<f64>() / len as f64;
+    let variance = sorted.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / len as f64;
+    let median = if len.is_multiple_of(2) {
+      (sorted[len / 2 - 1] + sorted[len / 2]) / 2.0
+    } else {
+      sorted[len / 2]
+    };
+    ScoreDistribution {
+      min: sorted[0],
+      max: sorted[len - 1],
+      mean,
+      std_dev: variance.sqrt(),
+      median,
+    }
+  }
+}
+
+impl StatsFormat {
+  /// Files ending in `.csv` are written as CSV. Anything else is
+  /// written as JSON lines.
+  pub fn from_path(path: &Path) -> Self {
+    match path.extension() {
+      Some(ext) if ext.eq_ignore_ascii_case("csv") => StatsFormat::Csv,
+      _ => StatsFormat::JsonLines,
+    }
+  }
+}
+
+impl StatsWriter {
+  /// Opens a statistics file. If `append` is true, records are added
+  /// to the end of any existing file (as when resuming a run);
+  /// otherwise, the file is truncated.
+  pub fn open(path: impl AsRef<Path>, append: bool) -> Result<Self, StatsError> {
+    let path = path.as_ref();
+    let format = StatsFormat::from_path(path);
+    let file = OpenOptions::new()
+      .create(true)
+      .write(true)
+      .append(append)
+      .truncate(!append)
+      .open(path)?;
+    let is_empty = file.metadata()?.len() == 0;
+    let mut writer = StatsWriter { file: BufWriter::new(file), format };
+    if format == StatsFormat::Csv && is_empty {
+      writeln!(writer.file, "{CSV_HEADER}")?;
+    }
+    Ok(writer)
+  }
+
+  /// Writes one record and flushes it, so that the file can be read
+  /// while the run is in progress.
+  pub fn write(&mut self, stats: &GenerationStats) -> Result<(), StatsError> {
+    match self.format {
+      StatsFormat::JsonLines => {
+        serde_json::to_writer(&mut self.file, stats)?;
+        writeln!(self.file)?;
+      }
+      StatsFormat::Csv => {
+        writeln!(self.file, "{}", stats.to_csv_row())?;
+      }
+    }
+    self.file.flush()?;
+    Ok(())
+  }
+}
+
+fn csv_quote(field: &str) -> String {
+  format!("\"{}\"", field.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_score_distribution() {
+    let distribution = ScoreDistribution::new(&[3.0, 1.0, 4.0, 2.0]);
+    assert_eq!(distribution.min, 1.0);
+    assert_eq!(distribution.max, 4.0);
+    assert_eq!(distribution.mean, 2.5);
+    assert_eq!(distribution.median, 2.5);
+  }
+
+  #[test]
+  fn test_stats_format_from_path() {
+    assert_eq!(StatsFormat::from_path(Path::new("stats.CSV")), StatsFormat::Csv);
+    assert_eq!(StatsFormat::from_path(Path::new("stats.jsonl")), StatsFormat::JsonLines);
+    assert_eq!(StatsFormat::from_path(Path::new("stats")), StatsFormat::JsonLines);
+  }
+}
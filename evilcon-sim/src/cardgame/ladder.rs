@@ -0,0 +1,187 @@
+//! A persistent Elo rating ladder, which accumulates the results of
+//! many separate runs of the simulator.
+
+use super::{Deck, GameResult, GameWinner};
+use super::yaml_file::{YamlFile, YamlFileError};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Rating of a deck which has not yet played on the ladder.
+pub const INITIAL_RATING: f64 = 1500.0;
+/// Maximum rating change from a single game.
+pub const K_FACTOR: f64 = 24.0;
+
+/// Every deck which has played on the ladder, keyed by
+/// [`deck_key`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Ladder {
+  pub decks: BTreeMap<String, LadderEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LadderEntry {
+  pub name: String,
+  pub deck: Deck,
+  pub rating: f64,
+  /// Results against each opponent, keyed by the opponent's
+  /// [`deck_key`].
+  pub history: BTreeMap<String, Record>,
+}
+
+/// Win, loss, and draw counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+  pub wins: u64,
+  pub losses: u64,
+  pub draws: u64,
+}
+
+/// A canonical key for a deck, which does not depend on the order of
+/// its cards. The key is a 64-bit FNV-1a hash of the sorted card IDs,
+/// so it is stable across platforms and compiler versions.
+pub fn deck_key(deck: &Deck) -> String {
+  const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+  const FNV_PRIME: u64 = 0x100000001b3;
+
+  let mut cards = deck.0.clone();
+  cards.sort();
+  let mut hash = FNV_OFFSET_BASIS;
+  for card in cards {
+    for byte in card.0.to_le_bytes() {
+      hash ^= u64::from(byte);
+      hash = hash.wrapping_mul(FNV_PRIME);
+    }
+  }
+  format!("{hash:016x}")
+}
+
+impl YamlFile for Ladder {}
+
+impl Ladder {
+  /// Reads the ladder at the given path, or returns an empty ladder if
+  /// the file does not exist yet.
+  pub fn read_or_default(path: impl AsRef<Path>) -> Result<Self, YamlFileError> {
+    match Self::read_from_path(path) {
+      Err(err) if err.is_not_found() => Ok(Self::default()),
+      result => result,
+    }
+  }
+
+  /// Adds a deck to the ladder if it is not already present, and
+  /// returns its key. If a name is given, it replaces the deck's
+  /// current name. Decks added without a name are named after their
+  /// key.
+  pub fn register(&mut self, deck: &Deck, name: Option<&str>) -> String {
+    let key = deck_key(deck);
+    let entry = self.decks.entry(key.clone()).or_insert_with(|| LadderEntry {
+      name: key.clone(),
+      deck: deck.clone(),
+      rating: INITIAL_RATING,
+      history: BTreeMap::new(),
+    });
+    if let Some(name) = name {
+      entry.name = name.to_owned();
+    }
+    key
+  }
+
+  /// Updates the ratings of two registered decks after a game between
+  /// them. Games between a deck and itself are ignored.
+  pub fn record_game(&mut self, bottom_key: &str, top_key: &str, result: GameResult) {
+    if bottom_key == top_key {
+      return;
+    }
+    let bottom_score = match result {
+      GameResult::Win(GameWinner::Bottom) => 1.0,
+      GameResult::Win(GameWinner::Top) => 0.0,
+      GameResult::Draw { .. } => 0.5,
+    };
+    let bottom_rating = self.decks[bottom_key].rating;
+    let top_rating = self.decks[top_key].rating;
+    let bottom_expected = expected_score(bottom_rating, top_rating);
+    let change = K_FACTOR * (bottom_score - bottom_expected);
+
+    let bottom = self.decks.get_mut(bottom_key).expect("Deck is not registered");
+    bottom.rating += change;
+    bottom.history.entry(top_key.to_owned()).or_default().add(bottom_score);
+    let top = self.decks.get_mut(top_key).expect("Deck is not registered");
+    top.rating -= change;
+    top.history.entry(bottom_key.to_owned()).or_default().add(1.0 - bottom_score);
+  }
+
+  /// Decks from highest to lowest rating.
+  pub fn standings(&self) -> Vec<(&str, &LadderEntry)> {
+    let mut standings = self.decks.iter().map(|(key, entry)| (key.as_str(), entry)).collect::<Vec<_>>();
+    standings.sort_by(|(_, a), (_, b)| b.rating.partial_cmp(&a.rating).unwrap());
+    standings
+  }
+
+  pub fn log_standings(&self) {
+    let name_width = self.decks.values().map(|entry| entry.name.len()).max().unwrap_or(0).max(4);
+    tracing::info!("{:>4}  {:name_width$}  {:>16} {:>7} {:>6} {:>6} {:>6}", "Rank", "Deck", "Key", "Rating", "Wins", "Losses", "Draws");
+    for (rank, (key, entry)) in self.standings().into_iter().enumerate() {
+      let record = entry.record();
+      tracing::info!("{:>4}  {:name_width$}  {key:>16} {:>7.1} {:>6} {:>6} {:>6}",
+                     rank + 1, entry.name, entry.rating, record.wins, record.losses, record.draws);
+    }
+  }
+}
+
+impl LadderEntry {
+  /// The deck's record against all opponents.
+  pub fn record(&self) -> Record {
+    self.history.values().fold(Record::default(), |total, record| Record {
+      wins: total.wins + record.wins,
+      losses: total.losses + record.losses,
+      draws: total.draws + record.draws,
+    })
+  }
+}
+
+impl Record {
+  fn add(&mut self, score: f64) {
+    if score == 1.0 {
+      self.wins += 1;
+    } else if score == 0.0 {
+      self.losses += 1;
+    } else {
+      self.draws += 1;
+    }
+  }
+}
+
+/// The expected score of a player rated `rating` against a player
+/// rated `opponent_rating`.
+fn expected_score(rating: f64, opponent_rating: f64) -> f64 {
+  1.0 / (1.0 + 10f64.powf((opponent_rating - rating) / 400.0))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_deck_key_ignores_order() {
+    let deck1: Deck = "1, 2, 3".parse().unwrap();
+    let deck2: Deck = "3, 1, 2".parse().unwrap();
+    let deck3: Deck = "1, 2, 4".parse().unwrap();
+    assert_eq!(deck_key(&deck1), deck_key(&deck2));
+    assert_ne!(deck_key(&deck1), deck_key(&deck3));
+  }
+
+  #[test]
+  fn test_record_game() {
+    let mut ladder = Ladder::default();
+    let a = ladder.register(&"1, 2, 3".parse().unwrap(), Some("A"));
+    let b = ladder.register(&"4, 5, 6".parse().unwrap(), None);
+    ladder.record_game(&a, &b, GameResult::Win(GameWinner::Bottom));
+    assert_eq!(ladder.decks[&a].rating, INITIAL_RATING + K_FACTOR / 2.0);
+    assert_eq!(ladder.decks[&b].rating, INITIAL_RATING - K_FACTOR / 2.0);
+    assert_eq!(ladder.decks[&a].record(), Record { wins: 1, losses: 0, draws: 0 });
+    assert_eq!(ladder.decks[&b].history[&a], Record { wins: 0, losses: 1, draws: 0 });
+    assert_eq!(ladder.standings()[0].1.name, "A");
+  }
+}
@@ -1,19 +1,36 @@
 
+pub mod agent;
+pub mod card_pool;
+pub mod card_stats;
 pub mod code;
 pub mod deck;
 pub mod genetic;
+pub mod ladder;
+pub mod observer;
+pub mod outcome;
+pub mod rules;
+pub mod significance;
+pub mod tempo;
+pub mod tournament;
+pub mod trace;
+pub mod yaml_file;
 
+pub use agent::{AgentSpec, LookaheadPriorities, PlayerAgent, PlayerAgents, PlayerAgentsArgs};
 pub use deck::{Deck, CardId, DECK_SIZE};
+pub use outcome::{GameOutcome, GameResult, EndCondition, PlayerOutcome};
+pub use rules::{GameRules, GameRulesArgs, TURN_LIMIT};
 
 use crate::interpreter::eval::{SuperglobalState, EvaluatorState};
 use crate::interpreter::mocking::{PLAYING_FIELD_RES_PATH, ENDGAME_VARIABLE, TURN_TRANSITIONS_RES_PATH,
-                                  DEFAULT_FORT_DEFENSE, SECOND_PLAYER_FORT_ADVANTAGE};
+                                  DESTINY_SONG_LIMIT_VARIABLE};
 use crate::interpreter::mocking::codex::CODEX_GD_NAME;
 use crate::interpreter::error::EvalError;
 use crate::interpreter::value::{SimpleValue, Value};
 use code::serialize_game_code;
+use observer::{GameObserver, GameObserverFactory, ObserverSet};
 
 use thiserror::Error;
+use serde::Serialize;
 use rand_chacha::ChaCha8Rng;
 use rand::{RngCore, SeedableRng};
 use rand::seq::SliceRandom;
@@ -21,20 +38,22 @@ use strum_macros::Display;
 
 use std::sync::Arc;
 use std::error::Error as StdError;
+use std::fmt::{self, Debug, Formatter};
 
-pub const LOOKAHEAD_AI_AGENT_PATH: &str = "res://card_game/playing_field/player_agent/lookahead_ai_agent/lookahead_ai_agent.gd";
-
-// TODO Might make this a CLI arg, if we need it
-pub const TURN_LIMIT: usize = 200;
-
-/// Newtype wrapper around a superglobal state, indicating that it has
-/// loaded the requisite files in order to play the card game. This
-/// condition is unchecked.
+/// Wrapper around a superglobal state, indicating that it has loaded
+/// the requisite files in order to play the card game. This condition
+/// is unchecked.
+///
+/// The engine also holds the observer factories which are notified of
+/// the events of every game it plays.
 ///
-/// `GameEngine` is cheap to clone, as it maintains an `Arc`
+/// `GameEngine` is cheap to clone, as it maintains `Arc`s
 /// internally.
-#[derive(Debug, Clone)]
-pub struct GameEngine(pub Arc<SuperglobalState>);
+#[derive(Clone)]
+pub struct GameEngine {
+  superglobals: Arc<SuperglobalState>,
+  observer_factories: Vec<Arc<dyn GameObserverFactory>>,
+}
 
 /// The contents of the players' decks at the start of a card game.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -43,70 +62,153 @@ pub struct CardGameEnv<T> {
   pub top_deck: T,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize)]
 #[strum(serialize_all = "UPPERCASE")]
+#[serde(rename_all = "UPPERCASE")]
 pub enum GameWinner {
   Bottom,
   Top,
 }
 
+impl<T> CardGameEnv<T> {
+  /// Exchanges the two decks, so that the bottom player's deck goes
+  /// second and vice versa.
+  pub fn swap_sides(self) -> Self {
+    CardGameEnv {
+      bottom_deck: self.top_deck,
+      top_deck: self.bottom_deck,
+    }
+  }
+}
+
 #[derive(Debug, Clone, Error)]
 #[non_exhaustive]
 pub enum GameEngineError {
   #[error("{0}")]
   EvalError(#[from] EvalError),
-  #[error("Deck of incorrect size was passed, decks must have size 20")]
-  BadDeckSize,
+  #[error("Deck of size {actual} was passed, decks must have size {expected}")]
+  BadDeckSize { expected: usize, actual: usize },
+  #[error("{0}")]
+  GameCodeError(#[from] code::SerializeError),
   #[error("Got unknown result from card game: {0:?}")]
   UnknownResult(String),
 }
 
 impl GameEngine {
   pub fn new(state: SuperglobalState) -> Self {
-    Self(Arc::new(state))
+    GameEngine {
+      superglobals: Arc::new(state),
+      observer_factories: Vec::new(),
+    }
+  }
+
+  pub fn superglobals(&self) -> &Arc<SuperglobalState> {
+    &self.superglobals
+  }
+
+  /// Registers a factory, which creates an observer for every game
+  /// subsequently played by this engine (or its clones).
+  pub fn add_observer_factory(&mut self, factory: Arc<dyn GameObserverFactory>) {
+    self.observer_factories.push(factory);
   }
 
   pub fn play_game_seeded<T: AsRef<[CardId]>>(
     &self,
     env: &CardGameEnv<T>,
+    agents: &PlayerAgents,
+    rules: &GameRules,
     seed: u64,
-  ) -> Result<GameWinner, GameEngineError> {
+  ) -> Result<GameOutcome, GameEngineError> {
     tracing::debug!("Running game with code: {}", serialize_game_code(seed, env).unwrap_or("(failed to serialize)".to_string()));
 
     let random = ChaCha8Rng::seed_from_u64(seed);
-    self.play_game(env, random)
+    self.play_game(env, agents, rules, random)
+  }
+
+  /// As [`GameEngine::play_game_seeded`], notifying `observers` of
+  /// the game's events in addition to the engine's own observers.
+  pub fn play_game_observed<T: AsRef<[CardId]>>(
+    &self,
+    env: &CardGameEnv<T>,
+    agents: &PlayerAgents,
+    rules: &GameRules,
+    seed: u64,
+    observers: Vec<Box<dyn GameObserver>>,
+  ) -> Result<GameOutcome, GameEngineError> {
+    tracing::debug!("Running observed game with code: {}", serialize_game_code(seed, env).unwrap_or("(failed to serialize)".to_string()));
+
+    let random = ChaCha8Rng::seed_from_u64(seed);
+    self.play_game_impl(env, agents, rules, random, observers)
   }
 
   pub fn play_game<T: AsRef<[CardId]>>(
     &self,
     env: &CardGameEnv<T>,
+    agents: &PlayerAgents,
+    rules: &GameRules,
     random: impl RngCore + 'static,
-  ) -> Result<GameWinner, GameEngineError> {
-    if env.bottom_deck.as_ref().len() != DECK_SIZE || env.top_deck.as_ref().len() != DECK_SIZE {
-      return Err(GameEngineError::BadDeckSize);
+  ) -> Result<GameOutcome, GameEngineError> {
+    self.play_game_impl(env, agents, rules, random, Vec::new())
+  }
+
+  fn play_game_impl<T: AsRef<[CardId]>>(
+    &self,
+    env: &CardGameEnv<T>,
+    agents: &PlayerAgents,
+    rules: &GameRules,
+    random: impl RngCore + 'static,
+    extra_observers: Vec<Box<dyn GameObserver>>,
+  ) -> Result<GameOutcome, GameEngineError> {
+    let observers = self.observer_factories.iter()
+      .map(|factory| factory.create_observer())
+      .chain(extra_observers)
+      .collect::<Vec<_>>();
+    let observers = ObserverSet::new(observers);
+    let decks = CardGameEnv { bottom_deck: env.bottom_deck.as_ref(), top_deck: env.top_deck.as_ref() };
+    observers.notify(|observer| observer.on_game_start(&decks));
+    let outcome = self.run_game(env, agents, rules, random, observers.clone());
+    observers.notify(|observer| observer.on_game_end(outcome.as_ref()));
+    outcome
+  }
+
+  fn run_game<T: AsRef<[CardId]>>(
+    &self,
+    env: &CardGameEnv<T>,
+    agents: &PlayerAgents,
+    rules: &GameRules,
+    random: impl RngCore + 'static,
+    observers: ObserverSet,
+  ) -> Result<GameOutcome, GameEngineError> {
+    for deck in [env.bottom_deck.as_ref(), env.top_deck.as_ref()] {
+      if deck.len() != rules.deck_size {
+        return Err(GameEngineError::BadDeckSize { expected: rules.deck_size, actual: deck.len() });
+      }
     }
-    let (state, playing_field) = self.initialize_game(env, random)?;
-    let Some(turn_transitions) = self.0.get_file(TURN_TRANSITIONS_RES_PATH) else {
+    let (state, playing_field) = self.initialize_game(env, agents, rules, random, observers)?;
+    let Some(turn_transitions) = self.superglobals.get_file(TURN_TRANSITIONS_RES_PATH) else {
       return Err(EvalError::UndefinedClass(String::from(TURN_TRANSITIONS_RES_PATH)).into());
     };
-    state.call_function_on_class(&turn_transitions, "play_full_game", vec![playing_field.clone(), Value::from(TURN_LIMIT as i64)])?;
-    let outcome = playing_field.get_value_raw(ENDGAME_VARIABLE, &self.0)?;
-    let Value::String(outcome) = outcome else {
-      return Err(GameEngineError::UnknownResult(format!("{outcome:?}")));
+    state.call_function_on_class(&turn_transitions, "play_full_game", vec![playing_field.clone(), Value::from(rules.turn_limit as i64)])?;
+    // If nobody has won by the time play_full_game returns, then we
+    // hit the turn limit.
+    let result = match playing_field.get_value_raw(ENDGAME_VARIABLE, &self.superglobals)? {
+      Value::Null => GameResult::Draw { turn_limit: rules.turn_limit },
+      Value::String(outcome) if outcome == "TOP" => GameResult::Win(GameWinner::Top),
+      Value::String(outcome) if outcome == "BOTTOM" => GameResult::Win(GameWinner::Bottom),
+      outcome => { return Err(GameEngineError::UnknownResult(format!("{outcome:?}"))); }
     };
-    match &*outcome {
-      "TOP" => Ok(GameWinner::Top),
-      "BOTTOM" => Ok(GameWinner::Bottom),
-      outcome => Err(GameEngineError::UnknownResult(format!("{outcome:?}"))),
-    }
+    Ok(GameOutcome::read_from(&state, &playing_field, rules, result)?)
   }
 
   fn initialize_game<T: AsRef<[CardId]>>(
     &self,
     env: &CardGameEnv<T>,
+    agents: &PlayerAgents,
+    rules: &GameRules,
     random: impl RngCore + 'static,
+    observers: ObserverSet,
   ) -> Result<(EvaluatorState, Value), EvalError> {
-    let state = EvaluatorState::new(Arc::clone(&self.0), random);
+    let state = EvaluatorState::new(Arc::clone(&self.superglobals), random).with_observers(observers);
     let playing_field_class = state.superglobal_state().get_file(PLAYING_FIELD_RES_PATH)
       .ok_or_else(|| EvalError::UndefinedClass(String::from(PLAYING_FIELD_RES_PATH)))?;
     let playing_field = state.call_function_on_class(&playing_field_class, "new", Vec::new())?;
@@ -119,23 +221,38 @@ impl GameEngine {
       install_deck(&state, &playing_field, "TOP", top_deck)?;
     }
     {
-      let bottom_agent = create_ai_agent(&state)?;
+      let bottom_agent = agents.bottom.create_agent(&state)?;
       install_player_agent(&state, &playing_field, "BOTTOM", bottom_agent)?;
     }
     {
-      let top_agent = create_ai_agent(&state)?;
+      let top_agent = agents.top.create_agent(&state)?;
       install_player_agent(&state, &playing_field, "TOP", top_agent)?;
     }
-    {
-      const SECOND_PLAYER_FORT_DEFENSE: Value = Value::Int(DEFAULT_FORT_DEFENSE + SECOND_PLAYER_FORT_ADVANTAGE);
-      let top_stats = playing_field.get_value("__evilconsim_statspanel_top", state.superglobal_state())?;
-      top_stats.set_value("max_fort_defense", SECOND_PLAYER_FORT_DEFENSE, state.superglobal_state())?;
-      top_stats.set_value("fort_defense", SECOND_PLAYER_FORT_DEFENSE, state.superglobal_state())?;
-    }
+    install_rules(&state, &playing_field, "__evilconsim_statspanel_bottom", rules, rules.fort_defense)?;
+    install_rules(&state, &playing_field, "__evilconsim_statspanel_top", rules, rules.second_player_fort_defense())?;
     Ok((state, playing_field))
   }
 }
 
+impl Debug for GameEngine {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    f.debug_struct("GameEngine")
+      .field("superglobals", &self.superglobals)
+      .field("observer_factories", &self.observer_factories.len())
+      .finish()
+  }
+}
+
+/// Derives the seed of an individual game from a master seed and the
+/// game's index within a batch. Each index selects a distinct ChaCha8
+/// stream, so games in a batch differ from one another while the
+/// whole batch can be reproduced from the master seed alone.
+pub fn derive_game_seed(master_seed: u64, index: u64) -> u64 {
+  let mut rng = ChaCha8Rng::seed_from_u64(master_seed);
+  rng.set_stream(index);
+  rng.next_u64()
+}
+
 impl GameEngineError {
   pub fn root_cause(&self) -> &dyn StdError {
     if let GameEngineError::EvalError(e) = self {
@@ -147,12 +264,11 @@ impl GameEngineError {
 }
 
 fn create_deck_of_cards(state: &EvaluatorState, cards: &[CardId]) -> Result<Value, EvalError> {
-  assert!(cards.len() == DECK_SIZE, "Wrong deck size");
   let Some(SimpleValue::ClassRef(codex)) = state.superglobal_state().get_var(CODEX_GD_NAME) else {
     return Err(EvalError::UnknownClass(CODEX_GD_NAME.to_string()));
   };
   let mut cards = cards.iter()
-    .map(|c| state.call_function_on_class(&codex, "get_entity", vec![Value::from(c.0)]))
+    .map(|c| state.call_function_on_class(codex, "get_entity", vec![Value::from(c.0)]))
     .collect::<Result<Vec<_>, _>>()?;
   state.do_random(|rng| cards.shuffle(rng));
   Ok(Value::new_array(cards))
@@ -165,16 +281,18 @@ fn install_deck(state: &EvaluatorState, playing_field: &Value, player: &str, dec
   Ok(())
 }
 
-fn create_ai_agent(state: &EvaluatorState) -> Result<Value, EvalError> {
-  let Some(class) = state.superglobal_state().get_file(LOOKAHEAD_AI_AGENT_PATH) else {
-    return Err(EvalError::UndefinedClass(LOOKAHEAD_AI_AGENT_PATH.to_string()));
-  };
-  state.call_function_on_class(&class, "new", Vec::new())
+fn install_rules(state: &EvaluatorState, playing_field: &Value, stats_var: &str, rules: &GameRules, fort_defense: i64) -> Result<(), EvalError> {
+  let stats = playing_field.get_value(stats_var, state.superglobal_state())?;
+  stats.set_value("max_fort_defense", Value::from(fort_defense), state.superglobal_state())?;
+  stats.set_value("fort_defense", Value::from(fort_defense), state.superglobal_state())?;
+  stats.set_value(DESTINY_SONG_LIMIT_VARIABLE, Value::from(rules.destiny_song_limit), state.superglobal_state())?;
+  Ok(())
 }
 
 fn install_player_agent(state: &EvaluatorState, playing_field: &Value, player: &str, agent: Value) -> Result<(), EvalError> {
-  // NOTE: Doesn't bother to call added_to_playing_field, since the
-  // lookahead agent doesn't use it.
+  // NOTE: Doesn't bother to call added_to_playing_field. The only
+  // supported agent that uses it is the Monte Carlo agent, which only
+  // uses it to maintain a card watcher it never consults.
   let var_name = match player {
     "BOTTOM" => "_bottom_agent",
     "TOP" => "_top_agent",
@@ -185,10 +303,14 @@ fn install_player_agent(state: &EvaluatorState, playing_field: &Value, player: &
   Ok(())
 }
 
-impl From<code::SerializeError> for GameEngineError {
-  fn from(e: code::SerializeError) -> Self {
-    match e {
-      code::SerializeError::BadDeckSize => GameEngineError::BadDeckSize,
-    }
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_derive_game_seed() {
+    assert_eq!(derive_game_seed(5, 0), derive_game_seed(5, 0));
+    assert_ne!(derive_game_seed(5, 0), derive_game_seed(5, 1));
+    assert_ne!(derive_game_seed(5, 0), derive_game_seed(6, 0));
   }
 }
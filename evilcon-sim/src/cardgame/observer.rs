@@ -0,0 +1,398 @@
+//! Rust-side hooks into a running card game.
+//!
+//! A [`GameObserver`] receives callbacks for the events of a single
+//! game: turns and phases, cards moving between zones, and changes to
+//! player and card stats. Observers are created per game by the
+//! [`GameObserverFactory`] instances registered on a
+//! [`GameEngine`](super::GameEngine), so analytics, traces, and
+//! invariant checks can be written without touching the interpreter.
+//!
+//! Events are driven from the augmented `CardGameApi` methods (see
+//! `driver.rs`), the mocked `Stats` class, and the turn loop. The
+//! card hooks of every card type are augmented as well, so that stat
+//! changes can be attributed to the card which caused them.
+
+use super::{CardGameEnv, CardId, GameEngineError, GameOutcome, GameWinner, PlayerOutcome};
+use crate::interpreter::eval::EvaluatorState;
+use crate::interpreter::error::EvalError;
+use crate::interpreter::value::Value;
+use crate::interpreter::operator::{expect_int, expect_string};
+
+use serde::Serialize;
+use strum_macros::{Display, IntoStaticStr, VariantArray};
+
+use std::cell::{Cell, RefCell};
+use std::fmt::{self, Debug, Formatter};
+use std::rc::Rc;
+
+/// Receives the events of a single game, in the order in which they
+/// happen. Every callback does nothing by default.
+///
+/// Callbacks must not assume that the event has taken effect yet:
+/// `CardGameApi` events are reported when the method is called,
+/// before its body runs.
+pub trait GameObserver {
+  /// Called before the game starts, with the contents of each
+  /// player's deck.
+  fn on_game_start(&mut self, _decks: &CardGameEnv<&[CardId]>) {}
+
+  /// Called after the turn number has been incremented at the start
+  /// of each full turn. Turns count from zero.
+  fn on_turn_start(&mut self, _turn: i64) {}
+
+  /// Called after both players have finished a full turn.
+  fn on_turn_end(&mut self, _turn: i64) {}
+
+  fn on_phase_start(&mut self, _phase: Phase, _player: GameWinner) {}
+
+  /// Called when a player's agent has finished playing cards, before
+  /// the End Phase, with a snapshot of that player's state. Any Evil
+  /// Points left at this point are lost in the End Phase.
+  fn on_play_phase_end(&mut self, _player: GameWinner, _state: &PlayerOutcome) {}
+
+  /// Called when a player attempts to draw cards from the deck. Fewer
+  /// cards may actually be drawn, if the player's hand is full or
+  /// their deck and discard pile are empty.
+  fn on_cards_drawn(&mut self, _player: GameWinner, _count: i64) {}
+
+  fn on_discard_pile_reshuffled(&mut self, _player: GameWinner) {}
+
+  /// Called for every action on a single card. By default, dispatches
+  /// to [`GameObserver::on_card_drawn`],
+  /// [`GameObserver::on_card_played`], and
+  /// [`GameObserver::on_card_destroyed`] as appropriate.
+  fn on_card_action(&mut self, player: GameWinner, action: CardAction, card: &ObservedCard) {
+    if action.is_play() {
+      self.on_card_played(player, action, card);
+    } else if action == CardAction::Draw {
+      self.on_card_drawn(player, card);
+    } else if action == CardAction::Destroy {
+      self.on_card_destroyed(player, card);
+    }
+  }
+
+  /// Called when a card moves from a player's deck to their hand.
+  /// Cards drawn by `draw_specific_card` are reported here as well as
+  /// by [`CardAction::DrawSpecific`].
+  fn on_card_drawn(&mut self, _player: GameWinner, _card: &ObservedCard) {}
+
+  /// Called when a card is played to the field by any means. `action`
+  /// indicates where the card came from.
+  fn on_card_played(&mut self, _player: GameWinner, _action: CardAction, _card: &ObservedCard) {}
+
+  /// Called when a card on the field is destroyed. `player` is the
+  /// card's owner.
+  fn on_card_destroyed(&mut self, _player: GameWinner, _card: &ObservedCard) {}
+
+  fn on_stat_changed(&mut self, _change: &StatChange) {}
+
+  /// Called once the game is over, with its outcome or the error
+  /// which ended it.
+  fn on_game_end(&mut self, _outcome: Result<&GameOutcome, &GameEngineError>) {}
+}
+
+/// Creates a fresh observer for each game played by an engine.
+/// Factories are shared between threads, so an observer which
+/// aggregates results across games should hold a handle to shared
+/// state (such as an `Arc<Mutex<_>>`) and merge into it in
+/// [`GameObserver::on_game_end`].
+pub trait GameObserverFactory: Send + Sync {
+  fn create_observer(&self) -> Box<dyn GameObserver>;
+}
+
+impl<F> GameObserverFactory for F
+where F: Fn() -> Box<dyn GameObserver> + Send + Sync {
+  fn create_observer(&self) -> Box<dyn GameObserver> {
+    self()
+  }
+}
+
+/// The observers of a single game, together with the card hooks
+/// executing in that game. Cloning an `ObserverSet` produces a handle
+/// to the same observers.
+#[derive(Clone, Default)]
+pub struct ObserverSet {
+  observers: Rc<RefCell<Vec<Box<dyn GameObserver>>>>,
+  /// The card hooks currently executing, with the `Card` object each
+  /// was called for, innermost last.
+  executing_hooks: Rc<RefCell<Vec<(CardHook, Value)>>>,
+  /// Depth of nested [`ObserverSet::muted`] calls.
+  muted_depth: Rc<Cell<usize>>,
+}
+
+/// The phases of a single player's turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Phase {
+  Draw,
+  Attack,
+  Morale,
+  Standby,
+  /// The player's agent plays cards.
+  Play,
+  End,
+}
+
+/// An action on a single card, as performed by one of the
+/// `CardGameApi` methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Display)]
+#[serde(rename_all = "snake_case")]
+pub enum CardAction {
+  #[strum(serialize = "Draw from deck")]
+  Draw,
+  #[strum(serialize = "Scry from deck")]
+  DrawSpecific,
+  #[strum(serialize = "Play from hand")]
+  PlayFromHand,
+  #[strum(serialize = "Play from deck")]
+  PlayFromDeck,
+  #[strum(serialize = "Play from nowhere (probably Mystery Box)")]
+  PlayFromNowhere,
+  #[strum(serialize = "Resurrect from discard pile")]
+  Resurrect,
+  #[strum(serialize = "Destroy")]
+  Destroy,
+  #[strum(serialize = "Discard from hand")]
+  Discard,
+  #[strum(serialize = "Move from discard to deck")]
+  MoveFromDiscardToDeck,
+  #[strum(serialize = "Create card")]
+  Create,
+  #[strum(serialize = "Copy card")]
+  Copy,
+  #[strum(serialize = "Exile")]
+  Exile,
+}
+
+/// A `CardType` method which runs a card's effects. Each converts to
+/// the name of the GDScript method, all of which take the playing
+/// field and the card as their first two arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Display, IntoStaticStr, VariantArray)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum CardHook {
+  OnPlay,
+  OnPlayBroadcasted,
+  OnEnterOwnership,
+  OnEnterOwnershipBroadcasted,
+  OnDrawPhase,
+  OnAttackPhase,
+  OnMoralePhase,
+  OnStandbyPhase,
+  OnEndPhase,
+  OnPreExpire,
+  OnExpire,
+  OnPreExpireBroadcasted,
+  OnExpireBroadcasted,
+  OnCardsDiscarded,
+}
+
+/// A stat of a player or of a card on the field. Player stats convert
+/// to the names of the corresponding fields on the stats panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Display, IntoStaticStr)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Stat {
+  EvilPoints,
+  FortDefense,
+  DestinySong,
+  Level,
+  Morale,
+}
+
+/// A change to a player or card stat.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatChange {
+  /// The player whose stat changed, or the owner of the card whose
+  /// stat changed.
+  pub player: GameWinner,
+  pub stat: Stat,
+  /// The card whose stat changed. Present only for card stats.
+  pub card: Option<ObservedCard>,
+  pub old_value: i64,
+  pub new_value: i64,
+  /// The innermost card hook which was executing when the stat
+  /// changed, if any.
+  pub source: Option<StatSource>,
+}
+
+/// The card hook responsible for a stat change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatSource {
+  pub hook: CardHook,
+  pub card: ObservedCard,
+  pub owner: GameWinner,
+}
+
+/// A card type, as reported to observers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ObservedCard {
+  pub id: CardId,
+  pub name: String,
+}
+
+impl CardAction {
+  /// Whether the action puts the card onto the field.
+  pub fn is_play(self) -> bool {
+    matches!(self, CardAction::PlayFromHand | CardAction::PlayFromDeck | CardAction::PlayFromNowhere | CardAction::Resurrect)
+  }
+}
+
+impl StatChange {
+  pub fn delta(&self) -> i64 {
+    self.new_value - self.old_value
+  }
+}
+
+impl ObserverSet {
+  pub fn new(observers: Vec<Box<dyn GameObserver>>) -> Self {
+    ObserverSet {
+      observers: Rc::new(RefCell::new(observers)),
+      executing_hooks: Rc::default(),
+      muted_depth: Rc::default(),
+    }
+  }
+
+  /// True if an event sent now would reach no observer, either
+  /// because there are none or because they are currently muted.
+  /// Callers may skip computing event details in that case.
+  pub fn is_inactive(&self) -> bool {
+    self.muted_depth.get() > 0 || self.observers.borrow().is_empty()
+  }
+
+  /// Calls `callback` on every observer. The callback must not call
+  /// back into the `ObserverSet`.
+  pub fn notify(&self, mut callback: impl FnMut(&mut dyn GameObserver)) {
+    if self.muted_depth.get() > 0 {
+      return;
+    }
+    for observer in self.observers.borrow_mut().iter_mut() {
+      callback(observer.as_mut());
+    }
+  }
+
+  /// Records that `hook` has started executing for `card`. Every call
+  /// must be matched by a call to [`ObserverSet::exit_hook`].
+  pub fn enter_hook(&self, hook: CardHook, card: Value) {
+    self.executing_hooks.borrow_mut().push((hook, card));
+  }
+
+  pub fn exit_hook(&self) {
+    self.executing_hooks.borrow_mut().pop();
+  }
+
+  /// Runs `body` without notifying any observers. This is used while
+  /// an AI agent plays out hypothetical games on a virtual playing
+  /// field, whose events are not part of the real game.
+  pub fn muted<R>(&self, body: impl FnOnce() -> R) -> R {
+    self.muted_depth.set(self.muted_depth.get() + 1);
+    let result = body();
+    self.muted_depth.set(self.muted_depth.get() - 1);
+    result
+  }
+
+  /// The innermost card hook currently executing, if any.
+  pub fn current_hook(&self) -> Option<(CardHook, Value)> {
+    self.executing_hooks.borrow().last().cloned()
+  }
+}
+
+impl Debug for ObserverSet {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    write!(f, "ObserverSet({} observer(s))", self.observers.borrow().len())
+  }
+}
+
+impl StatSource {
+  /// The innermost card hook currently executing in the game, if any.
+  pub fn current(state: &EvaluatorState) -> Result<Option<Self>, EvalError> {
+    let Some((hook, card)) = state.observers().current_hook() else {
+      return Ok(None);
+    };
+    Ok(Some(StatSource {
+      hook,
+      card: ObservedCard::from_card(state, &card)?,
+      owner: card_owner(state, &card)?,
+    }))
+  }
+}
+
+impl ObservedCard {
+  /// Reads the ID and title of a `CardType` object.
+  pub fn from_card_type(state: &EvaluatorState, card_type: &Value) -> Result<Self, EvalError> {
+    let id = expect_int("ObservedCard", &state.call_function_on(card_type, "get_id", Vec::new())?)?;
+    let name = state.call_function_on(card_type, "get_title", Vec::new())?;
+    Ok(ObservedCard { id: CardId(id), name: expect_string("ObservedCard", &name)?.to_owned() })
+  }
+
+  /// Reads the ID and title of the card type of a `Card` object.
+  pub fn from_card(state: &EvaluatorState, card: &Value) -> Result<Self, EvalError> {
+    let card_type = card.get_value("card_type", state.superglobal_state())?;
+    Self::from_card_type(state, &card_type)
+  }
+}
+
+/// Reads a player (`BOTTOM` or `TOP`) from a GDScript value.
+pub fn player_from_value(value: &Value) -> Result<GameWinner, EvalError> {
+  match expect_string("player_from_value", value)? {
+    "BOTTOM" => Ok(GameWinner::Bottom),
+    "TOP" => Ok(GameWinner::Top),
+    _ => Err(EvalError::domain_error("Bad card player")),
+  }
+}
+
+/// Reads the owner of a `Card` object.
+pub fn card_owner(state: &EvaluatorState, card: &Value) -> Result<GameWinner, EvalError> {
+  player_from_value(&card.get_value("owner", state.superglobal_state())?)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Default)]
+  struct CountingObserver {
+    plays: Rc<RefCell<Vec<CardAction>>>,
+    destroyed: Rc<RefCell<usize>>,
+  }
+
+  impl GameObserver for CountingObserver {
+    fn on_card_played(&mut self, _player: GameWinner, action: CardAction, _card: &ObservedCard) {
+      self.plays.borrow_mut().push(action);
+    }
+
+    fn on_card_destroyed(&mut self, _player: GameWinner, _card: &ObservedCard) {
+      *self.destroyed.borrow_mut() += 1;
+    }
+  }
+
+  #[test]
+  fn test_card_actions_dispatch_to_specific_callbacks() {
+    let observer = CountingObserver::default();
+    let plays = Rc::clone(&observer.plays);
+    let destroyed = Rc::clone(&observer.destroyed);
+    let observers = ObserverSet::new(vec![Box::new(observer)]);
+    let card = ObservedCard { id: CardId(3), name: String::from("Card") };
+    for action in [CardAction::PlayFromHand, CardAction::Discard, CardAction::Resurrect, CardAction::Destroy, CardAction::Exile] {
+      observers.notify(|observer| observer.on_card_action(GameWinner::Bottom, action, &card));
+    }
+    assert_eq!(*plays.borrow(), vec![CardAction::PlayFromHand, CardAction::Resurrect]);
+    assert_eq!(*destroyed.borrow(), 1);
+  }
+
+  #[test]
+  fn test_muted_observers_are_not_notified() {
+    let observer = CountingObserver::default();
+    let destroyed = Rc::clone(&observer.destroyed);
+    let observers = ObserverSet::new(vec![Box::new(observer)]);
+    let card = ObservedCard { id: CardId(3), name: String::from("Card") };
+    observers.muted(|| {
+      assert!(observers.is_inactive());
+      observers.notify(|observer| observer.on_card_action(GameWinner::Top, CardAction::Destroy, &card));
+    });
+    assert!(!observers.is_inactive());
+    observers.notify(|observer| observer.on_card_action(GameWinner::Top, CardAction::Destroy, &card));
+    assert_eq!(*destroyed.borrow(), 1);
+  }
+}
@@ -0,0 +1,183 @@
+//! Summary of a completed card game, read off of the playing field
+//! after the game loop has terminated.
+
+use super::{GameWinner, GameRules};
+use crate::interpreter::eval::EvaluatorState;
+use crate::interpreter::error::EvalError;
+use crate::interpreter::value::Value;
+use crate::interpreter::operator::expect_int;
+
+use strum_macros::Display;
+
+use std::fmt::{self, Formatter};
+
+/// The full result of a card game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameOutcome {
+  pub result: GameResult,
+  /// The number of full turns (i.e. a turn for each player) that
+  /// were started before the game ended.
+  pub turn_count: usize,
+  pub end_condition: EndCondition,
+  pub bottom: PlayerOutcome,
+  pub top: PlayerOutcome,
+}
+
+/// Who, if anyone, won the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+  Win(GameWinner),
+  /// Neither player won before the turn limit was reached.
+  Draw { turn_limit: usize },
+}
+
+/// The way in which the game ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum EndCondition {
+  /// The loser's fort defense was reduced to zero.
+  #[strum(serialize = "fort destroyed")]
+  FortDestroyed,
+  /// The winner's Destiny's Song reached the limit specified in the
+  /// game rules.
+  #[strum(serialize = "Destiny's Song")]
+  DestinySong,
+  /// The game was stopped at the turn limit, resulting in a draw.
+  #[strum(serialize = "turn limit")]
+  TurnLimit,
+}
+
+/// The state of one player, at the end of a game or at some point
+/// during it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlayerOutcome {
+  pub fort_defense: i64,
+  pub max_fort_defense: i64,
+  pub evil_points: i64,
+  pub destiny_song: i64,
+  pub cards: ZoneCounts,
+}
+
+/// The number of cards in each of a player's zones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ZoneCounts {
+  pub deck: usize,
+  pub hand: usize,
+  pub discard_pile: usize,
+  pub minions: usize,
+  pub effects: usize,
+}
+
+impl GameOutcome {
+  /// Reads the outcome of a completed game from the playing field.
+  /// The result should have already been determined from the endgame
+  /// variable.
+  pub(super) fn read_from(state: &EvaluatorState, playing_field: &Value, rules: &GameRules, result: GameResult) -> Result<Self, EvalError> {
+    let turn_number = expect_int("GameOutcome", &playing_field.get_value("turn_number", state.superglobal_state())?)?;
+    let bottom = PlayerOutcome::read_from(state, playing_field, GameWinner::Bottom)?;
+    let top = PlayerOutcome::read_from(state, playing_field, GameWinner::Top)?;
+    let end_condition = match result {
+      GameResult::Draw { .. } => EndCondition::TurnLimit,
+      // Destiny's Song is the only way to win without damaging the
+      // opponent's fort, so check for it first.
+      GameResult::Win(GameWinner::Bottom) if bottom.destiny_song >= rules.destiny_song_limit => EndCondition::DestinySong,
+      GameResult::Win(GameWinner::Top) if top.destiny_song >= rules.destiny_song_limit => EndCondition::DestinySong,
+      GameResult::Win(_) => EndCondition::FortDestroyed,
+    };
+    Ok(GameOutcome {
+      result,
+      turn_count: usize::try_from(turn_number + 1).unwrap_or(0),
+      end_condition,
+      bottom,
+      top,
+    })
+  }
+
+  pub fn player(&self, player: GameWinner) -> &PlayerOutcome {
+    match player {
+      GameWinner::Bottom => &self.bottom,
+      GameWinner::Top => &self.top,
+    }
+  }
+
+  /// The winner of the game, or `None` if the game was a draw.
+  pub fn winner(&self) -> Option<GameWinner> {
+    match self.result {
+      GameResult::Win(winner) => Some(winner),
+      GameResult::Draw { .. } => None,
+    }
+  }
+
+  pub fn is_draw(&self) -> bool {
+    matches!(self.result, GameResult::Draw { .. })
+  }
+
+  /// Absolute difference between the two players' fort defense at
+  /// the end of the game, regardless of who is ahead. Small margins
+  /// indicate close games.
+  pub fn fort_defense_margin(&self) -> i64 {
+    (self.bottom.fort_defense - self.top.fort_defense).abs()
+  }
+}
+
+impl PlayerOutcome {
+  pub(crate) fn read_from(state: &EvaluatorState, playing_field: &Value, player: GameWinner) -> Result<Self, EvalError> {
+    let superglobals = state.superglobal_state();
+    let stats_var = match player {
+      GameWinner::Bottom => "__evilconsim_statspanel_bottom",
+      GameWinner::Top => "__evilconsim_statspanel_top",
+    };
+    let stats = playing_field.get_value(stats_var, superglobals)?;
+    let get_stat = |name: &str| expect_int("PlayerOutcome", &stats.get_value(name, superglobals)?);
+    Ok(PlayerOutcome {
+      fort_defense: get_stat("fort_defense")?,
+      max_fort_defense: get_stat("max_fort_defense")?,
+      evil_points: get_stat("evil_points")?,
+      destiny_song: get_stat("destiny_song")?,
+      cards: ZoneCounts::read_from(state, playing_field, player)?,
+    })
+  }
+}
+
+impl ZoneCounts {
+  fn read_from(state: &EvaluatorState, playing_field: &Value, player: GameWinner) -> Result<Self, EvalError> {
+    let suffix = match player {
+      GameWinner::Bottom => "bottom",
+      GameWinner::Top => "top",
+    };
+    let count_cards = |zone: &str| -> Result<usize, EvalError> {
+      let strip = playing_field.get_value(&format!("__evilconsim_{zone}_{suffix}"), state.superglobal_state())?;
+      let container = state.call_function_on(&strip, "cards", Vec::new())?;
+      let count = expect_int("ZoneCounts", &state.call_function_on(&container, "card_count", Vec::new())?)?;
+      usize::try_from(count).map_err(|_| EvalError::domain_error("Negative card count"))
+    };
+    Ok(ZoneCounts {
+      deck: count_cards("deck")?,
+      hand: count_cards("hand")?,
+      discard_pile: count_cards("discardpile")?,
+      minions: count_cards("minionstrip")?,
+      effects: count_cards("effectstrip")?,
+    })
+  }
+}
+
+impl fmt::Display for GameOutcome {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    write!(f, "{} ({} on turn {}; BOTTOM fort {}/{}, TOP fort {}/{})",
+           self.result,
+           self.end_condition,
+           self.turn_count,
+           self.bottom.fort_defense,
+           self.bottom.max_fort_defense,
+           self.top.fort_defense,
+           self.top.max_fort_defense)
+  }
+}
+
+impl fmt::Display for GameResult {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      GameResult::Win(winner) => write!(f, "{winner}"),
+      GameResult::Draw { turn_limit } => write!(f, "DRAW (turn limit {turn_limit} reached)"),
+    }
+  }
+}
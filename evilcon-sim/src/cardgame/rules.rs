@@ -0,0 +1,138 @@
+//! Configurable rules of the card game.
+//!
+//! The defaults match the rules of the Godot game. Rule variants can
+//! be loaded from a YAML file, where any rule that is not specified
+//! keeps its default value.
+
+use super::deck::DECK_SIZE;
+use super::yaml_file::{YamlFile, YamlFileError};
+use crate::interpreter::mocking::{DEFAULT_FORT_DEFENSE, SECOND_PLAYER_FORT_ADVANTAGE, DESTINY_SONG_LIMIT};
+
+use clap::Args;
+use serde::{Deserialize, Serialize};
+
+use std::path::PathBuf;
+
+/// Default maximum number of full turns before a game is declared a
+/// draw.
+pub const TURN_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameRules {
+  /// Maximum number of full turns before the game is declared a
+  /// draw.
+  pub turn_limit: usize,
+  /// Number of cards in each player's deck.
+  pub deck_size: usize,
+  /// Starting (and maximum) fort defense of the first player.
+  pub fort_defense: i64,
+  /// Additional fort defense given to the second player.
+  pub second_player_fort_advantage: i64,
+  /// Destiny's Song value at which a player wins the game.
+  pub destiny_song_limit: i64,
+}
+
+/// Command line arguments for configuring the rules of the game. A
+/// rules file is loaded first (if supplied), and then any individual
+/// rules passed on the command line take precedence.
+#[derive(Debug, Clone, Default, Args)]
+pub struct GameRulesArgs {
+  /// YAML file of game rules. Rules not specified in the file keep
+  /// their default values.
+  #[arg(long = "rules")]
+  pub rules_file: Option<PathBuf>,
+  /// Maximum number of full turns before the game is a draw.
+  /// (Default = 200)
+  #[arg(long)]
+  pub turn_limit: Option<usize>,
+  /// Number of cards in each player's deck. (Default = 20)
+  #[arg(long)]
+  pub deck_size: Option<usize>,
+  /// Starting fort defense of the first player. (Default = 60)
+  #[arg(long)]
+  pub fort_defense: Option<i64>,
+  /// Additional fort defense for the second player. (Default = 2)
+  #[arg(long)]
+  pub second_player_fort_advantage: Option<i64>,
+  /// Destiny's Song value at which a player wins. (Default = 3)
+  #[arg(long)]
+  pub destiny_song_limit: Option<i64>,
+}
+
+impl YamlFile for GameRules {}
+
+impl GameRules {
+  /// Starting fort defense of the second (top) player.
+  pub fn second_player_fort_defense(&self) -> i64 {
+    self.fort_defense + self.second_player_fort_advantage
+  }
+}
+
+impl Default for GameRules {
+  fn default() -> Self {
+    GameRules {
+      turn_limit: TURN_LIMIT,
+      deck_size: DECK_SIZE,
+      fort_defense: DEFAULT_FORT_DEFENSE,
+      second_player_fort_advantage: SECOND_PLAYER_FORT_ADVANTAGE,
+      destiny_song_limit: DESTINY_SONG_LIMIT,
+    }
+  }
+}
+
+impl GameRulesArgs {
+  pub fn resolve(&self) -> Result<GameRules, YamlFileError> {
+    let mut rules = match &self.rules_file {
+      Some(path) => GameRules::read_from_path(path)?,
+      None => GameRules::default(),
+    };
+    if let Some(turn_limit) = self.turn_limit {
+      rules.turn_limit = turn_limit;
+    }
+    if let Some(deck_size) = self.deck_size {
+      rules.deck_size = deck_size;
+    }
+    if let Some(fort_defense) = self.fort_defense {
+      rules.fort_defense = fort_defense;
+    }
+    if let Some(second_player_fort_advantage) = self.second_player_fort_advantage {
+      rules.second_player_fort_advantage = second_player_fort_advantage;
+    }
+    if let Some(destiny_song_limit) = self.destiny_song_limit {
+      rules.destiny_song_limit = destiny_song_limit;
+    }
+    Ok(rules)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_read_partial_rules() {
+    let rules = GameRules::read_from_file("fort_defense: 80\ndeck_size: 25\n".as_bytes()).unwrap();
+    assert_eq!(rules, GameRules {
+      fort_defense: 80,
+      deck_size: 25,
+      ..GameRules::default()
+    });
+  }
+
+  #[test]
+  fn test_read_unknown_rule() {
+    GameRules::read_from_file("fort_defence: 80\n".as_bytes()).unwrap_err();
+  }
+
+  #[test]
+  fn test_args_override_defaults() {
+    let args = GameRulesArgs {
+      turn_limit: Some(50),
+      ..GameRulesArgs::default()
+    };
+    let rules = args.resolve().unwrap();
+    assert_eq!(rules.turn_limit, 50);
+    assert_eq!(rules.fort_defense, DEFAULT_FORT_DEFENSE);
+  }
+}
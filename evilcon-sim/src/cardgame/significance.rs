@@ -0,0 +1,224 @@
+//! Confidence intervals and significance tests for the win rate of
+//! one deck against another.
+//!
+//! Draws are excluded: the win rate is the fraction of decisive games
+//! won, tested against the null hypothesis of an even matchup.
+
+use strum_macros::Display;
+
+/// The win rate of a deck over a batch of decisive games.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WinRateSummary {
+  pub wins: u64,
+  pub losses: u64,
+  /// Confidence level of the interval, from 0 to 1.
+  pub confidence: f64,
+  /// Lower bound of the Wilson score interval.
+  pub low: f64,
+  /// Upper bound of the Wilson score interval.
+  pub high: f64,
+  /// Two-sided p-value of the exact binomial test against a win rate
+  /// of 50%.
+  pub p_value: f64,
+}
+
+impl WinRateSummary {
+  pub fn new(wins: u64, losses: u64, confidence: f64) -> Self {
+    let (low, high) = wilson_interval(wins, wins + losses, confidence);
+    WinRateSummary {
+      wins,
+      losses,
+      confidence,
+      low,
+      high,
+      p_value: binomial_test(wins, wins + losses),
+    }
+  }
+
+  pub fn games(&self) -> u64 {
+    self.wins + self.losses
+  }
+
+  pub fn win_rate(&self) -> f64 {
+    if self.games() == 0 {
+      0.5
+    } else {
+      self.wins as f64 / self.games() as f64
+    }
+  }
+
+  /// Whether the confidence interval excludes an even matchup.
+  pub fn is_significant(&self) -> bool {
+    self.low > 0.5 || self.high < 0.5
+  }
+
+  pub fn log(&self, label: &str) {
+    tracing::info!("{label} won {:.1}% of {} decisive game(s), {:.0}% CI [{:.1}%, {:.1}%], p = {:.4} against 50%",
+                   self.win_rate() * 100.0,
+                   self.games(),
+                   self.confidence * 100.0,
+                   self.low * 100.0,
+                   self.high * 100.0,
+                   self.p_value);
+  }
+}
+
+/// Wald's sequential probability ratio test of an even matchup (a
+/// win rate of 50%) against a win rate of `p1`. After each batch of
+/// games, the log-likelihood ratio of the decisive games is compared
+/// against bounds derived from the error rates, so the chance of a
+/// false decision holds no matter how often the test is checked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+  /// Win rate under the alternative hypothesis.
+  pub p1: f64,
+  /// Probability of accepting `p1` when the matchup is even.
+  pub alpha: f64,
+  /// Probability of accepting an even matchup when the win rate is
+  /// `p1`.
+  pub beta: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum SprtDecision {
+  /// The log-likelihood ratio fell to the lower bound.
+  #[strum(serialize = "accepted the even matchup")]
+  AcceptNull,
+  /// The log-likelihood ratio rose to the upper bound.
+  #[strum(serialize = "accepted the alternative win rate")]
+  AcceptAlternative,
+  #[strum(serialize = "undecided")]
+  Continue,
+}
+
+impl Sprt {
+  /// Log-likelihood ratio of the alternative win rate against 50%.
+  pub fn log_likelihood_ratio(&self, wins: u64, losses: u64) -> f64 {
+    wins as f64 * (self.p1 / 0.5).ln() + losses as f64 * ((1.0 - self.p1) / 0.5).ln()
+  }
+
+  /// The ratio at which the alternative is accepted,
+  /// `log((1 - beta) / alpha)`.
+  pub fn upper_bound(&self) -> f64 {
+    ((1.0 - self.beta) / self.alpha).ln()
+  }
+
+  /// The ratio at which the even matchup is accepted,
+  /// `log(beta / (1 - alpha))`.
+  pub fn lower_bound(&self) -> f64 {
+    (self.beta / (1.0 - self.alpha)).ln()
+  }
+
+  pub fn decide(&self, wins: u64, losses: u64) -> SprtDecision {
+    let ratio = self.log_likelihood_ratio(wins, losses);
+    if ratio >= self.upper_bound() {
+      SprtDecision::AcceptAlternative
+    } else if ratio <= self.lower_bound() {
+      SprtDecision::AcceptNull
+    } else {
+      SprtDecision::Continue
+    }
+  }
+}
+
+/// Wilson score interval for a binomial proportion.
+pub fn wilson_interval(successes: u64, trials: u64, confidence: f64) -> (f64, f64) {
+  if trials == 0 {
+    return (0.0, 1.0);
+  }
+  let n = trials as f64;
+  let p = successes as f64 / n;
+  let z = normal_quantile(1.0 - (1.0 - confidence) / 2.0);
+  let z2 = z * z;
+  let denominator = 1.0 + z2 / n;
+  let center = (p + z2 / (2.0 * n)) / denominator;
+  let half_width = z / denominator * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+  ((center - half_width).max(0.0), (center + half_width).min(1.0))
+}
+
+/// Two-sided p-value of the exact binomial test that the success
+/// probability is one half.
+pub fn binomial_test(successes: u64, trials: u64) -> f64 {
+  let tail = successes.min(trials - successes);
+  let log_half = (0.5f64).ln() * trials as f64;
+  let mut log_choose = 0.0;
+  let mut tail_probability = log_half.exp();
+  for i in 1..=tail {
+    log_choose += ((trials - i + 1) as f64).ln() - (i as f64).ln();
+    tail_probability += (log_choose + log_half).exp();
+  }
+  (2.0 * tail_probability).min(1.0)
+}
+
+/// Inverse of the standard normal cumulative distribution function,
+/// by Acklam's rational approximation (relative error below 1.15e-9).
+pub fn normal_quantile(p: f64) -> f64 {
+  const A: [f64; 6] = [-3.969683028665376e+01, 2.209460984245205e+02, -2.759285104469687e+02, 1.38357751867269e+02, -3.066479806614716e+01, 2.506628277459239e+00];
+  const B: [f64; 5] = [-5.447609879822406e+01, 1.615858368580409e+02, -1.556989798598866e+02, 6.680131188771972e+01, -1.328068155288572e+01];
+  const C: [f64; 6] = [-7.784894002430293e-03, -3.223964580411365e-01, -2.400758277161838e+00, -2.549732539343734e+00, 4.374664141464968e+00, 2.938163982698783e+00];
+  const D: [f64; 4] = [7.784695709041462e-03, 3.224671290700398e-01, 2.445134137142996e+00, 3.754408661907416e+00];
+  const P_LOW: f64 = 0.02425;
+
+  let tail = |q: f64| {
+    (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5]) /
+      ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+  };
+  if p <= 0.0 {
+    f64::NEG_INFINITY
+  } else if p >= 1.0 {
+    f64::INFINITY
+  } else if p < P_LOW {
+    tail((-2.0 * p.ln()).sqrt())
+  } else if p <= 1.0 - P_LOW {
+    let q = p - 0.5;
+    let r = q * q;
+    (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q /
+      (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+  } else {
+    -tail((-2.0 * (1.0 - p).ln()).sqrt())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_normal_quantile() {
+    assert!((normal_quantile(0.975) - 1.959964).abs() < 1e-5);
+    assert!((normal_quantile(0.5)).abs() < 1e-9);
+    assert!((normal_quantile(0.001) + 3.090232).abs() < 1e-5);
+  }
+
+  #[test]
+  fn test_wilson_interval() {
+    let (low, high) = wilson_interval(523, 1000, 0.95);
+    assert!((low - 0.4920).abs() < 1e-3);
+    assert!((high - 0.5538).abs() < 1e-3);
+    assert_eq!(wilson_interval(0, 0, 0.95), (0.0, 1.0));
+  }
+
+  #[test]
+  fn test_binomial_test() {
+    assert_eq!(binomial_test(10, 20), 1.0);
+    assert!((binomial_test(15, 20) - 0.041389).abs() < 1e-5);
+    assert!((binomial_test(5, 20) - 0.041389).abs() < 1e-5);
+  }
+
+  #[test]
+  fn test_sprt() {
+    let sprt = Sprt { p1: 0.6, alpha: 0.05, beta: 0.05 };
+    assert!((sprt.upper_bound() - 2.944439).abs() < 1e-5);
+    assert!((sprt.lower_bound() + 2.944439).abs() < 1e-5);
+    assert_eq!(sprt.log_likelihood_ratio(0, 0), 0.0);
+    assert_eq!(sprt.decide(12, 8), SprtDecision::Continue);
+    assert_eq!(sprt.decide(75, 45), SprtDecision::AcceptAlternative);
+    assert_eq!(sprt.decide(45, 55), SprtDecision::AcceptNull);
+  }
+
+  #[test]
+  fn test_small_samples_are_not_significant() {
+    assert!(!WinRateSummary::new(13, 7, 0.95).is_significant());
+    assert!(WinRateSummary::new(600, 400, 0.95).is_significant());
+  }
+}
@@ -0,0 +1,360 @@
+//! Game length and tempo statistics across a batch of games.
+//!
+//! These describe how games play out rather than who wins them: how
+//! long games last, how soon forts take damage, and how efficiently
+//! each player uses their Evil Points and hand. Turns count from zero,
+//! as in game traces.
+
+use super::{EndCondition, GameEngineError, GameOutcome, GameWinner, PlayerOutcome};
+use super::observer::{GameObserver, GameObserverFactory, Phase, Stat, StatChange};
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Width of the longest bar when logging a histogram.
+const HISTOGRAM_BAR_WIDTH: u64 = 40;
+
+/// Maximum number of rows when logging a histogram or a per-turn
+/// table. Longer ones are grouped into wider buckets.
+const MAX_LOGGED_ROWS: usize = 25;
+
+/// Tempo statistics for every game in a batch. Games that ended in
+/// an error are not counted.
+#[derive(Debug, Clone, Default)]
+pub struct TempoStats {
+  pub games: u64,
+  /// Games which were stopped at the turn limit.
+  pub turn_limit_games: u64,
+  /// Number of full turns per game.
+  pub turns: Histogram,
+  /// Turn on which either fort first took damage, over the games in
+  /// which a fort took damage at all.
+  pub first_fort_damage_turn: Histogram,
+  pub bottom: SeatTempo,
+  pub top: SeatTempo,
+}
+
+/// Tempo statistics for one seat, with one sample per turn of each
+/// game.
+#[derive(Debug, Clone, Default)]
+pub struct SeatTempo {
+  /// Fort defense at the end of the player's turn, indexed by turn.
+  pub fort_defense_by_turn: Vec<Histogram>,
+  /// Evil Points spent during the player's own Play Phase. Evil
+  /// Points lost to the opponent's cards, or at any other time, are
+  /// not counted.
+  pub evil_points_spent: Histogram,
+  /// Evil Points left unspent at the end of the player's turn, which
+  /// are lost in the End Phase.
+  pub evil_points_wasted: Histogram,
+  /// Cards left in the player's hand at the end of their turn.
+  pub cards_in_hand: Histogram,
+}
+
+/// A distribution of integer samples.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Histogram(BTreeMap<i64, u64>);
+
+/// Collects [`TempoStats`] from every game played by an engine.
+#[derive(Debug, Clone, Default)]
+pub struct TempoCollector(Arc<Mutex<TempoStats>>);
+
+/// Observes a single game on behalf of a [`TempoCollector`].
+#[derive(Debug)]
+struct TempoObserver {
+  collector: TempoCollector,
+  turn: i64,
+  /// The current phase and the player whose turn it is.
+  phase: Option<(Phase, GameWinner)>,
+  first_fort_damage_turn: Option<i64>,
+  bottom: Vec<PlayerTurn>,
+  top: Vec<PlayerTurn>,
+  /// Evil Points spent so far in the current Play Phase.
+  evil_points_spent: i64,
+}
+
+/// A sample taken at the end of one of a player's turns.
+#[derive(Debug, Clone)]
+struct PlayerTurn {
+  turn: i64,
+  fort_defense: i64,
+  evil_points_spent: i64,
+  evil_points_wasted: i64,
+  cards_in_hand: i64,
+}
+
+impl Histogram {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn add(&mut self, value: i64) {
+    *self.0.entry(value).or_default() += 1;
+  }
+
+  pub fn count(&self) -> u64 {
+    self.0.values().sum()
+  }
+
+  pub fn min(&self) -> Option<i64> {
+    self.0.keys().next().copied()
+  }
+
+  pub fn max(&self) -> Option<i64> {
+    self.0.keys().next_back().copied()
+  }
+
+  pub fn mean(&self) -> Option<f64> {
+    let count = self.count();
+    (count > 0).then(|| self.0.iter().map(|(&value, &n)| value as f64 * n as f64).sum::<f64>() / count as f64)
+  }
+
+  /// The smallest sample such that at least `fraction` of the samples
+  /// are less than or equal to it.
+  pub fn percentile(&self, fraction: f64) -> Option<i64> {
+    let target = ((fraction * self.count() as f64).ceil() as u64).max(1);
+    let mut seen = 0;
+    for (&value, &count) in &self.0 {
+      seen += count;
+      if seen >= target {
+        return Some(value);
+      }
+    }
+    None
+  }
+
+  /// Logs a one-line summary of the distribution.
+  pub fn log_summary(&self, label: &str) {
+    match (self.mean(), self.min(), self.percentile(0.5), self.max()) {
+      (Some(mean), Some(min), Some(median), Some(max)) => {
+        tracing::info!("{label}: mean {mean:.2}, min {min}, median {median}, max {max} ({} sample(s))", self.count());
+      }
+      _ => {
+        tracing::info!("{label}: no samples");
+      }
+    }
+  }
+
+  /// Logs a summary followed by a bar chart of the distribution.
+  pub fn log(&self, label: &str) {
+    self.log_summary(label);
+    let (Some(min), Some(max)) = (self.min(), self.max()) else {
+      return;
+    };
+    let bucket_width = (max - min) / MAX_LOGGED_ROWS as i64 + 1;
+    let mut bucket_counts = vec![0; ((max - min) / bucket_width + 1) as usize];
+    for (&value, &count) in &self.0 {
+      bucket_counts[((value - min) / bucket_width) as usize] += count;
+    }
+    let largest = bucket_counts.iter().copied().max().unwrap_or(0).max(1);
+    for (bucket, count) in bucket_counts.into_iter().enumerate() {
+      let low = min + bucket as i64 * bucket_width;
+      let range = if bucket_width == 1 { format!("{low}") } else { format!("{low}-{}", low + bucket_width - 1) };
+      let bar = "#".repeat((count * HISTOGRAM_BAR_WIDTH).div_ceil(largest) as usize);
+      tracing::info!("  {range:>9} {count:>7} {bar}");
+    }
+  }
+}
+
+impl TempoStats {
+  pub fn log(&self, turn_limit: usize) {
+    tracing::info!("Tempo statistics over {} game(s):", self.games);
+    self.turns.log("Game length (full turns)");
+    tracing::info!("{} game(s) reached the turn limit of {}", self.turn_limit_games, turn_limit);
+    self.first_fort_damage_turn.log("Turn of first fort damage");
+    tracing::info!("{} game(s) ended with no fort damage", self.games - self.first_fort_damage_turn.count());
+
+    for (name, seat) in [("BOTTOM", &self.bottom), ("TOP", &self.top)] {
+      seat.evil_points_spent.log_summary(&format!("{name} Evil Points spent per turn"));
+      seat.evil_points_wasted.log(&format!("{name} Evil Points wasted per turn"));
+      seat.cards_in_hand.log(&format!("{name} cards left in hand at end of turn"));
+    }
+
+    tracing::info!("Mean fort defense at end of turn (games still running):");
+    tracing::info!("{:>9} {:>8} {:>8} {:>7}", "Turn", "BOTTOM", "TOP", "Games");
+    let turn_count = usize::max(self.bottom.fort_defense_by_turn.len(), self.top.fort_defense_by_turn.len());
+    let step = turn_count.div_ceil(MAX_LOGGED_ROWS).max(1);
+    let mean_at = |by_turn: &[Histogram], turn: usize| {
+      by_turn.get(turn).and_then(Histogram::mean).map_or_else(|| String::from("-"), |mean| format!("{mean:.1}"))
+    };
+    for turn in (0..turn_count).step_by(step) {
+      let games = self.bottom.fort_defense_by_turn.get(turn).map_or(0, Histogram::count);
+      tracing::info!("{turn:>9} {:>8} {:>8} {games:>7}",
+                     mean_at(&self.bottom.fort_defense_by_turn, turn),
+                     mean_at(&self.top.fort_defense_by_turn, turn));
+    }
+  }
+
+  fn seat_mut(&mut self, player: GameWinner) -> &mut SeatTempo {
+    match player {
+      GameWinner::Bottom => &mut self.bottom,
+      GameWinner::Top => &mut self.top,
+    }
+  }
+}
+
+impl TempoCollector {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// The statistics of every game that has finished so far.
+  pub fn stats(&self) -> TempoStats {
+    self.0.lock().unwrap().clone()
+  }
+}
+
+impl GameObserverFactory for TempoCollector {
+  fn create_observer(&self) -> Box<dyn GameObserver> {
+    Box::new(TempoObserver {
+      collector: self.clone(),
+      turn: -1,
+      phase: None,
+      first_fort_damage_turn: None,
+      bottom: Vec::new(),
+      top: Vec::new(),
+      evil_points_spent: 0,
+    })
+  }
+}
+
+impl GameObserver for TempoObserver {
+  fn on_turn_start(&mut self, turn: i64) {
+    self.turn = turn;
+    self.phase = None;
+  }
+
+  fn on_phase_start(&mut self, phase: Phase, player: GameWinner) {
+    self.phase = Some((phase, player));
+    self.evil_points_spent = 0;
+  }
+
+  fn on_stat_changed(&mut self, change: &StatChange) {
+    match change.stat {
+      Stat::FortDefense if change.delta() < 0 => {
+        self.first_fort_damage_turn.get_or_insert(self.turn);
+      }
+      // Only the player whose Play Phase it is can spend Evil
+      // Points. Other decreases, such as the reset in the End Phase
+      // or drains by the opponent's cards, are not spending.
+      Stat::EvilPoints if change.delta() < 0 && self.phase == Some((Phase::Play, change.player)) => {
+        self.evil_points_spent -= change.delta();
+      }
+      _ => {}
+    }
+  }
+
+  fn on_play_phase_end(&mut self, player: GameWinner, state: &PlayerOutcome) {
+    let evil_points_spent = std::mem::take(&mut self.evil_points_spent);
+    let player_turn = PlayerTurn {
+      turn: self.turn,
+      fort_defense: state.fort_defense,
+      evil_points_spent,
+      evil_points_wasted: state.evil_points,
+      cards_in_hand: state.cards.hand as i64,
+    };
+    match player {
+      GameWinner::Bottom => self.bottom.push(player_turn),
+      GameWinner::Top => self.top.push(player_turn),
+    }
+  }
+
+  fn on_game_end(&mut self, outcome: Result<&GameOutcome, &GameEngineError>) {
+    // Games that errored are not counted.
+    let Ok(outcome) = outcome else {
+      return;
+    };
+    let mut stats = self.collector.0.lock().unwrap();
+    stats.games += 1;
+    if outcome.end_condition == EndCondition::TurnLimit {
+      stats.turn_limit_games += 1;
+    }
+    stats.turns.add(outcome.turn_count as i64);
+    if let Some(turn) = self.first_fort_damage_turn {
+      stats.first_fort_damage_turn.add(turn);
+    }
+    for (player, turns) in [(GameWinner::Bottom, &self.bottom), (GameWinner::Top, &self.top)] {
+      let seat = stats.seat_mut(player);
+      for player_turn in turns {
+        let Ok(turn) = usize::try_from(player_turn.turn) else {
+          continue;
+        };
+        if seat.fort_defense_by_turn.len() <= turn {
+          seat.fort_defense_by_turn.resize_with(turn + 1, Histogram::new);
+        }
+        seat.fort_defense_by_turn[turn].add(player_turn.fort_defense);
+        seat.evil_points_spent.add(player_turn.evil_points_spent);
+        seat.evil_points_wasted.add(player_turn.evil_points_wasted);
+        seat.cards_in_hand.add(player_turn.cards_in_hand);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cardgame::GameResult;
+  use crate::cardgame::outcome::ZoneCounts;
+
+  #[test]
+  fn test_histogram_summary() {
+    let mut histogram = Histogram::new();
+    assert_eq!(histogram.mean(), None);
+    for value in [3, 1, 4, 1, 5] {
+      histogram.add(value);
+    }
+    assert_eq!(histogram.count(), 5);
+    assert_eq!((histogram.min(), histogram.max()), (Some(1), Some(5)));
+    assert_eq!(histogram.mean(), Some(2.8));
+    assert_eq!(histogram.percentile(0.5), Some(3));
+    assert_eq!(histogram.percentile(0.0), Some(1));
+    assert_eq!(histogram.percentile(1.0), Some(5));
+  }
+
+  #[test]
+  fn test_tempo_aggregation() {
+    let collector = TempoCollector::new();
+    let mut observer = collector.create_observer();
+    let change = |player, stat, old_value, new_value| StatChange { player, stat, card: None, old_value, new_value, source: None };
+    let snapshot = |fort_defense, evil_points, hand| PlayerOutcome {
+      fort_defense,
+      evil_points,
+      cards: ZoneCounts { hand, ..Default::default() },
+      ..Default::default()
+    };
+    observer.on_turn_start(0);
+    observer.on_phase_start(Phase::Play, GameWinner::Bottom);
+    observer.on_stat_changed(&change(GameWinner::Bottom, Stat::EvilPoints, 3, 1));
+    observer.on_play_phase_end(GameWinner::Bottom, &snapshot(60, 1, 4));
+    observer.on_phase_start(Phase::End, GameWinner::Bottom);
+    observer.on_stat_changed(&change(GameWinner::Bottom, Stat::EvilPoints, 1, 0));
+    observer.on_turn_start(1);
+    observer.on_phase_start(Phase::Attack, GameWinner::Top);
+    observer.on_stat_changed(&change(GameWinner::Bottom, Stat::FortDefense, 60, 58));
+    observer.on_phase_start(Phase::Play, GameWinner::Top);
+    // TOP drains BOTTOM's Evil Points, which BOTTOM did not spend.
+    observer.on_stat_changed(&change(GameWinner::Bottom, Stat::EvilPoints, 2, 0));
+    observer.on_stat_changed(&change(GameWinner::Top, Stat::EvilPoints, 4, 1));
+    observer.on_play_phase_end(GameWinner::Top, &snapshot(62, 0, 2));
+    observer.on_game_end(Ok(&GameOutcome {
+      result: GameResult::Win(GameWinner::Top),
+      turn_count: 2,
+      end_condition: EndCondition::FortDestroyed,
+      bottom: PlayerOutcome::default(),
+      top: PlayerOutcome::default(),
+    }));
+
+    let stats = collector.stats();
+    assert_eq!(stats.games, 1);
+    assert_eq!(stats.turns.mean(), Some(2.0));
+    assert_eq!(stats.first_fort_damage_turn.min(), Some(1));
+    assert_eq!(stats.bottom.evil_points_spent.mean(), Some(2.0));
+    assert_eq!(stats.bottom.evil_points_wasted.mean(), Some(1.0));
+    assert_eq!(stats.bottom.cards_in_hand.mean(), Some(4.0));
+    assert_eq!(stats.top.evil_points_spent.mean(), Some(3.0));
+    assert_eq!(stats.top.fort_defense_by_turn.len(), 2);
+    assert_eq!(stats.top.fort_defense_by_turn[0].count(), 0);
+    assert_eq!(stats.top.fort_defense_by_turn[1].mean(), Some(62.0));
+  }
+}
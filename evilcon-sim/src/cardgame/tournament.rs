@@ -0,0 +1,184 @@
+//! Round-robin tournaments between a fixed list of decks.
+
+use super::{GameEngine, CardGameEnv, GameResult, GameRules, GameWinner, PlayerAgents, Deck, derive_game_seed};
+use super::code::serialize_game_code;
+use super::deck::NamedDeck;
+use super::genetic::bradley_terry::{self, BradleyTerryFit, WinMatrix};
+
+use itertools::Itertools;
+use threadpool::ThreadPool;
+
+use std::sync::{Arc, mpsc};
+
+/// The results of a round-robin tournament.
+#[derive(Debug, Clone)]
+pub struct TournamentResults {
+  pub names: Vec<String>,
+  /// Wins between every pair of decks, counting each draw as half of
+  /// a win for each deck.
+  pub(crate) wins: WinMatrix,
+  pub errors: u64,
+  pub(crate) fit: BradleyTerryFit,
+  /// Every game of the tournament, in the order in which they were
+  /// scheduled.
+  games: Vec<(TournamentGame, Option<GameResult>)>,
+}
+
+/// A single game of a tournament, by index into the list of decks.
+#[derive(Debug, Clone, Copy)]
+struct TournamentGame {
+  index: usize,
+  bottom: usize,
+  top: usize,
+  seed: u64,
+}
+
+/// Plays `games_per_pair` games between every pair of decks on the
+/// thread pool, alternating seats from game to game. Decks are
+/// played with the default agents.
+pub fn play_tournament(
+  engine: &GameEngine,
+  thread_pool: &ThreadPool,
+  decks: &[NamedDeck],
+  rules: &Arc<GameRules>,
+  games_per_pair: usize,
+  master_seed: u64,
+) -> TournamentResults {
+  let deck_list = Arc::new(decks.iter().map(|named| named.deck.clone()).collect::<Vec<Deck>>());
+  let (sender, receiver) = mpsc::channel();
+  let mut total_games = 0;
+  for (pair_index, (first, second)) in (0..decks.len()).tuple_combinations().enumerate() {
+    let pair_seed = derive_game_seed(master_seed, pair_index as u64);
+    for game_index in 0..games_per_pair {
+      let (bottom, top) = if game_index % 2 == 1 { (second, first) } else { (first, second) };
+      let game = TournamentGame { index: total_games, bottom, top, seed: derive_game_seed(pair_seed, game_index as u64) };
+      total_games += 1;
+      let sender = sender.clone();
+      let engine = engine.clone();
+      let rules = Arc::clone(rules);
+      let deck_list = Arc::clone(&deck_list);
+      thread_pool.execute(move || {
+        let env = CardGameEnv {
+          bottom_deck: &deck_list[game.bottom],
+          top_deck: &deck_list[game.top],
+        };
+        let result = match engine.play_game_seeded(&env, &PlayerAgents::default(), &rules, game.seed) {
+          Ok(outcome) => Some(outcome.result),
+          Err(err) => {
+            let game_code = serialize_game_code(game.seed, &env).unwrap_or_else(|_| "(failed to get game code)".to_owned());
+            tracing::error!(%game_code, "Error during game: {}", err.root_cause());
+            None
+          }
+        };
+        sender.send((game, result)).unwrap();
+      });
+    }
+  }
+
+  // Threads finish in an arbitrary order, so sort the results. Order
+  // matters to anything that rates decks game by game.
+  let mut wins = WinMatrix::zeroes(decks.len());
+  let mut errors = 0;
+  let mut games = receiver.iter().take(total_games).collect::<Vec<_>>();
+  games.sort_by_key(|(game, _)| game.index);
+  for (game, result) in &games {
+    match result {
+      Some(GameResult::Win(GameWinner::Bottom)) => {
+        wins[(game.bottom, game.top, GameWinner::Bottom)] += 1.0;
+      }
+      Some(GameResult::Win(GameWinner::Top)) => {
+        wins[(game.top, game.bottom, GameWinner::Top)] += 1.0;
+      }
+      Some(GameResult::Draw { .. }) => {
+        wins[(game.bottom, game.top, GameWinner::Bottom)] += 0.5;
+        wins[(game.top, game.bottom, GameWinner::Top)] += 0.5;
+      }
+      None => {
+        errors += 1;
+      }
+    }
+  }
+
+  let fit = bradley_terry::fit(&wins);
+  TournamentResults {
+    names: decks.iter().map(|named| named.name.clone()).collect(),
+    wins,
+    errors,
+    fit,
+    games,
+  }
+}
+
+impl TournamentResults {
+  /// The fraction of games between `x` and `y` won by `x`, or `None`
+  /// if they played no (error-free) games.
+  pub fn win_rate(&self, x: usize, y: usize) -> Option<f64> {
+    let games = self.wins.wins(x, y) + self.wins.wins(y, x);
+    (games > 0.0).then(|| self.wins.wins(x, y) / games)
+  }
+
+  /// The result of every game that completed without error, as
+  /// indices of the BOTTOM and TOP decks and the result, in the order
+  /// in which the games were scheduled.
+  pub fn game_results(&self) -> impl Iterator<Item = (usize, usize, GameResult)> + '_ {
+    self.games.iter().filter_map(|(game, result)| result.map(|result| (game.bottom, game.top, result)))
+  }
+
+  /// Indices of the decks, from highest to lowest rating.
+  pub fn ranking(&self) -> Vec<usize> {
+    let mut indices = (0..self.names.len()).collect::<Vec<_>>();
+    indices.sort_by(|&a, &b| self.fit.scores[b].partial_cmp(&self.fit.scores[a]).unwrap());
+    indices
+  }
+
+  pub fn log_results(&self) {
+    let name_width = self.names.iter().map(|name| name.len()).max().unwrap_or(0).max(4);
+    let deck_count = self.names.len();
+
+    tracing::info!("Win rates (row deck against column deck):");
+    let header = (1..=deck_count).map(|j| format!("{j:>7}")).join("");
+    tracing::info!("{:>3} {:name_width$} {header}", "", "");
+    for i in 0..deck_count {
+      let row = (0..deck_count)
+        .map(|j| match self.win_rate(i, j) {
+          Some(rate) if i != j => format!("{:>6.1}%", rate * 100.0),
+          _ => format!("{:>7}", "-"),
+        })
+        .join("");
+      tracing::info!("{:>3} {:name_width$} {row}", i + 1, self.names[i]);
+    }
+
+    tracing::info!("Ranking:");
+    tracing::info!("{:>4}  {:>3} {:name_width$} {:>8} {:>7} {:>8} {:>7}", "Rank", "#", "Deck", "Rating", "±SE", "Win rate", "Games");
+    for (rank, i) in self.ranking().into_iter().enumerate() {
+      let won = (0..deck_count).filter(|&j| j != i).map(|j| self.wins.wins(i, j)).sum::<f64>();
+      let games = (0..deck_count).filter(|&j| j != i).map(|j| self.wins.wins(i, j) + self.wins.wins(j, i)).sum::<f64>();
+      let win_rate = if games > 0.0 { won / games * 100.0 } else { 0.0 };
+      tracing::info!("{:>4}  {:>3} {:name_width$} {:>8.3} {:>7.3} {:>7.1}% {:>7}",
+                     rank + 1, i + 1, self.names[i], self.fit.scores[i], self.fit.standard_errors[i], win_rate, games);
+    }
+
+    tracing::info!("Estimated BOTTOM seat advantage: {:.3} ± {:.3} log-odds",
+                   self.fit.seat_advantage,
+                   self.fit.seat_advantage_standard_error);
+    tracing::info!("Tournament finished with {} error(s)", self.errors);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_win_rate_and_ranking() {
+    let mut wins = WinMatrix::zeroes(2);
+    wins[(1, 0, GameWinner::Bottom)] = 3.0;
+    wins[(1, 0, GameWinner::Top)] = 3.0;
+    wins[(0, 1, GameWinner::Bottom)] = 2.0;
+    let fit = bradley_terry::fit(&wins);
+    let results = TournamentResults { names: vec![String::from("A"), String::from("B")], wins, errors: 0, fit, games: Vec::new() };
+    assert_eq!(results.win_rate(1, 0), Some(0.75));
+    assert_eq!(results.win_rate(0, 1), Some(0.25));
+    assert_eq!(results.ranking(), vec![1, 0]);
+  }
+}
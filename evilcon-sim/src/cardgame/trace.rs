@@ -0,0 +1,214 @@
+//! Structured, per-game traces of everything that happens in a card
+//! game, for inspecting individual games after the fact.
+//!
+//! Events are collected by a [`TraceRecorder`], which observes a
+//! single game.
+
+use super::GameWinner;
+use super::observer::{CardAction, GameObserver, ObservedCard, Phase, Stat, StatChange, StatSource};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+/// The trace of a complete game.
+#[derive(Debug, Clone, Serialize)]
+pub struct GameTrace {
+  /// The game code, from which the game can be replayed.
+  pub game_code: String,
+  /// The result of the game, or `None` if the game ended in an
+  /// error.
+  pub result: Option<String>,
+  /// The error which ended the game, if any.
+  pub error: Option<String>,
+  pub events: Vec<TraceEvent>,
+}
+
+/// A single event in a game.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TraceEvent {
+  /// The full turn on which the event happened, counting from zero.
+  /// Events before the first turn (such as drawing the opening hands)
+  /// are on turn -1.
+  pub turn: i64,
+  /// The phase of the turn, or `None` outside of any phase.
+  pub phase: Option<Phase>,
+  /// The player who performed the action or whose stats changed.
+  /// Absent for events which concern both players.
+  pub player: Option<GameWinner>,
+  #[serde(flatten)]
+  pub action: TraceAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum TraceAction {
+  TurnStart,
+  TurnEnd,
+  PhaseStart,
+  DrawCards { count: i64 },
+  ReshuffleDiscardPile,
+  Card { card_action: CardAction, card: ObservedCard },
+  /// A player stat (such as `fort_defense`) or a card stat (such as
+  /// `level`) changed. `card` is present only for card stats, and
+  /// `source` only if the change happened during a card hook.
+  StatChange {
+    stat: Stat,
+    card: Option<ObservedCard>,
+    old_value: i64,
+    new_value: i64,
+    source: Option<StatSource>,
+  },
+}
+
+/// Collects the events of a single game. Cloning a recorder produces
+/// a handle to the same list of events.
+#[derive(Debug, Clone, Default)]
+pub struct TraceRecorder(Rc<RefCell<RecorderState>>);
+
+#[derive(Debug, Clone)]
+struct RecorderState {
+  turn: i64,
+  phase: Option<Phase>,
+  events: Vec<TraceEvent>,
+}
+
+#[derive(Debug, Error)]
+pub enum TraceError {
+  #[error("{0}")]
+  IoError(#[from] io::Error),
+  #[error("{0}")]
+  JsonError(#[from] serde_json::Error),
+}
+
+impl TraceRecorder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Records the start of a full turn. Subsequent events are stamped
+  /// with the new turn number.
+  pub fn start_turn(&self, turn: i64) {
+    {
+      let mut state = self.0.borrow_mut();
+      state.turn = turn;
+      state.phase = None;
+    }
+    self.record(None, TraceAction::TurnStart);
+  }
+
+  /// Records the start of a phase of a player's turn. Subsequent
+  /// events are stamped with the new phase.
+  pub fn start_phase(&self, phase: Phase, player: GameWinner) {
+    self.0.borrow_mut().phase = Some(phase);
+    self.record(Some(player), TraceAction::PhaseStart);
+  }
+
+  pub fn record(&self, player: Option<GameWinner>, action: TraceAction) {
+    let mut state = self.0.borrow_mut();
+    let event = TraceEvent { turn: state.turn, phase: state.phase, player, action };
+    state.events.push(event);
+  }
+
+  /// Removes and returns every event recorded so far.
+  pub fn take_events(&self) -> Vec<TraceEvent> {
+    std::mem::take(&mut self.0.borrow_mut().events)
+  }
+}
+
+impl Default for RecorderState {
+  fn default() -> Self {
+    RecorderState { turn: -1, phase: None, events: Vec::new() }
+  }
+}
+
+impl GameObserver for TraceRecorder {
+  fn on_turn_start(&mut self, turn: i64) {
+    self.start_turn(turn);
+  }
+
+  fn on_turn_end(&mut self, _turn: i64) {
+    self.record(None, TraceAction::TurnEnd);
+  }
+
+  fn on_phase_start(&mut self, phase: Phase, player: GameWinner) {
+    self.start_phase(phase, player);
+  }
+
+  fn on_cards_drawn(&mut self, player: GameWinner, count: i64) {
+    self.record(Some(player), TraceAction::DrawCards { count });
+  }
+
+  fn on_discard_pile_reshuffled(&mut self, player: GameWinner) {
+    self.record(Some(player), TraceAction::ReshuffleDiscardPile);
+  }
+
+  fn on_card_action(&mut self, player: GameWinner, action: CardAction, card: &ObservedCard) {
+    self.record(Some(player), TraceAction::Card { card_action: action, card: card.clone() });
+  }
+
+  fn on_stat_changed(&mut self, change: &StatChange) {
+    self.record(Some(change.player), TraceAction::StatChange {
+      stat: change.stat,
+      card: change.card.clone(),
+      old_value: change.old_value,
+      new_value: change.new_value,
+      source: change.source.clone(),
+    });
+  }
+}
+
+/// Writes a list of game traces to a file, as a JSON array.
+pub fn write_traces_to_path(traces: &[GameTrace], path: impl AsRef<Path>) -> Result<(), TraceError> {
+  let mut file = BufWriter::new(File::create(path)?);
+  serde_json::to_writer_pretty(&mut file, traces)?;
+  writeln!(file)?;
+  file.flush()?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cardgame::CardId;
+
+  #[test]
+  fn test_events_are_stamped_with_turn_and_phase() {
+    let recorder = TraceRecorder::new();
+    recorder.record(Some(GameWinner::Bottom), TraceAction::DrawCards { count: 5 });
+    recorder.start_turn(0);
+    recorder.start_phase(Phase::Draw, GameWinner::Bottom);
+    recorder.clone().on_cards_drawn(GameWinner::Bottom, 1);
+    let events = recorder.take_events();
+    assert_eq!(events.len(), 4);
+    assert_eq!((events[0].turn, events[0].phase), (-1, None));
+    assert_eq!((events[1].turn, events[1].phase), (0, None));
+    assert_eq!((events[3].turn, events[3].phase), (0, Some(Phase::Draw)));
+    assert!(recorder.take_events().is_empty());
+  }
+
+  #[test]
+  fn test_event_serialization() {
+    let event = TraceEvent {
+      turn: 2,
+      phase: Some(Phase::Play),
+      player: Some(GameWinner::Top),
+      action: TraceAction::Card {
+        card_action: CardAction::PlayFromHand,
+        card: ObservedCard { id: CardId(7), name: String::from("Mystery Box") },
+      },
+    };
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json["action"], "card");
+    assert_eq!(json["card_action"], "play_from_hand");
+    assert_eq!(json["player"], "TOP");
+    assert_eq!(json["phase"], "play");
+    assert_eq!(json["card"]["id"], 7);
+    assert_eq!(json["card"]["name"], "Mystery Box");
+  }
+}
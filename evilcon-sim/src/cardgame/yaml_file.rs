@@ -0,0 +1,56 @@
+//! Reading and writing the simulator's YAML data files, such as game
+//! rules, deck constraints, checkpoints, and ladders.
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+use std::io::{self, Read, Write};
+use std::fs::{self, File};
+use std::path::Path;
+
+#[derive(Debug, Error)]
+pub enum YamlFileError {
+  #[error("{0}")]
+  IoError(#[from] io::Error),
+  #[error("{0}")]
+  YmlError(#[from] serde_yaml::Error),
+}
+
+/// A value which is stored on disk as a YAML file. Since YAML is a
+/// superset of JSON, JSON files are accepted as well.
+pub trait YamlFile: Serialize + DeserializeOwned {
+  fn read_from_file<R: Read>(reader: R) -> serde_yaml::Result<Self> {
+    serde_yaml::from_reader(reader)
+  }
+
+  fn read_from_path(path: impl AsRef<Path>) -> Result<Self, YamlFileError> {
+    let file = File::open(path)?;
+    Ok(Self::read_from_file(file)?)
+  }
+
+  /// Writes the value to the given path. The value is written to a
+  /// temporary file which then replaces the original, so an
+  /// interrupted write leaves the previous file intact.
+  fn write_to_path(&self, path: impl AsRef<Path>) -> Result<(), YamlFileError> {
+    let path = path.as_ref();
+    // Append to the full file name, so that "ladder.yml" and
+    // "ladder.json" do not share a temporary file.
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    {
+      let mut file = File::create(&tmp_path)?;
+      serde_yaml::to_writer(&mut file, self)?;
+      file.flush()?;
+    }
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+  }
+}
+
+impl YamlFileError {
+  /// Whether the file could not be read because it does not exist.
+  pub fn is_not_found(&self) -> bool {
+    matches!(self, YamlFileError::IoError(err) if err.kind() == io::ErrorKind::NotFound)
+  }
+}
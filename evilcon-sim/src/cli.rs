@@ -1,11 +1,14 @@
 
 //! Command line args.
 
-use crate::cardgame::Deck;
+use crate::cardgame::{Deck, GameRulesArgs, PlayerAgentsArgs};
 use crate::cardgame::genetic::GeneticAlgorithmArgs;
+use crate::runner::{BatchArgs, SprtArgs};
 
 use clap::{Parser, Subcommand};
 
+use std::path::PathBuf;
+
 /// Evilcon card game simulation engine.
 #[derive(Debug, Parser)]
 #[command(author, version)]
@@ -23,36 +26,40 @@ pub enum Command {
   /// Plays a single instance of the card game from a hex code.
   PlayFromCode {
     /// The base64-encoded string containing the game's seed and
-    /// player decks.
+    /// player decks. The rules must specify the same deck size as
+    /// the game code.
     code: String,
+    /// File to which a JSON trace of the game is written.
+    #[arg(long)]
+    trace_out: Option<PathBuf>,
+    #[command(flatten)]
+    agents: PlayerAgentsArgs,
+    #[command(flatten)]
+    rules: GameRulesArgs,
   },
   /// Plays the card game one or more times with the supplied player
   /// decks.
   PlaySequential {
-    /// Random seed as a u64. If not provided, generator will be
-    /// randomly seeded.
-    #[arg(long)]
-    seed: Option<u64>,
-    /// Number of runs to perform.
-    #[arg(short = 'n', long, default_value_t = 1)]
-    count: u32,
     /// Bottom player's deck.
     #[arg(short, long = "bottom")]
     bottom_deck: Deck,
     /// Top player's deck.
     #[arg(short, long = "top")]
     top_deck: Deck,
+    /// File to which a JSON trace of every game is written, as an
+    /// array with one trace per game.
+    #[arg(long)]
+    trace_out: Option<PathBuf>,
+    #[command(flatten)]
+    batch: BatchArgs,
+    #[command(flatten)]
+    agents: PlayerAgentsArgs,
+    #[command(flatten)]
+    rules: GameRulesArgs,
   },
   /// Plays the card game one or more times with the supplied player
   /// decks, using multiple threads to run in parallel.
   PlayParallel {
-    /// Random seed as a u64. If not provided, generator will be
-    /// randomly seeded.
-    #[arg(long)]
-    seed: Option<u64>,
-    /// Number of runs to perform.
-    #[arg(short = 'n', long, default_value_t = 1)]
-    count: u32,
     /// Number of threads to utilize. If not supplied, a best estimate
     /// will be made based on the CPU capabilities of the host
     /// machine.
@@ -64,6 +71,44 @@ pub enum Command {
     /// Top player's deck.
     #[arg(short, long = "top")]
     top_deck: Deck,
+    #[command(flatten)]
+    batch: BatchArgs,
+    #[command(flatten)]
+    sprt: SprtArgs,
+    #[command(flatten)]
+    agents: PlayerAgentsArgs,
+    #[command(flatten)]
+    rules: GameRulesArgs,
+  },
+  /// Plays every pair of decks from a file against each other,
+  /// alternating seats, and ranks the decks.
+  Tournament {
+    /// File of decks, one per line, each optionally preceded by a
+    /// name and a colon (as in `Clowns: 1, 2, 3`).
+    decks: PathBuf,
+    /// Number of games to play between each pair of decks.
+    #[arg(short = 'n', long, default_value_t = 10)]
+    games_per_pair: usize,
+    /// Random seed as a u64. If not provided, generator will be
+    /// randomly seeded.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Number of threads to utilize. If not supplied, a best estimate
+    /// will be made based on the CPU capabilities of the host
+    /// machine.
+    #[arg(long = "threads")]
+    thread_count: Option<usize>,
+    /// Ladder file in which to record the results of these games. The
+    /// file is created if it does not exist.
+    #[arg(long)]
+    ladder: Option<PathBuf>,
+    #[command(flatten)]
+    rules: GameRulesArgs,
+  },
+  /// Inspects a rating ladder.
+  Ladder {
+    #[command(subcommand)]
+    command: LadderCommand,
   },
   /// Runs a genetic algorithm to identify the most powerful decks.
   RunGeneticAlgorithm {
@@ -75,15 +120,43 @@ pub enum Command {
     /// machine.
     #[arg(long = "threads")]
     thread_count: Option<usize>,
+    /// File to which a checkpoint is written after every
+    /// generation. If not supplied, no checkpoints are written.
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// Continue a previous run from a checkpoint file. The algorithm
+    /// arguments, game rules, deck constraints, and gauntlet decks
+    /// stored in the checkpoint are used (with a warning for each one that differs
+    /// from the command line), and the generation count is the total
+    /// including completed generations.
+    #[arg(long)]
+    resume: Option<PathBuf>,
+    /// File to which statistics are written after every generation.
+    /// Files ending in `.csv` are written as CSV; anything else is
+    /// written as JSON lines.
+    #[arg(long)]
+    stats_out: Option<PathBuf>,
     #[command(flatten)]
     additional_args: GeneticAlgorithmArgs,
+    #[command(flatten)]
+    rules: GameRulesArgs,
   }
 }
 
+#[derive(Debug, Subcommand)]
+pub enum LadderCommand {
+  /// Prints the standings of every deck on the ladder.
+  Show {
+    /// The ladder file.
+    #[arg(default_value = "ladder.yml")]
+    ladder: PathBuf,
+  },
+}
+
 impl Command {
   pub fn min_log_level(&self) -> &'static str {
     match self {
-      Self::RunGeneticAlgorithm { .. } => "info",
+      Self::RunGeneticAlgorithm { .. } | Self::Tournament { .. } | Self::Ladder { .. } => "info",
       _ => "trace"
     }
   }
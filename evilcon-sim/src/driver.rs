@@ -1,11 +1,17 @@
 
 use crate::loader::GdScriptLoader;
 use crate::ast::identifier::Identifier;
-use crate::interpreter::eval::SuperglobalState;
+use crate::interpreter::eval::{SuperglobalState, EvaluatorState};
 use crate::interpreter::value::{Value, SimpleValue, ObjectInst};
 use crate::interpreter::class::{Class, ClassBuilder};
+use crate::interpreter::operator::expect_int;
+use crate::interpreter::method::{Method, MethodArgs};
+use crate::interpreter::error::EvalError;
+use crate::cardgame::GameWinner;
+use crate::cardgame::observer::{CardAction, CardHook, GameObserver, ObservedCard, card_owner, player_from_value};
 
 use glob::glob;
+use strum::VariantArray;
 
 use std::sync::Arc;
 
@@ -23,8 +29,12 @@ const GDSCRIPT_FILES: &[&str] = &[
   "../card_game/playing_field/destination_transform.gd",
   "../card_game/playing_field/card_container/card_container.gd",
   "../card_game/playing_field/player_agent/player_agent.gd",
+  "../card_game/playing_field/player_agent/null_ai_agent.gd",
+  "../card_game/playing_field/player_agent/greedy_ai_agent/greedy_ai_agent.gd",
   "../card_game/playing_field/player_agent/lookahead_ai_agent/lookahead_ai_agent.gd",
   "../card_game/playing_field/player_agent/lookahead_ai_agent/lookahead_priorities.gd",
+  "../card_game/playing_field/player_agent/monte_carlo_ai_agent/monte_carlo_simulation.gd",
+  "../card_game/playing_field/card_watcher/card_watcher.gd",
   "../util.gd",
   "../operator.gd",
   "../card_game/playing_card/playing_card_lists.gd",
@@ -32,9 +42,9 @@ const GDSCRIPT_FILES: &[&str] = &[
   "../card_game/playing_card/archetype.gd",
 ];
 
-const GDSCRIPT_GLOBS: &[&str] = &[
-  "../card_game/playing_card/card_type/*.gd",
-];
+/// Base classes of the individual card types. These are loaded with
+/// their card hooks augmented, like the card types themselves.
+const CARD_TYPE_GLOB: &str = "../card_game/playing_card/card_type/*.gd";
 
 pub fn load_all_files() -> anyhow::Result<SuperglobalState> {
   let mut loader = GdScriptLoader::new();
@@ -44,6 +54,7 @@ pub fn load_all_files() -> anyhow::Result<SuperglobalState> {
   }
   load_card_gd(&mut loader)?;
   load_card_game_api_gd(&mut loader)?;
+  load_monte_carlo_ai_agent_gd(&mut loader)?;
 
   // Each individual card type loader and to_string
   {
@@ -52,16 +63,22 @@ pub fn load_all_files() -> anyhow::Result<SuperglobalState> {
       match entry {
         Ok(path) => {
           let file_name = path.file_stem().unwrap().to_string_lossy().into_owned();
-          loader.load_file_augmented(&path, with_custom_to_string(move |_| file_name.to_owned()))?;
+          let custom_to_string = with_custom_to_string(move |_| file_name.to_owned());
+          loader.load_file_augmented(&path, |builder| with_card_hook_scopes(custom_to_string(builder)))?;
         }
         Err(e) => tracing::error!("Error during glob: {:?}", e),
       }
     }
   }
 
-  for glob in GDSCRIPT_GLOBS {
-    let glob = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), glob);
-    loader.load_all_files(&glob)?;
+  {
+    let glob_str = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), CARD_TYPE_GLOB);
+    for entry in glob(&glob_str).expect("Could not read glob pattern") {
+      match entry {
+        Ok(path) => loader.load_file_augmented(&path, with_card_hook_scopes)?,
+        Err(e) => tracing::error!("Error during glob: {:?}", e),
+      }
+    }
   }
   tracing::info!("Loaded all files.");
 
@@ -104,12 +121,41 @@ fn do_surgery(superglobals: &mut SuperglobalState) -> anyhow::Result<()> {
 
 fn with_custom_to_string(
   custom_to_string: impl Fn(&ObjectInst) -> String + Send + Sync + 'static,
-) -> (impl FnOnce(ClassBuilder) -> ClassBuilder + 'static) {
+) -> impl FnOnce(ClassBuilder) -> ClassBuilder + 'static {
   move |builder| {
     builder.custom_to_string(custom_to_string)
   }
 }
 
+/// Augments every card hook defined in a card type class to record,
+/// while it executes, that it is running for the given card. Stat
+/// changes are attributed to the innermost such hook.
+fn with_card_hook_scopes(builder: ClassBuilder) -> ClassBuilder {
+  CardHook::VARIANTS.iter().fold(builder, |builder, &hook| {
+    let method_name: &str = hook.into();
+    if builder.has_method(method_name) {
+      builder.modify_method(method_name, |method| with_card_hook_scope(hook, method))
+    } else {
+      builder
+    }
+  })
+}
+
+fn with_card_hook_scope(hook: CardHook, method: Method) -> Method {
+  method.wrapped(move |method, state, args| {
+    if state.observers().is_inactive() {
+      return method.call(state, args);
+    }
+    // Card hooks take `(playing_field, card, ...)`.
+    let card = args.0.get(1).cloned().unwrap_or(Value::Null);
+    let observers = state.observers().clone();
+    observers.enter_hook(hook, card);
+    let result = method.call(state, args);
+    observers.exit_hook();
+    result
+  })
+}
+
 fn load_card_gd(loader: &mut GdScriptLoader) -> anyhow::Result<()> {
   let file = format!("{}/../card_game/playing_card/card.gd", env!("CARGO_MANIFEST_DIR"));
   loader.load_file_augmented(&file, with_custom_to_string(|obj| {
@@ -122,115 +168,177 @@ fn load_card_gd(loader: &mut GdScriptLoader) -> anyhow::Result<()> {
   Ok(())
 }
 
+fn load_monte_carlo_ai_agent_gd(loader: &mut GdScriptLoader) -> anyhow::Result<()> {
+  // The agent chooses each card by playing out hypothetical games on
+  // virtual playing fields. Those games are not part of the real
+  // game, so the observers must not hear about them.
+  let file = format!("{}/../card_game/playing_field/player_agent/monte_carlo_ai_agent/monte_carlo_ai_agent.gd", env!("CARGO_MANIFEST_DIR"));
+  loader.load_file_augmented(&file, |builder| {
+    builder.modify_method("_get_next_card", |method| method.wrapped(|method, state, args| {
+      let observers = state.observers().clone();
+      observers.muted(|| method.call(state, args))
+    }))
+  })?;
+  Ok(())
+}
+
+/// How a hooked `CardGameApi` method identifies the card it acts on.
+#[derive(Debug, Clone, Copy)]
+enum CardArg {
+  /// `(playing_field, player, card_type, ...)`
+  CardType,
+  /// `(playing_field, player, card, ...)`
+  Card,
+  /// `(playing_field, card)`. The player is the card's owner.
+  OwnedCard,
+}
+
+/// A `CardGameApi` method which acts on a single card, and which is
+/// augmented to log and to notify the game's observers.
+#[derive(Debug, Clone, Copy)]
+struct CardActionHook {
+  method: &'static str,
+  min_arity: usize,
+  max_arity: usize,
+  card_arg: CardArg,
+  action: CardAction,
+}
+
+const CARD_ACTION_HOOKS: &[CardActionHook] = &[
+  CardActionHook { method: "draw_specific_card", min_arity: 3, max_arity: 3, card_arg: CardArg::CardType, action: CardAction::DrawSpecific },
+  CardActionHook { method: "play_card_from_hand", min_arity: 3, max_arity: 3, card_arg: CardArg::CardType, action: CardAction::PlayFromHand },
+  CardActionHook { method: "resurrect_card", min_arity: 3, max_arity: 3, card_arg: CardArg::CardType, action: CardAction::Resurrect },
+  CardActionHook { method: "play_card_from_deck", min_arity: 3, max_arity: 3, card_arg: CardArg::CardType, action: CardAction::PlayFromDeck },
+  // This function accepts additional args that I don't care about
+  CardActionHook { method: "play_card_from_nowhere", min_arity: 3, max_arity: 5, card_arg: CardArg::CardType, action: CardAction::PlayFromNowhere },
+  CardActionHook { method: "destroy_card", min_arity: 2, max_arity: 2, card_arg: CardArg::OwnedCard, action: CardAction::Destroy },
+  CardActionHook { method: "discard_card", min_arity: 3, max_arity: 3, card_arg: CardArg::CardType, action: CardAction::Discard },
+  CardActionHook { method: "move_card_from_discard_to_deck", min_arity: 3, max_arity: 3, card_arg: CardArg::CardType, action: CardAction::MoveFromDiscardToDeck },
+  // Ignore optional is_token arg
+  CardActionHook { method: "create_card", min_arity: 3, max_arity: 4, card_arg: CardArg::CardType, action: CardAction::Create },
+  CardActionHook { method: "copy_card", min_arity: 3, max_arity: 4, card_arg: CardArg::Card, action: CardAction::Copy },
+  CardActionHook { method: "exile_card", min_arity: 2, max_arity: 2, card_arg: CardArg::OwnedCard, action: CardAction::Exile },
+];
+
 fn load_card_game_api_gd(loader: &mut GdScriptLoader) -> anyhow::Result<()> {
   // We add tracing to a TON of these functions, since they're the
   // central access point for things in the game moving about.
   let file = format!("{}/../card_game/playing_field/util/card_game_api.gd", env!("CARGO_MANIFEST_DIR"));
   loader.load_file_augmented(&file, |builder| {
-    builder
-      .modify_method("draw_cards", |method| method.with_tracing(|_, args| {
-        match args.len() {
-          2 => { // playing_field and player
-            tracing::debug!(player=?args[1], "Attempt to draw 1 card(s)");
-          }
-          3 => {
-            tracing::debug!(player=?args[1], "Attempt to draw {} card(s)", &args[2]);
-          }
+    let builder = builder
+      .modify_method("draw_cards", |method| method.with_tracing(|state, args| {
+        let count = match args.len() {
+          2 => 1, // playing_field and player
+          3 => match expect_int("draw_cards", &args[2]) {
+            Ok(count) => count,
+            Err(err) => {
+              tracing::error!("Bad card count to draw_cards: {err}");
+              return;
+            }
+          },
           _ => {
             tracing::error!("Bad arity to draw_cards");
+            return;
           }
-        }
+        };
+        tracing::debug!(player=?args[1], "Attempt to draw {} card(s)", count);
+        notify_observers(state, &args[1], |observer, player| observer.on_cards_drawn(player, count));
       }))
-      .modify_method("draw_specific_card", |method| method.with_tracing(|_, args| {
-        if args.len() != 3 {
-          tracing::error!("Bad arity to draw_specific_card");
+      .modify_method("move_card", |method| method.with_tracing(|state, args| {
+        if state.observers().is_inactive() {
           return;
         }
-        tracing::debug!(player=?args[1], "Scry {} from deck", &args[2]);
+        if let Err(err) = notify_if_card_drawn(state, args) {
+          tracing::error!("Could not notify observers of move_card: {err}");
+        }
       }))
-      .modify_method("reshuffle_discard_pile", |method| method.with_tracing(|_, args| {
+      .modify_method("reshuffle_discard_pile", |method| method.with_tracing(|state, args| {
         if args.len() != 2 {
           tracing::error!("Bad arity to reshuffle_discard_pile");
           return;
         }
         tracing::debug!(player=?args[1], "Reshuffle discard pile");
-      }))
-      .modify_method("play_card_from_hand", |method| method.with_tracing(|_, args| {
-        if args.len() != 3 {
-          tracing::error!("Bad arity to play_card_from_hand");
-          return;
-        }
-        tracing::debug!(player=?args[1], "Play {} from hand", &args[2]);
-      }))
-      .modify_method("resurrect_card", |method| method.with_tracing(|_, args| {
-        if args.len() != 3 {
-          tracing::error!("Bad arity to resurrect_card");
-          return;
-        }
-        tracing::debug!(player=?args[1], "Resurrect {} from discard pile", &args[2]);
-      }))
-      .modify_method("play_card_from_deck", |method| method.with_tracing(|_, args| {
-        if args.len() != 3 {
-          tracing::error!("Bad arity to play_card_from_deck");
-          return;
-        }
-        tracing::debug!(player=?args[1], "Play {} from deck", &args[2]);
-      }))
-      .modify_method("play_card_from_nowhere", |method| method.with_tracing(|_, args| {
-        if args.len() < 3 { // This function accepts additional args that I don't care about
-          tracing::error!("Bad arity to play_card_from_nowhere");
-          return;
-        }
-        tracing::debug!(player=?args[1], "Play {} from nowhere (probably Mystery Box)", &args[2]);
-      }))
-      .modify_method("destroy_card", |method| method.with_tracing(|_, args| {
-        if args.len() != 2 {
-          tracing::error!("Bad arity to destroy_card");
-          return;
-        }
-        let player = try_get_owner(&args[1]);
-        tracing::debug!(player=player, "Destroy {}", &args[1]);
-      }))
-      .modify_method("discard_card", |method| method.with_tracing(|_, args| {
-        if args.len() != 3 {
-          tracing::error!("Bad arity to discard_card");
-          return;
-        }
-        tracing::debug!(player=?args[1], "Discard {} from hand", &args[2]);
-      }))
-      .modify_method("move_card_from_discard_to_deck", |method| method.with_tracing(|_, args| {
-        if args.len() != 3 {
-          tracing::error!("Bad arity to move_card_from_discard_to_deck");
-          return;
-        }
-        tracing::debug!(player=?args[1], "Move {} from discard to deck", &args[2]);
-      }))
-      .modify_method("create_card", |method| method.with_tracing(|_, args| {
-        if args.len() < 3 { // Ignore optional is_token arg
-          tracing::error!("Bad arity to create_card");
-          return;
-        }
-        tracing::debug!(player=?args[1], "Create card {}", &args[2]);
-      }))
-      .modify_method("copy_card", |method| method.with_tracing(|_, args| {
-        if args.len() < 3 { // Ignore optional is_token arg
-          tracing::error!("Bad arity to copy_card");
-          return;
-        }
-        tracing::debug!(player=?args[1], "Copy card {}", &args[2]);
-      }))
-      .modify_method("exile_card", |method| method.with_tracing(|_, args| {
-        if args.len() != 2 {
-          tracing::error!("Bad arity to exile_card");
-          return;
-        }
-        let player = try_get_owner(&args[1]);
-        tracing::debug!(player=player, "Exile {}", &args[1]);
-      }))
+        notify_observers(state, &args[1], |observer, player| observer.on_discard_pile_reshuffled(player));
+      }));
+    CARD_ACTION_HOOKS.iter().fold(builder, |builder, hook| {
+      builder.modify_method(hook.method, |method| method.with_tracing(move |state, args| on_card_action_hook(hook, state, args)))
+    })
   })?;
   Ok(())
 }
 
+fn on_card_action_hook(hook: &CardActionHook, state: &EvaluatorState, args: &MethodArgs) {
+  if args.expect_arity_within(hook.min_arity, hook.max_arity, hook.method).is_err() {
+    tracing::error!("Bad arity to {}", hook.method);
+    return;
+  }
+  match hook.card_arg {
+    CardArg::CardType | CardArg::Card => {
+      tracing::debug!(player=?args[1], "{} {}", hook.action, &args[2]);
+    }
+    CardArg::OwnedCard => {
+      let player = try_get_owner(&args[1]);
+      tracing::debug!(player=player, "{} {}", hook.action, &args[1]);
+    }
+  }
+  if state.observers().is_inactive() {
+    return;
+  }
+  let observed = match hook.card_arg {
+    CardArg::CardType => player_from_value(&args[1]).and_then(|player| Ok((player, ObservedCard::from_card_type(state, &args[2])?))),
+    CardArg::Card => player_from_value(&args[1]).and_then(|player| Ok((player, ObservedCard::from_card(state, &args[2])?))),
+    CardArg::OwnedCard => card_owner(state, &args[1]).and_then(|player| Ok((player, ObservedCard::from_card(state, &args[1])?))),
+  };
+  match observed {
+    Ok((player, card)) => {
+      state.observers().notify(|observer| observer.on_card_action(player, hook.action, &card));
+    }
+    Err(err) => {
+      tracing::error!("Could not notify observers of {}: {}", hook.method, err);
+    }
+  }
+}
+
+/// Notifies the game's observers if a call to `move_card` is about to
+/// draw a card, that is, to move it from a player's deck to their
+/// hand.
+fn notify_if_card_drawn(state: &EvaluatorState, args: &MethodArgs) -> Result<(), EvalError> {
+  args.expect_arity_within(3, 4, "move_card")?;
+  let (playing_field, source, destination) = (&args[0], &args[1], &args[2]);
+  for player in [GameWinner::Bottom, GameWinner::Top] {
+    let player_value = Value::from(player.to_string());
+    let deck = state.call_function_on(playing_field, "get_deck", vec![player_value.clone()])?;
+    if *source != deck {
+      continue;
+    }
+    let hand = state.call_function_on(playing_field, "get_hand", vec![player_value])?;
+    if *destination != hand {
+      return Ok(());
+    }
+    let source_index = match args.0.get(3) {
+      Some(opts) => state.call_function_on(opts, "get", vec![Value::from("source_index"), Value::from(-1)])?,
+      None => Value::from(-1),
+    };
+    let cards = state.call_function_on(source, "cards", Vec::new())?;
+    let card_type = state.call_function_on(&cards, "peek_card", vec![source_index])?;
+    let card = ObservedCard::from_card_type(state, &card_type)?;
+    state.observers().notify(|observer| observer.on_card_action(player, CardAction::Draw, &card));
+    return Ok(());
+  }
+  Ok(())
+}
+
+/// Notifies the game's observers of an event concerning a player.
+fn notify_observers(state: &EvaluatorState, player: &Value, mut callback: impl FnMut(&mut dyn GameObserver, GameWinner)) {
+  if state.observers().is_inactive() {
+    return;
+  }
+  match player_from_value(player) {
+    Ok(player) => state.observers().notify(|observer| callback(observer, player)),
+    Err(err) => tracing::error!("Could not notify observers: {err}"),
+  }
+}
+
 /// Best-effort attempt to get the owner, for logging purposes. If
 /// anything bad happens, returns a default value.
 fn try_get_owner(card_value: &Value) -> String {
@@ -9,7 +9,7 @@ use super::operator::{expect_int, expect_float_loosely, expect_string, expect_bo
 use crate::ast::identifier::Identifier;
 use crate::util::{try_sort_by, try_reduce};
 
-use rand::seq::SliceRandom;
+use rand::seq::{IndexedRandom, SliceRandom};
 
 use std::sync::Arc;
 use std::collections::HashMap;
@@ -124,6 +124,7 @@ fn array_class() -> Class {
   methods.insert(Identifier::from(GETITEM_METHOD_NAME), Method::rust_method(GETITEM_METHOD_NAME, array_getitem));
   methods.insert(Identifier::from("clear"), Method::rust_method("clear", array_clear));
   methods.insert(Identifier::from("shuffle"), Method::rust_method("shuffle", array_shuffle));
+  methods.insert(Identifier::from("pick_random"), Method::rust_method("pick_random", array_pick_random));
   methods.insert(Identifier::from("is_empty"), Method::rust_method("is_empty", array_is_empty));
   methods.insert(Identifier::from("remove_at"), Method::rust_method("remove_at", array_remove_at));
   methods.insert(Identifier::from("push_back"), Method::rust_method("push_back", array_push_back));
@@ -211,7 +212,7 @@ pub fn call_func(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value,
       )
     }
     Value::Lambda(lambda) => {
-      let lambda_name = lambda.contents.name.as_ref().map(|x| x.as_ref()).unwrap_or(&"<lambda>");
+      let lambda_name = lambda.contents.name.as_ref().map(|x| x.as_ref()).unwrap_or("<lambda>");
       let mut lambda_scope = lambda.outer_scope.clone();
       lambda_scope.bind_arguments(lambda_name, args.0, lambda.contents.params.clone())?;
       let result = lambda_scope.eval_body(&lambda.contents.body);
@@ -292,6 +293,17 @@ fn array_shuffle(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value,
   Ok(Value::Null)
 }
 
+fn array_pick_random(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, EvalError> {
+  let self_inst = expect_array("pick_random", state.self_instance())?.borrow();
+  args.expect_arity(0, "pick_random")?;
+  // Godot returns null (and logs an error) for empty arrays.
+  let value = state.do_random(|rng| self_inst.choose(rng).cloned());
+  if value.is_none() {
+    tracing::error!("Can't pick_random from empty array");
+  }
+  Ok(value.unwrap_or_default())
+}
+
 fn array_push_back(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, EvalError> {
   let mut self_inst = expect_array("push_back", state.self_instance())?.borrow_mut();
   let new_value = args.expect_one_arg("push_back")?;
@@ -332,7 +344,7 @@ fn array_fill(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, Eva
 fn array_map(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, EvalError> {
   let mut arr = expect_array("map", state.self_instance())?.borrow().clone();
   let callable = args.expect_one_arg("map")?;
-  let callable = callable.to_rust_function(&state);
+  let callable = callable.to_rust_function(state);
   for elem in &mut arr {
     *elem = callable(MethodArgs(vec![elem.clone()]))?;
   }
@@ -342,7 +354,7 @@ fn array_map(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, Eval
 fn array_any(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, EvalError> {
   let mut arr = expect_array("any", state.self_instance())?.borrow().clone();
   let callable = args.expect_one_arg("any")?;
-  let callable = callable.to_rust_function(&state);
+  let callable = callable.to_rust_function(state);
   for elem in &mut arr {
     if callable(MethodArgs(vec![elem.clone()])).unwrap().as_bool() {
       return Ok(Value::Bool(true));
@@ -354,7 +366,7 @@ fn array_any(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, Eval
 fn array_all(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, EvalError> {
   let mut arr = expect_array("all", state.self_instance())?.borrow().clone();
   let callable = args.expect_one_arg("all")?;
-  let callable = callable.to_rust_function(&state);
+  let callable = callable.to_rust_function(state);
   for elem in &mut arr {
     if !callable(MethodArgs(vec![elem.clone()])).unwrap().as_bool() {
       return Ok(Value::Bool(false));
@@ -366,7 +378,7 @@ fn array_all(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, Eval
 fn array_filter(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, EvalError> {
   let arr = expect_array("filter", state.self_instance())?.borrow().clone();
   let callable = args.expect_one_arg("filter")?;
-  let callable = callable.to_rust_function(&state);
+  let callable = callable.to_rust_function(state);
   let mut result_arr = Vec::with_capacity(arr.len());
   for elem in arr {
     if callable(MethodArgs(vec![elem.clone()]))?.as_bool() {
@@ -408,7 +420,7 @@ fn array_reduce(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, E
   let arr = expect_array("reduce", state.self_instance())?.borrow().clone();
   args.expect_arity_within(1, 2, "reduce")?;
   let callable = &args.0[0];
-  let callable = callable.to_rust_function(&state);
+  let callable = callable.to_rust_function(state);
   let mut accum = args.0.get(1).unwrap_or_default().clone();
   let mut iter = arr.into_iter();
   if accum.is_null() {
@@ -462,7 +474,7 @@ fn array_sort(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, Eva
 fn array_sort_custom(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, EvalError> {
   let mut arr = expect_array("sort_custom", state.self_instance())?.borrow_mut();
   let callable = args.expect_one_arg("sort_custom")?;
-  let callable = callable.to_rust_function(&state);
+  let callable = callable.to_rust_function(state);
   // NOTE CAREFULLY: This is one of the few places in this codebase
   // where we execute arbitrary user code inside of a
   // RefCell::borrow_mut. A badly written sort comparator function
@@ -518,7 +530,7 @@ fn dict_merge(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, Eva
 fn duplicate_method(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, EvalError> {
   let self_inst = state.self_instance();
   args.expect_arity_within(0, 1, "duplicate")?;
-  let arg = args.0.get(0).unwrap_or(&Value::Bool(false));
+  let arg = args.0.first().unwrap_or(&Value::Bool(false));
   let deep = expect_bool("duplicate", arg)?;
   if deep {
     Ok(self_inst.deep_copy())
@@ -541,7 +553,7 @@ fn string_substr(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value,
 }
 
 fn call_method_on_obj(state: &mut EvaluatorState, mut args: MethodArgs) -> Result<Value, EvalError> {
-  if args.len() < 1 {
+  if args.is_empty() {
     return Err(EvalError::WrongArity {
       function: "call".to_owned(),
       actual: args.len(),
@@ -557,5 +569,5 @@ fn callv_method_on_obj(state: &mut EvaluatorState, args: MethodArgs) -> Result<V
   let (method_name, args) = args.expect_two_args("callv")?;
   let method_name = expect_string("callv", &method_name)?;
   let args = expect_array("callv", &args)?.borrow().clone();
-  state.call_function_on(state.self_instance(), &method_name, args)
+  state.call_function_on(state.self_instance(), method_name, args)
 }
@@ -17,9 +17,11 @@ pub struct LazyConst {
   value: OnceLock<Result<SimpleValue, EvalError>>,
   // Invariant: initializer is always Some if the value is
   // uninitialized.
-  initializer: Mutex<Option<Box<dyn FnOnce(&EvaluatorState) -> Result<SimpleValue, EvalError> + Send + Sync>>>,
+  initializer: Mutex<Option<Initializer>>,
 }
 
+type Initializer = Box<dyn FnOnce(&EvaluatorState) -> Result<SimpleValue, EvalError> + Send + Sync>;
+
 impl LazyConst {
   pub fn new<F>(initializer: F) -> Self
   where F: FnOnce(&EvaluatorState) -> Result<SimpleValue, EvalError> + Send + Sync + 'static {
@@ -72,7 +74,7 @@ impl LazyConst {
       first_init = true;
       initializer(state)
     });
-    let value = as_ref_ok(&value);
+    let value = as_ref_ok(value);
     if first_init {
       value
     } else {
@@ -211,6 +211,10 @@ impl Class {
 }
 
 impl ClassBuilder {
+  pub fn has_method(&self, method_name: &str) -> bool {
+    self.methods.as_ref().is_some_and(|methods| methods.contains_key(method_name))
+  }
+
   pub fn modify_method<F>(mut self, method_name: &str, augmentation: F) -> Self
   where F: FnOnce(Method) -> Method {
     let Some(methods) = &mut self.methods else {
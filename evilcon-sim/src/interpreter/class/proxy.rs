@@ -81,12 +81,12 @@ impl<'a> BackedField<'a> {
 
 impl<'a> ProxyField for BackedField<'a> {
   fn get_field(&self, superglobals: &Arc<SuperglobalState>, object: &Value) -> Result<Value, EvalError> {
-    object.get_value_raw(&self.inner_field_name, superglobals)
+    object.get_value_raw(self.inner_field_name, superglobals)
   }
 
   fn set_field(&self, _: &Arc<SuperglobalState>, object: &Value, value: Value) -> Result<(), EvalError> {
     let new_value = (self.value_adjustment)(value)?;
-    object.set_value_raw(&self.inner_field_name, new_value)?;
+    object.set_value_raw(self.inner_field_name, new_value)?;
     Ok(())
   }
 }
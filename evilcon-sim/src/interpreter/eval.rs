@@ -5,12 +5,14 @@ use super::method::{Method, ScopedMethod, MethodArgs};
 use super::error::{EvalError, EvalErrorOrControlFlow, ControlFlow, LoopControlFlow, ExpectedArity};
 use super::operator::{eval_unary_op, eval_binary_op};
 use super::bootstrapping::BootstrappedTypes;
+use super::mocking;
 use crate::ast::identifier::{Identifier, ResourcePath};
 use crate::ast::file::SourceFile;
 use crate::ast::expr::Expr;
 use crate::ast::expr::operator::{BinaryOp, AssignOp};
 use crate::ast::decl::Parameter;
 use crate::ast::stmt::Stmt;
+use crate::cardgame::observer::ObserverSet;
 
 use ordermap::OrderMap;
 use rand::RngCore;
@@ -32,6 +34,7 @@ pub struct EvaluatorState {
   // I am going straight to hell for writing this in a ref cell. Oh
   // well, the consequences of my design choices.
   random_generator: Arc<RefCell<dyn RngCore>>,
+  observers: ObserverSet,
 }
 
 #[derive(Debug, Clone)]
@@ -50,24 +53,23 @@ impl EvaluatorState {
       enclosing_class: None,
       superglobal_state,
       random_generator: Arc::new(RefCell::new(random_generator)),
+      observers: ObserverSet::default(),
     }
   }
 
-  fn new_with_shared_rng(superglobal_state: Arc<SuperglobalState>, random_generator: Arc<RefCell<dyn RngCore>>) -> Self {
+  /// A new state that only shares the RNG and the game observers with
+  /// `self`.
+  pub fn fresh_state(&self) -> Self {
     EvaluatorState {
       self_instance: Box::new(Value::default()),
       locals: HashMap::new(),
       enclosing_class: None,
-      superglobal_state,
-      random_generator,
+      superglobal_state: Arc::clone(&self.superglobal_state),
+      random_generator: Arc::clone(&self.random_generator),
+      observers: self.observers.clone(),
     }
   }
 
-  /// A new state that only shares the RNG with `self`.
-  pub fn fresh_state(&self) -> Self {
-    Self::new_with_shared_rng(Arc::clone(&self.superglobal_state), Arc::clone(&self.random_generator))
-  }
-
   pub fn bootstrapped_classes(&self) -> &BootstrappedTypes {
     &self.superglobal_state.bootstrapped_classes
   }
@@ -86,6 +88,16 @@ impl EvaluatorState {
     self
   }
 
+  pub fn with_observers(mut self, observers: ObserverSet) -> Self {
+    self.observers = observers;
+    self
+  }
+
+  /// The observers of the game being played, if any.
+  pub fn observers(&self) -> &ObserverSet {
+    &self.observers
+  }
+
   pub fn self_instance(&self) -> &Value {
     &self.self_instance
   }
@@ -143,7 +155,7 @@ impl EvaluatorState {
   /// calls, so we have to support both lookup orders.
   pub fn get_func_prefer_superglobal(&self, ident: &Identifier) -> Option<ScopedMethod> {
     if let Some(func) = self.get_superglobal_func(ident) {
-      return Some(func.clone().scoped(None));
+      Some(func.clone().scoped(None))
     } else {
       self.get_func(ident)
     }
@@ -192,12 +204,13 @@ impl EvaluatorState {
         Err(EvalError::UndefinedVariable(name.clone().into()))
       }
       Expr::GetNode(node) => {
-        // Okay, this is only used (currently) in LookaheadAiAgent.
-        // And its used to evaluate animations (which don't run in
-        // this simulation). We have to parse it because it's part of
-        // the code, but if we try to eval a GetNode then we've gone
-        // down a code path I didn't expect.
-        Err(EvalError::UnexpectedGetNode(node.clone().into()))
+        // Mocked scenes create the few child nodes the simulation
+        // needs (such as MonteCarloAIAgent's timer). Any other GetNode
+        // (LookaheadAiAgent uses them to evaluate animations, which
+        // don't run in this simulation) means we've gone down a code
+        // path I didn't expect.
+        self.self_instance.get_value_raw(&mocking::child_node_var(node.as_ref()), &self.superglobal_state)
+          .map_err(|_| EvalError::UnexpectedGetNode(node.clone().into()))
       }
       Expr::Call { func, args } => {
         let func = match func.as_ref() {
@@ -289,7 +302,7 @@ impl EvaluatorState {
   pub fn eval_expr_for_assignment(&self, expr: &Expr) -> Result<AssignmentLeftHand, EvalError> {
     match expr {
       Expr::Name(name) => {
-        Ok(AssignmentLeftHand::Name(name.clone().into()))
+        Ok(AssignmentLeftHand::Name(name.clone()))
       }
       Expr::Subscript(left, right) => {
         let left = self.eval_expr(left)?;
@@ -298,7 +311,7 @@ impl EvaluatorState {
       }
       Expr::Attr(left, name) => {
         let left = self.eval_expr(left)?;
-        Ok(AssignmentLeftHand::Attr(left, name.clone().into()))
+        Ok(AssignmentLeftHand::Attr(left, name.clone()))
       }
       other => {
         Err(EvalError::CannotAssignTo(other.clone()))
@@ -309,7 +322,7 @@ impl EvaluatorState {
   pub fn eval_assignment_left_hand_as_expr(&self, left_hand: &AssignmentLeftHand) -> Result<Value, EvalError> {
     match left_hand {
       AssignmentLeftHand::Name(name) => {
-        self.eval_expr(&Expr::Name(name.clone().into()))
+        self.eval_expr(&Expr::Name(name.clone()))
       }
       AssignmentLeftHand::Subscript(left, right) => {
         // Just do it the Python way, even though Godot doesn't :)
@@ -378,22 +391,20 @@ impl EvaluatorState {
       Stmt::While(while_stmt) => {
         while self.eval_expr(&while_stmt.condition)?.as_bool() {
           let inner_res = self.eval_body(&while_stmt.body);
-          if let Some(cf) = ControlFlow::extract_loop_control(inner_res)? {
-            if cf == LoopControlFlow::Break {
+          if let Some(cf) = ControlFlow::extract_loop_control(inner_res)?
+            && cf == LoopControlFlow::Break {
               break;
             }
-          }
         }
       }
       Stmt::For(for_stmt) => {
         let iterable = self.eval_expr(&for_stmt.iterable)?.try_iter()?;
         for elem in iterable {
           self.set_local_var(for_stmt.variable.clone(), elem);
-          if let Some(cf) = ControlFlow::extract_loop_control(self.eval_body(&for_stmt.body))? {
-            if cf == LoopControlFlow::Break {
+          if let Some(cf) = ControlFlow::extract_loop_control(self.eval_body(&for_stmt.body))?
+            && cf == LoopControlFlow::Break {
               break;
             }
-          }
         }
       }
       Stmt::Match(match_stmt) => {
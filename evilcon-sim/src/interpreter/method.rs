@@ -32,9 +32,11 @@ pub struct ScopedMethod {
 pub struct RustMethod {
   pub name: Identifier,
   pub is_static: bool,
-  pub body: Arc<dyn Fn(&mut EvaluatorState, MethodArgs) -> Result<Value, EvalError> + Send + Sync>,
+  pub body: RustMethodBody,
 }
 
+pub type RustMethodBody = Arc<dyn Fn(&mut EvaluatorState, MethodArgs) -> Result<Value, EvalError> + Send + Sync>;
+
 #[derive(Debug, Clone)]
 pub struct MethodArgs(pub Vec<Value>);
 
@@ -150,6 +152,17 @@ impl Method {
     })
   }
 
+  /// Replaces this method with `wrapper`, which receives the
+  /// original method and is responsible for calling it.
+  pub fn wrapped<F>(self, wrapper: F) -> Self
+  where F: Fn(&Method, &mut EvaluatorState, MethodArgs) -> Result<Value, EvalError> + Send + Sync + 'static {
+    Method::RustMethod(RustMethod {
+      name: self.name().clone(),
+      is_static: self.is_static(),
+      body: Arc::new(move |state, args| wrapper(&self, state, args)),
+    })
+  }
+
   pub fn scoped(self, owning_class: Option<Arc<Class>>) -> ScopedMethod {
     ScopedMethod {
       method: self,
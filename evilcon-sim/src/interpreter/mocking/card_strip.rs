@@ -19,10 +19,11 @@ pub(super) fn card_strip_class(node: Arc<Class>) -> Class {
     Expr::call("load", vec![Expr::string("res://card_game/playing_field/card_container/card_container.gd")])
     .attr_call("new", Vec::new());
 
-  let mut instance_vars = Vec::new();
-  instance_vars.push(InstanceVar::new("__evilconsim_cards", Some(cards_initial_value)));
-  instance_vars.push(InstanceVar::new("card_added", Some(Expr::NewSignal)));
-  instance_vars.push(InstanceVar::new("cards_modified", Some(Expr::NewSignal)));
+  let instance_vars = vec![
+    InstanceVar::new("__evilconsim_cards", Some(cards_initial_value)),
+    InstanceVar::new("card_added", Some(Expr::NewSignal)),
+    InstanceVar::new("cards_modified", Some(Expr::NewSignal)),
+  ];
 
   let mut methods = HashMap::new();
   methods.insert(Identifier::new("_init"), Method::rust_method("_init", card_strip_constructor));
@@ -9,23 +9,59 @@ mod playing_field;
 mod randomness;
 mod stats;
 mod stats_panel;
+mod threading;
 mod turn_transitions;
+mod virtualization;
 
 pub mod codex;
 
 pub use playing_field::{ENDGAME_VARIABLE, SECOND_PLAYER_FORT_ADVANTAGE};
 pub use turn_transitions::{PLAY_FULL_GAME_METHOD, TURN_TRANSITIONS_RES_PATH};
-pub use stats_panel::DEFAULT_FORT_DEFENSE;
+pub use stats_panel::{DEFAULT_FORT_DEFENSE, DESTINY_SONG_LIMIT, DESTINY_SONG_LIMIT_VARIABLE};
 
 pub const PLAYING_FIELD_RES_PATH: &str = "res://card_game/playing_field/playing_field.gd";
 
-use super::class::{Class, ClassBuilder};
+/// A mocked `.tscn` file whose root node is a player agent.
+struct AgentScene {
+  tscn_path: &'static str,
+  /// The script attached to the root node.
+  script_path: &'static str,
+  /// Child nodes of the root, as pairs of node name and the global
+  /// class to instantiate for that node.
+  children: &'static [(&'static str, &'static str)],
+}
+
+const PLAYER_AGENT_SCENES: &[AgentScene] = &[
+  AgentScene {
+    tscn_path: "res://card_game/playing_field/player_agent/greedy_ai_agent/greedy_ai_agent.tscn",
+    script_path: "res://card_game/playing_field/player_agent/greedy_ai_agent/greedy_ai_agent.gd",
+    children: &[],
+  },
+  AgentScene {
+    tscn_path: "res://card_game/playing_field/player_agent/lookahead_ai_agent/lookahead_ai_agent.tscn",
+    script_path: "res://card_game/playing_field/player_agent/lookahead_ai_agent/lookahead_ai_agent.gd",
+    children: &[],
+  },
+  AgentScene {
+    tscn_path: "res://card_game/playing_field/player_agent/monte_carlo_ai_agent/monte_carlo_ai_agent.tscn",
+    script_path: "res://card_game/playing_field/player_agent/monte_carlo_ai_agent/monte_carlo_ai_agent.gd",
+    children: &[("AwaitThreadTimer", "Timer")],
+  },
+];
+
+/// Prefix of the instance variable holding a child node created by a
+/// mocked scene. `$Name` expressions are evaluated by looking up this
+/// variable on `self`.
+const CHILD_NODE_PREFIX: &str = "__evilconsim_node_";
+
+use super::class::{Class, ClassBuilder, InstanceVar};
 use super::class::constant::LazyConst;
 use super::value::{Value, SimpleValue};
 use super::eval::{SuperglobalState, EvaluatorState};
 use super::method::{MethodArgs, Method};
 use super::error::EvalError;
 use super::operator::{expect_string, expect_int_loosely, expect_float_loosely};
+use crate::ast::expr::Expr;
 use crate::ast::identifier::{Identifier, ResourcePath};
 
 use itertools::Itertools;
@@ -74,7 +110,7 @@ pub fn bind_mocked_classes(superglobals: &mut SuperglobalState) {
   superglobals.add_file(ResourcePath::new("res://card_game/playing_field/card_container/card_container.tscn"), Arc::new(card_container_tscn));
 
   // Randomness
-  let randomness = randomness::randomness_class(Arc::clone(&superglobals.bootstrapped_classes().refcounted()));
+  let randomness = randomness::randomness_class(Arc::clone(superglobals.bootstrapped_classes().refcounted()));
   superglobals.add_file(ResourcePath::new("res://card_game/playing_field/randomness.gd"), Arc::new(randomness));
 
   // GameStatsPanel
@@ -96,7 +132,7 @@ pub fn bind_mocked_classes(superglobals: &mut SuperglobalState) {
   superglobals.bind_class(Identifier::new("CardGameTurnTransitions"), turn_transitions);
 
   // InputBlockAnimation placeholder (needs to inherit from Object so we get free())
-  let input_block_animation = ClassBuilder::default().parent(Arc::clone(&superglobals.bootstrapped_classes().object())).build();
+  let input_block_animation = ClassBuilder::default().parent(Arc::clone(superglobals.bootstrapped_classes().object())).build();
   superglobals.add_file(ResourcePath::new("res://card_game/playing_field/animation/input_block_animation.gd"), Arc::new(input_block_animation));
 
   // A bunch of placeholders that CardGameApi needs :)
@@ -104,6 +140,36 @@ pub fn bind_mocked_classes(superglobals: &mut SuperglobalState) {
   superglobals.add_file(ResourcePath::new("res://card_game/playing_card/deck_card_display/deck_card_display.tscn"), Arc::new(dummy_class()));
   superglobals.add_file(ResourcePath::new("res://card_game/playing_field/animation/puff_of_smoke/puff_of_smoke_animation.gd"), Arc::new(dummy_class()));
   superglobals.add_file(ResourcePath::new("res://card_game/playing_field/animation/musical_note/musical_note_animation.gd"), Arc::new(dummy_class()));
+
+  // Timer
+  let timer = timer_class(Arc::clone(&node));
+  superglobals.bind_class(Identifier::new("Timer"), Arc::new(timer));
+
+  // WorkerThreadPool and Mutex (see threading.rs; the
+  // MonteCarloSimulation which uses them is parsed from the real .gd
+  // file)
+  let worker_thread_pool = threading::worker_thread_pool_class(Arc::clone(superglobals.bootstrapped_classes().object()));
+  superglobals.bind_class(Identifier::new("WorkerThreadPool"), Arc::new(worker_thread_pool));
+  let mutex = threading::mutex_class(Arc::clone(superglobals.bootstrapped_classes().refcounted()));
+  superglobals.bind_class(Identifier::new("Mutex"), Arc::new(mutex));
+
+  // Virtualization
+  let virtualization = virtualization::virtualization_class(Arc::clone(&node));
+  let virtualization = Arc::new(virtualization);
+  superglobals.add_file(ResourcePath::new(virtualization::VIRTUALIZATION_RES_PATH), Arc::clone(&virtualization));
+  superglobals.bind_class(Identifier::new("Virtualization"), virtualization);
+
+  // Player agent scenes (the scripts themselves are parsed from the
+  // real .gd files)
+  for scene in PLAYER_AGENT_SCENES {
+    superglobals.add_file(ResourcePath::new(scene.tscn_path), Arc::new(scene_class(scene)));
+  }
+}
+
+/// The variable name under which a mocked scene stores its child
+/// node `name`.
+pub fn child_node_var(name: &str) -> String {
+  format!("{CHILD_NODE_PREFIX}{name}")
 }
 
 pub fn bind_mocked_constants(superglobals: &mut SuperglobalState) {
@@ -168,6 +234,48 @@ fn dummy_class() -> Class {
   Class::default()
 }
 
+/// A mocked `.tscn` file whose `instantiate` method constructs an
+/// instance of the scene's root script, along with the scene's child
+/// nodes (see [`child_node_var`]). Children of child nodes are not
+/// created.
+fn scene_class(scene: &'static AgentScene) -> Class {
+  let mut methods = HashMap::new();
+  methods.insert(Identifier::new("instantiate"), Method::rust_static_method("instantiate", move |state, args| {
+    args.expect_arity(0, "instantiate")?;
+    let script = state.get_file(scene.script_path)
+      .ok_or_else(|| EvalError::UndefinedClass(scene.script_path.to_owned()))?;
+    let instance = state.call_function_on_class(&script, "new", Vec::new())?;
+    for (node_name, class_name) in scene.children {
+      let Some(SimpleValue::ClassRef(class)) = state.superglobal_state().get_var(*class_name) else {
+        return Err(EvalError::UndefinedClass((*class_name).to_owned()));
+      };
+      let child = state.call_function_on_class(class, "new", Vec::new())?;
+      instance.set_value_raw(&child_node_var(node_name), child)?;
+    }
+    Ok(instance)
+  }));
+  ClassBuilder::default()
+    .methods(methods)
+    .build()
+}
+
+/// A Godot `Timer` that never fires. Code awaiting its `timeout`
+/// signal continues immediately, since we ignore `await`.
+fn timer_class(node: Arc<Class>) -> Class {
+  let instance_vars = vec![
+    InstanceVar::new("timeout", Some(Expr::NewSignal)),
+  ];
+  let mut methods = HashMap::new();
+  methods.insert(Identifier::new("start"), Method::noop());
+  methods.insert(Identifier::new("stop"), Method::noop());
+  ClassBuilder::default()
+    .name("Timer")
+    .parent(node)
+    .instance_vars(instance_vars)
+    .methods(methods)
+    .build()
+}
+
 fn preload_method(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, EvalError> {
   args.expect_arity(1, "preload")?;
   let [arg] = args.0.try_into().unwrap();
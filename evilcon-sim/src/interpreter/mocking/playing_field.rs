@@ -38,7 +38,6 @@ impl CardKind {
 
 // Intentionally omitted:
 // * _ready (all AI setup and node setup that we do by hand)
-// * replace_player_agent (will be done by hand)
 // * popup_display_card (visual stuff)
 // * Like twenty signal response methods that do nothing but animations and input
 // * Several private internal helpers
@@ -48,31 +47,32 @@ pub(super) fn playing_field_class(node: Arc<Class>) -> Class {
   constants.insert(Identifier::new("Randomness"), LazyConst::preload("res://card_game/playing_field/randomness.gd"));
   constants.insert(Identifier::new("EventLogger"), LazyConst::preload("res://card_game/playing_field/event_logger.gd"));
 
-  let mut instance_vars = Vec::new();
-  instance_vars.push(InstanceVar::new("turn_number", Some(Expr::from(-1))));
-  instance_vars.push(InstanceVar::new("turn_player", Some(Expr::from("BOTTOM"))));
-  instance_vars.push(InstanceVar::new("randomness", Some(
-    Expr::name("Randomness").attr_call("new", vec![]),
-  )));
-  instance_vars.push(InstanceVar::new("event_logger", Some(
-    Expr::name("EventLogger").attr_call("new", vec![]),
-  )));
-  instance_vars.push(InstanceVar::new("top_cards_are_hidden", Some(Expr::from(true))));
-  instance_vars.push(InstanceVar::new("top_cards_are_hidden", Some(Expr::from(false))));
-  instance_vars.push(InstanceVar::new("plays_animations", Some(Expr::from(false))));
-  instance_vars.push(InstanceVar::new("__evilconsim_deck_bottom", Some(instantiate_card_strip(CardKind::CardType))));
-  instance_vars.push(InstanceVar::new("__evilconsim_deck_top", Some(instantiate_card_strip(CardKind::CardType))));
-  instance_vars.push(InstanceVar::new("__evilconsim_discardpile_bottom", Some(instantiate_card_strip(CardKind::CardType))));
-  instance_vars.push(InstanceVar::new("__evilconsim_discardpile_top", Some(instantiate_card_strip(CardKind::CardType))));
-  instance_vars.push(InstanceVar::new("__evilconsim_hand_bottom", Some(instantiate_card_strip(CardKind::CardType))));
-  instance_vars.push(InstanceVar::new("__evilconsim_hand_top", Some(instantiate_card_strip(CardKind::CardType))));
-  instance_vars.push(InstanceVar::new("__evilconsim_minionstrip_bottom", Some(instantiate_card_strip(CardKind::PlayedCard))));
-  instance_vars.push(InstanceVar::new("__evilconsim_minionstrip_top", Some(instantiate_card_strip(CardKind::PlayedCard))));
-  instance_vars.push(InstanceVar::new("__evilconsim_effectstrip_bottom", Some(instantiate_card_strip(CardKind::PlayedCard))));
-  instance_vars.push(InstanceVar::new("__evilconsim_effectstrip_top", Some(instantiate_card_strip(CardKind::PlayedCard))));
-  instance_vars.push(InstanceVar::new("__evilconsim_statspanel_top", Some(instantiate_stats_panel())));
-  instance_vars.push(InstanceVar::new("__evilconsim_statspanel_bottom", Some(instantiate_stats_panel())));
-  instance_vars.push(InstanceVar::new(ENDGAME_VARIABLE, Some(Expr::Literal(Literal::Null))));
+  let instance_vars = vec![
+    InstanceVar::new("turn_number", Some(Expr::from(-1))),
+    InstanceVar::new("turn_player", Some(Expr::from("BOTTOM"))),
+    InstanceVar::new("randomness", Some(
+      Expr::name("Randomness").attr_call("new", vec![]),
+    )),
+    InstanceVar::new("event_logger", Some(
+      Expr::name("EventLogger").attr_call("new", vec![]),
+    )),
+    InstanceVar::new("top_cards_are_hidden", Some(Expr::from(true))),
+    InstanceVar::new("top_cards_are_hidden", Some(Expr::from(false))),
+    InstanceVar::new("plays_animations", Some(Expr::from(false))),
+    InstanceVar::new("__evilconsim_deck_bottom", Some(instantiate_card_strip(CardKind::CardType))),
+    InstanceVar::new("__evilconsim_deck_top", Some(instantiate_card_strip(CardKind::CardType))),
+    InstanceVar::new("__evilconsim_discardpile_bottom", Some(instantiate_card_strip(CardKind::CardType))),
+    InstanceVar::new("__evilconsim_discardpile_top", Some(instantiate_card_strip(CardKind::CardType))),
+    InstanceVar::new("__evilconsim_hand_bottom", Some(instantiate_card_strip(CardKind::CardType))),
+    InstanceVar::new("__evilconsim_hand_top", Some(instantiate_card_strip(CardKind::CardType))),
+    InstanceVar::new("__evilconsim_minionstrip_bottom", Some(instantiate_card_strip(CardKind::PlayedCard))),
+    InstanceVar::new("__evilconsim_minionstrip_top", Some(instantiate_card_strip(CardKind::PlayedCard))),
+    InstanceVar::new("__evilconsim_effectstrip_bottom", Some(instantiate_card_strip(CardKind::PlayedCard))),
+    InstanceVar::new("__evilconsim_effectstrip_top", Some(instantiate_card_strip(CardKind::PlayedCard))),
+    InstanceVar::new("__evilconsim_statspanel_top", Some(instantiate_stats_panel())),
+    InstanceVar::new("__evilconsim_statspanel_bottom", Some(instantiate_stats_panel())),
+    InstanceVar::new(ENDGAME_VARIABLE, Some(Expr::Literal(Literal::Null))),
+  ];
 
   let mut methods = HashMap::new();
   methods.insert(Identifier::new("with_animation"), Method::noop());
@@ -88,6 +88,7 @@ pub(super) fn playing_field_class(node: Arc<Class>) -> Class {
   methods.insert(Identifier::new("animate_card_moving"), Method::noop());
   methods.insert(Identifier::new("hand_cards_are_hidden"), Method::rust_method("hand_cards_are_hidden", hand_cards_are_hidden));
   methods.insert(Identifier::new("player_agent"), Method::rust_method("player_agent", player_agent));
+  methods.insert(Identifier::new("replace_player_agent"), Method::rust_method("replace_player_agent", replace_player_agent));
 
   ClassBuilder::default()
     .parent(node)
@@ -162,3 +163,19 @@ fn player_agent(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, E
     _ => Err(EvalError::domain_error("Bad player agent")),
   }
 }
+
+// NOTE: Like the initial agent setup in `cardgame`, this doesn't
+// bother to call added_to_playing_field or removed_from_playing_field.
+// The only supported agent that uses them is the Monte Carlo agent,
+// and it only uses them to maintain a card watcher it never consults.
+fn replace_player_agent(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, EvalError> {
+  let (player, agent) = args.expect_two_args("replace_player_agent")?;
+  let var_name = match expect_string("replace_player_agent", &player)? {
+    "BOTTOM" => "_bottom_agent",
+    "TOP" => "_top_agent",
+    _ => { return Err(EvalError::domain_error("Bad player agent")); }
+  };
+  agent.set_value("controlled_player", player, state.superglobal_state())?;
+  state.self_instance().set_value(var_name, agent, state.superglobal_state())?;
+  Ok(Value::Null)
+}
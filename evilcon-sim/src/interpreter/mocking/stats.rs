@@ -5,9 +5,11 @@ use crate::interpreter::method::{Method, MethodArgs};
 use crate::interpreter::value::{SimpleValue, Value};
 use crate::interpreter::eval::EvaluatorState;
 use crate::interpreter::error::EvalError;
-use crate::interpreter::operator::{expect_int_loosely, expect_string};
+use crate::interpreter::operator::{expect_int, expect_int_loosely, expect_string};
 use crate::ast::identifier::Identifier;
-use super::stats_panel::DESTINY_SONG_LIMIT;
+use crate::cardgame::GameWinner;
+use crate::cardgame::observer::{ObservedCard, Stat, StatChange, StatSource, card_owner, player_from_value};
+use super::stats_panel::DESTINY_SONG_LIMIT_VARIABLE;
 
 use std::sync::Arc;
 use std::collections::HashMap;
@@ -44,37 +46,37 @@ pub(super) fn stats_static_class(node: Arc<Class>) -> Class {
   methods.insert(Identifier::new("play_animation_for_stat_change"), Method::static_noop());
   methods.insert(Identifier::new("show_text"), Method::static_noop());
   methods.insert(Identifier::new("set_evil_points"), Method::rust_static_method("set_evil_points", |state, args| {
-    basic_set_stat("set_evil_points", "evil_points", state, args)?;
+    basic_set_stat("set_evil_points", Stat::EvilPoints, state, args)?;
     Ok(Value::Null)
   }));
   methods.insert(Identifier::new("add_evil_points"), Method::rust_static_method("add_evil_points", |state, args| {
-    basic_add_stat("add_evil_points", "evil_points", state, args)?;
+    basic_add_stat("add_evil_points", Stat::EvilPoints, state, args)?;
     Ok(Value::Null)
   }));
   methods.insert(Identifier::new("set_fort_defense"), Method::rust_static_method("set_fort_defense", |state, args| {
-    let res = basic_set_stat("set_fort_defense", "fort_defense", state, args)?;
+    let res = basic_set_stat("set_fort_defense", Stat::FortDefense, state, args)?;
     if res.new_value <= 0 {
       send_endgame_signal(state, res.playing_field, other_player(res.player)?)?;
     }
     Ok(Value::Null)
   }));
   methods.insert(Identifier::new("add_fort_defense"), Method::rust_static_method("add_fort_defense", |state, args| {
-    let res = basic_add_stat("add_fort_defense", "fort_defense", state, args)?;
+    let res = basic_add_stat("add_fort_defense", Stat::FortDefense, state, args)?;
     if res.new_value <= 0 {
       send_endgame_signal(state, res.playing_field, other_player(res.player)?)?;
     }
     Ok(Value::Null)
   }));
   methods.insert(Identifier::new("set_destiny_song"), Method::rust_static_method("set_destiny_song", |state, args| {
-    let res = basic_set_stat("set_destiny_song", "destiny_song", state, args)?;
-    if res.new_value >= DESTINY_SONG_LIMIT {
+    let res = basic_set_stat("set_destiny_song", Stat::DestinySong, state, args)?;
+    if res.new_value >= destiny_song_limit(state, &res)? {
       send_endgame_signal(state, res.playing_field, res.player)?;
     }
     Ok(Value::Null)
   }));
   methods.insert(Identifier::new("add_destiny_song"), Method::rust_static_method("add_destiny_song", |state, args| {
-    let res = basic_add_stat("add_destiny_song", "destiny_song", state, args)?;
-    if res.new_value >= DESTINY_SONG_LIMIT {
+    let res = basic_add_stat("add_destiny_song", Stat::DestinySong, state, args)?;
+    if res.new_value >= destiny_song_limit(state, &res)? {
       send_endgame_signal(state, res.playing_field, res.player)?;
     }
     Ok(Value::Null)
@@ -92,30 +94,52 @@ pub(super) fn stats_static_class(node: Arc<Class>) -> Class {
     .build()
 }
 
-fn basic_set_stat(func_name: &str, stat_name: &str, state: &mut EvaluatorState, args: MethodArgs) -> Result<BasicStatResult, EvalError> {
+fn basic_set_stat(func_name: &str, stat: Stat, state: &mut EvaluatorState, args: MethodArgs) -> Result<BasicStatResult, EvalError> {
+  let stat_name: &str = stat.into();
   let (playing_field, player, new_value) = args.expect_three_args(func_name)?;
   let stats = state.call_function_on(&playing_field, "get_stats", vec![player.clone()])?;
-  stats.set_value(stat_name, new_value.clone(), state.superglobal_state())?;
+  let old_value = if !state.observers().is_inactive() {
+    Some(expect_int_loosely(stat_name, &stats.get_value(stat_name, state.superglobal_state())?)?)
+  } else {
+    None
+  };
+  stats.set_value(stat_name, new_value, state.superglobal_state())?;
+  // The stats panel clamps some stats, so read back the value it
+  // actually stored.
+  let new_value = expect_int_loosely(stat_name, &stats.get_value(stat_name, state.superglobal_state())?)?;
+  if let Some(old_value) = old_value {
+    notify_stat_change(state, player_from_value(&player)?, stat, None, old_value, new_value)?;
+  }
   Ok(BasicStatResult {
-    new_value: expect_int_loosely(stat_name, &new_value)?,
+    new_value,
     playing_field,
     player,
   })
 }
 
-fn basic_add_stat(func_name: &str, stat_name: &str, state: &mut EvaluatorState, args: MethodArgs) -> Result<BasicStatResult, EvalError> {
+fn basic_add_stat(func_name: &str, stat: Stat, state: &mut EvaluatorState, args: MethodArgs) -> Result<BasicStatResult, EvalError> {
+  let stat_name: &str = stat.into();
   let (playing_field, player, delta_value) = args.expect_three_args(func_name)?;
   let delta_value = expect_int_loosely(stat_name, &delta_value)?;
   let stats = state.call_function_on(&playing_field, "get_stats", vec![player.clone()])?;
   let old_value = expect_int_loosely(stat_name, &stats.get_value(stat_name, state.superglobal_state())?)?;
   stats.set_value(stat_name, Value::from(old_value + delta_value), state.superglobal_state())?;
+  let new_value = expect_int_loosely(stat_name, &stats.get_value(stat_name, state.superglobal_state())?)?;
+  if !state.observers().is_inactive() {
+    notify_stat_change(state, player_from_value(&player)?, stat, None, old_value, new_value)?;
+  }
   Ok(BasicStatResult {
-    new_value: old_value + delta_value,
+    new_value,
     playing_field,
     player,
   })
 }
 
+fn destiny_song_limit(state: &EvaluatorState, res: &BasicStatResult) -> Result<i64, EvalError> {
+  let stats = state.call_function_on(&res.playing_field, "get_stats", vec![res.player.clone()])?;
+  expect_int("(destiny song limit)", &stats.get_value(DESTINY_SONG_LIMIT_VARIABLE, state.superglobal_state())?)
+}
+
 fn set_card_level(state: &mut EvaluatorState, mut args: MethodArgs) -> Result<Value, EvalError> {
   args.expect_arity_within(3, 4, "set_level")?;
   // Don't need the last arg, so ignore it if present.
@@ -125,6 +149,10 @@ fn set_card_level(state: &mut EvaluatorState, mut args: MethodArgs) -> Result<Va
   let [_, card, new_value] = args.try_into().unwrap();
   let new_value = i64::max(0, expect_int_loosely("set_level", &new_value)?);
   let metadata = card.get_value("metadata", state.superglobal_state())?;
+  if !state.observers().is_inactive() {
+    let old_value = expect_int_loosely("set_level", &metadata.get_index(Value::from(CARD_META_LEVEL), state)?)?;
+    notify_card_stat_change(state, &card, Stat::Level, old_value, new_value)?;
+  }
   metadata.set_index(Value::from(CARD_META_LEVEL), Value::from(new_value))?;
   Ok(Value::Null)
 }
@@ -141,6 +169,7 @@ fn add_card_level(state: &mut EvaluatorState, mut args: MethodArgs) -> Result<Va
   let old_value = expect_int_loosely("add_level", &metadata.get_index(Value::from(CARD_META_LEVEL), state)?)?;
   let new_value = i64::max(0, old_value + delta_value);
   metadata.set_index(Value::from(CARD_META_LEVEL), Value::from(new_value))?;
+  notify_card_stat_change(state, &card, Stat::Level, old_value, new_value)?;
   Ok(Value::Null)
 }
 
@@ -153,6 +182,10 @@ fn set_card_morale(state: &mut EvaluatorState, mut args: MethodArgs) -> Result<V
   let [playing_field, card, new_value] = args.try_into().unwrap();
   let new_value = i64::max(0, expect_int_loosely("set_morale", &new_value)?);
   let metadata = card.get_value("metadata", state.superglobal_state())?;
+  if !state.observers().is_inactive() {
+    let old_value = expect_int_loosely("set_morale", &metadata.get_index(Value::from(CARD_META_MORALE), state)?)?;
+    notify_card_stat_change(state, &card, Stat::Morale, old_value, new_value)?;
+  }
   metadata.set_index(Value::from(CARD_META_MORALE), Value::from(new_value))?;
   do_morale_check(state, playing_field, card)?;
   Ok(Value::Null)
@@ -167,13 +200,41 @@ fn add_card_morale(state: &mut EvaluatorState, mut args: MethodArgs) -> Result<V
   let [playing_field, card, delta_value] = args.try_into().unwrap();
   let delta_value = i64::max(0, expect_int_loosely("add_morale", &delta_value)?);
   let metadata = card.get_value("metadata", state.superglobal_state())?;
-  let old_value = expect_int_loosely("add_morale", &metadata.get_index(Value::from(CARD_META_LEVEL), state)?)?;
+  let old_value = expect_int_loosely("add_morale", &metadata.get_index(Value::from(CARD_META_MORALE), state)?)?;
   let new_value = i64::max(0, old_value + delta_value);
   metadata.set_index(Value::from(CARD_META_MORALE), Value::from(new_value))?;
+  notify_card_stat_change(state, &card, Stat::Morale, old_value, new_value)?;
   do_morale_check(state, playing_field, card)?;
   Ok(Value::Null)
 }
 
+/// Notifies the game's observers of a change to a player or card
+/// stat, attributed to the card hook currently executing.
+fn notify_stat_change(
+  state: &EvaluatorState,
+  player: GameWinner,
+  stat: Stat,
+  card: Option<ObservedCard>,
+  old_value: i64,
+  new_value: i64,
+) -> Result<(), EvalError> {
+  let source = StatSource::current(state)?;
+  let change = StatChange { player, stat, card, old_value, new_value, source };
+  state.observers().notify(|observer| observer.on_stat_changed(&change));
+  Ok(())
+}
+
+/// Notifies the game's observers of a change to a card stat. The
+/// card's owner is reported as the player.
+fn notify_card_stat_change(state: &EvaluatorState, card: &Value, stat: Stat, old_value: i64, new_value: i64) -> Result<(), EvalError> {
+  if state.observers().is_inactive() {
+    return Ok(());
+  }
+  let player = card_owner(state, card)?;
+  let observed_card = ObservedCard::from_card(state, card)?;
+  notify_stat_change(state, player, stat, Some(observed_card), old_value, new_value)
+}
+
 fn do_morale_check(state: &EvaluatorState, playing_field: Value, card: Value) -> Result<(), EvalError> {
   let metadata = card.get_value("metadata", state.superglobal_state())?;
   let curr_morale = expect_int_loosely("(morale setter)", &metadata.get_index(Value::from(CARD_META_MORALE), state)?)?;
@@ -209,3 +270,123 @@ fn other_player(player: Value) -> Result<Value, EvalError> {
     _ => Err(EvalError::domain_error("Bad card player")),
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::stats_panel::{DEFAULT_FORT_DEFENSE, STATS_PANEL_FIELDS, game_stats_panel_class};
+  use crate::interpreter::eval::SuperglobalState;
+  use crate::cardgame::CardId;
+  use crate::cardgame::observer::{GameObserver, ObserverSet};
+
+  use ordermap::OrderMap;
+  use rand::SeedableRng;
+  use rand_chacha::ChaCha8Rng;
+
+  use std::cell::RefCell;
+  use std::rc::Rc;
+
+  #[derive(Default)]
+  struct StatObserver {
+    changes: Rc<RefCell<Vec<StatChange>>>,
+  }
+
+  impl GameObserver for StatObserver {
+    fn on_stat_changed(&mut self, change: &StatChange) {
+      self.changes.borrow_mut().push(change.clone());
+    }
+  }
+
+  fn mock_card(state: &EvaluatorState, level: i64, morale: i64) -> Value {
+    let mut methods = HashMap::new();
+    methods.insert(Identifier::new("get_id"), Method::rust_method("get_id", |_, _| Ok(Value::from(7))));
+    methods.insert(Identifier::new("get_title"), Method::rust_method("get_title", |_, _| Ok(Value::from("Mock Minion"))));
+    let object = state.bootstrapped_classes().object();
+    let card_type_class = ClassBuilder::default().parent(Arc::clone(object)).methods(methods).build();
+    let card_class = ClassBuilder::default().parent(Arc::clone(object)).build();
+
+    let metadata = Value::new_dict(OrderMap::new());
+    metadata.set_index(Value::from(CARD_META_LEVEL), Value::from(level)).unwrap();
+    metadata.set_index(Value::from(CARD_META_MORALE), Value::from(morale)).unwrap();
+    let card = Value::new_object(Arc::new(card_class));
+    card.set_value_raw("card_type", Value::new_object(Arc::new(card_type_class))).unwrap();
+    card.set_value_raw("metadata", metadata).unwrap();
+    card.set_value_raw("owner", Value::from("TOP")).unwrap();
+    card
+  }
+
+  fn mock_playing_field(state: &EvaluatorState) -> Value {
+    let object = state.bootstrapped_classes().object();
+    let stats = Value::new_object(Arc::new(game_stats_panel_class(Arc::clone(object))));
+    for field in STATS_PANEL_FIELDS {
+      stats.set_value_raw(field, Value::from(0)).unwrap();
+    }
+    stats.set_value_raw("__evilconsim_fort_defense", Value::from(DEFAULT_FORT_DEFENSE)).unwrap();
+    stats.set_value_raw("__evilconsim_max_fort_defense", Value::from(DEFAULT_FORT_DEFENSE)).unwrap();
+
+    let mut methods = HashMap::new();
+    methods.insert(Identifier::new("get_stats"), Method::rust_method("get_stats", |state, _| {
+      state.self_instance().get_value("stats", state.superglobal_state())
+    }));
+    let playing_field = Value::new_object(Arc::new(ClassBuilder::default().parent(Arc::clone(object)).methods(methods).build()));
+    playing_field.set_value_raw("stats", stats).unwrap();
+    playing_field
+  }
+
+  #[test]
+  fn test_add_fort_defense_reports_clamped_value() {
+    let observer = StatObserver::default();
+    let changes = Rc::clone(&observer.changes);
+    let mut state = EvaluatorState::new(Arc::new(SuperglobalState::new()), ChaCha8Rng::seed_from_u64(0))
+      .with_observers(ObserverSet::new(vec![Box::new(observer)]));
+    let playing_field = mock_playing_field(&state);
+    let args = MethodArgs(vec![playing_field, Value::from("BOTTOM"), Value::from(5)]);
+    let res = basic_add_stat("add_fort_defense", Stat::FortDefense, &mut state, args).unwrap();
+
+    assert_eq!(res.new_value, DEFAULT_FORT_DEFENSE);
+    assert_eq!(*changes.borrow(), vec![StatChange {
+      player: GameWinner::Bottom,
+      stat: Stat::FortDefense,
+      card: None,
+      old_value: DEFAULT_FORT_DEFENSE,
+      new_value: DEFAULT_FORT_DEFENSE,
+      source: None,
+    }]);
+  }
+
+  #[test]
+  fn test_set_evil_points_reports_clamped_value() {
+    let observer = StatObserver::default();
+    let changes = Rc::clone(&observer.changes);
+    let mut state = EvaluatorState::new(Arc::new(SuperglobalState::new()), ChaCha8Rng::seed_from_u64(0))
+      .with_observers(ObserverSet::new(vec![Box::new(observer)]));
+    let playing_field = mock_playing_field(&state);
+    let args = MethodArgs(vec![playing_field, Value::from("TOP"), Value::from(-2)]);
+    basic_set_stat("set_evil_points", Stat::EvilPoints, &mut state, args).unwrap();
+
+    assert_eq!(changes.borrow().len(), 1);
+    assert_eq!(changes.borrow()[0].old_value, 0);
+    assert_eq!(changes.borrow()[0].new_value, 0);
+  }
+
+  #[test]
+  fn test_add_card_morale_reports_old_morale() {
+    let observer = StatObserver::default();
+    let changes = Rc::clone(&observer.changes);
+    let mut state = EvaluatorState::new(Arc::new(SuperglobalState::new()), ChaCha8Rng::seed_from_u64(0))
+      .with_observers(ObserverSet::new(vec![Box::new(observer)]));
+    let card = mock_card(&state, 5, 2);
+    add_card_morale(&mut state, MethodArgs(vec![Value::Null, card.clone(), Value::from(1)])).unwrap();
+
+    let metadata = card.get_value("metadata", state.superglobal_state()).unwrap();
+    assert_eq!(metadata.get_index(Value::from(CARD_META_MORALE), &state).unwrap(), Value::from(3));
+    assert_eq!(*changes.borrow(), vec![StatChange {
+      player: GameWinner::Top,
+      stat: Stat::Morale,
+      card: Some(ObservedCard { id: CardId(7), name: String::from("Mock Minion") }),
+      old_value: 2,
+      new_value: 3,
+      source: None,
+    }]);
+  }
+}
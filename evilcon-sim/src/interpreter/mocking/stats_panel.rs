@@ -13,17 +13,30 @@ use crate::util::clamp;
 use std::sync::Arc;
 use std::collections::HashMap;
 
-pub(super) const DESTINY_SONG_LIMIT: i64 = 3;
+pub const DESTINY_SONG_LIMIT: i64 = 3;
 pub const DEFAULT_FORT_DEFENSE: i64 = 60;
 
+/// Instance variable holding the Destiny's Song value at which the
+/// owning player wins. This can be overridden on a per-game basis.
+pub const DESTINY_SONG_LIMIT_VARIABLE: &str = "__evilconsim_destiny_song_limit";
+
 pub(super) const STATS_PANEL_RES_PATH: &str = "res://card_game/playing_field/game_stats_panel/game_stats_panel.gd";
 
+/// The instance variables backing the stats panel's state, including
+/// the per-game rules stored on it.
+pub(super) const STATS_PANEL_FIELDS: [&str; 5] = [
+  "__evilconsim_evil_points",
+  "__evilconsim_fort_defense",
+  "__evilconsim_max_fort_defense",
+  "__evilconsim_destiny_song",
+  DESTINY_SONG_LIMIT_VARIABLE,
+];
+
 pub(super) fn game_stats_panel_class(node: Arc<Class>) -> Class {
-  let mut vars = Vec::new();
-  vars.push(InstanceVar::new("__evilconsim_evil_points", Some(Expr::from(0))));
-  vars.push(InstanceVar::new("__evilconsim_fort_defense", Some(Expr::from(DEFAULT_FORT_DEFENSE))));
-  vars.push(InstanceVar::new("__evilconsim_max_fort_defense", Some(Expr::from(DEFAULT_FORT_DEFENSE))));
-  vars.push(InstanceVar::new("__evilconsim_destiny_song", Some(Expr::from(0))));
+  let initial_values = [0, DEFAULT_FORT_DEFENSE, DEFAULT_FORT_DEFENSE, 0, DESTINY_SONG_LIMIT];
+  let vars = STATS_PANEL_FIELDS.iter().zip(initial_values)
+    .map(|(name, value)| InstanceVar::new(*name, Some(Expr::from(value))))
+    .collect();
 
   let mut proxies = HashMap::new();
   proxies.insert(Identifier::new("evil_points"), ProxyVar::new(
@@ -42,7 +55,10 @@ pub(super) fn game_stats_panel_class(node: Arc<Class>) -> Class {
     },
   ));
   proxies.insert(Identifier::new("destiny_song"), ProxyVar::new(
-    BackedField::new("__evilconsim_destiny_song").clamped(0, DESTINY_SONG_LIMIT),
+    DestinySongProxyField {
+      field_name: "__evilconsim_destiny_song",
+      limit_field_name: DESTINY_SONG_LIMIT_VARIABLE,
+    },
   ));
 
   let mut methods = HashMap::new();
@@ -68,30 +84,49 @@ struct MaxFortDefenseProxyField {
   max_field_name: &'static str,
 }
 
+#[derive(Debug, Clone)]
+struct DestinySongProxyField {
+  field_name: &'static str,
+  limit_field_name: &'static str,
+}
+
 impl ProxyField for FortDefenseProxyField {
   fn get_field(&self, superglobals: &Arc<SuperglobalState>, object: &Value) -> Result<Value, EvalError> {
-    object.get_value_raw(&self.curr_field_name, superglobals)
+    object.get_value_raw(self.curr_field_name, superglobals)
   }
 
   fn set_field(&self, superglobals: &Arc<SuperglobalState>, object: &Value, value: Value) -> Result<(), EvalError> {
     let lower_bound = 0;
-    let upper_bound = expect_int("(field setter)", &object.get_value(&self.max_field_name, superglobals)?)?;
+    let upper_bound = expect_int("(field setter)", &object.get_value(self.max_field_name, superglobals)?)?;
     let new_value = clamp(expect_int("(field setter)", &value)?, lower_bound, upper_bound);
-    object.set_value_raw(&self.curr_field_name, Value::from(new_value))?;
+    object.set_value_raw(self.curr_field_name, Value::from(new_value))?;
     Ok(())
   }
 }
 
 impl ProxyField for MaxFortDefenseProxyField {
   fn get_field(&self, superglobals: &Arc<SuperglobalState>, object: &Value) -> Result<Value, EvalError> {
-    object.get_value_raw(&self.max_field_name, superglobals)
+    object.get_value_raw(self.max_field_name, superglobals)
   }
 
   fn set_field(&self, superglobals: &Arc<SuperglobalState>, object: &Value, value: Value) -> Result<(), EvalError> {
-    object.set_value_raw(&self.max_field_name, Value::from(i64::max(expect_int("(field setter)", &value)?, 0)))?;
+    object.set_value_raw(self.max_field_name, Value::from(i64::max(expect_int("(field setter)", &value)?, 0)))?;
     // Invoke setter for fort_defense variable as well.
-    let old_fort_defense = object.get_value(&self.curr_proxy_name, superglobals)?;
-    object.set_value(&self.curr_proxy_name, old_fort_defense, superglobals)?;
+    let old_fort_defense = object.get_value(self.curr_proxy_name, superglobals)?;
+    object.set_value(self.curr_proxy_name, old_fort_defense, superglobals)?;
+    Ok(())
+  }
+}
+
+impl ProxyField for DestinySongProxyField {
+  fn get_field(&self, superglobals: &Arc<SuperglobalState>, object: &Value) -> Result<Value, EvalError> {
+    object.get_value_raw(self.field_name, superglobals)
+  }
+
+  fn set_field(&self, superglobals: &Arc<SuperglobalState>, object: &Value, value: Value) -> Result<(), EvalError> {
+    let upper_bound = expect_int("(field setter)", &object.get_value_raw(self.limit_field_name, superglobals)?)?;
+    let new_value = clamp(expect_int("(field setter)", &value)?, 0, upper_bound);
+    object.set_value_raw(self.field_name, Value::from(new_value))?;
     Ok(())
   }
 }
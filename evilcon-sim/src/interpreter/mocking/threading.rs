@@ -0,0 +1,53 @@
+use crate::interpreter::class::{Class, ClassBuilder};
+use crate::interpreter::method::{Method, MethodArgs};
+use crate::interpreter::value::Value;
+use crate::interpreter::error::EvalError;
+use crate::interpreter::eval::EvaluatorState;
+use crate::interpreter::operator::expect_int;
+use crate::ast::identifier::Identifier;
+
+use std::sync::Arc;
+use std::collections::HashMap;
+
+/// Stand-in for Godot's `WorkerThreadPool`. Tasks run synchronously,
+/// in order, on the calling thread, and are finished by the time
+/// `add_group_task` returns. Interpreted values can't cross threads,
+/// and a game being simulated already has its own thread.
+pub(super) fn worker_thread_pool_class(object: Arc<Class>) -> Class {
+  let mut methods = HashMap::new();
+  methods.insert(Identifier::new("add_group_task"), Method::rust_static_method("add_group_task", add_group_task));
+  methods.insert(Identifier::new("wait_for_group_task_completion"), Method::rust_static_method("wait_for_group_task_completion", |_, _| Ok(Value::Null)));
+  methods.insert(Identifier::new("is_group_task_completed"), Method::rust_static_method("is_group_task_completed", |_, _| Ok(Value::Bool(true))));
+
+  ClassBuilder::default()
+    .name("WorkerThreadPool")
+    .parent(object)
+    .methods(methods)
+    .build()
+}
+
+/// Stand-in for Godot's `Mutex`. Since tasks never run concurrently
+/// (see [`worker_thread_pool_class`]), locking is a no-op.
+pub(super) fn mutex_class(refcounted: Arc<Class>) -> Class {
+  let mut methods = HashMap::new();
+  methods.insert(Identifier::new("lock"), Method::noop());
+  methods.insert(Identifier::new("unlock"), Method::noop());
+  methods.insert(Identifier::new("try_lock"), Method::rust_method("try_lock", |_, _| Ok(Value::Bool(true))));
+
+  ClassBuilder::default()
+    .name("Mutex")
+    .parent(refcounted)
+    .methods(methods)
+    .build()
+}
+
+// add_group_task(action, elements, tasks_needed = -1, high_priority = false, description = "")
+fn add_group_task(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, EvalError> {
+  args.expect_arity_within(2, 5, "add_group_task")?;
+  let elements = expect_int("add_group_task", &args[1])?;
+  for index in 0..elements {
+    state.call_function_on(&args[0], "call", vec![Value::from(index)])?;
+  }
+  // Task ID
+  Ok(Value::from(0))
+}
@@ -4,8 +4,10 @@ use crate::interpreter::method::{Method, MethodArgs};
 use crate::interpreter::error::EvalError;
 use crate::interpreter::eval::EvaluatorState;
 use crate::interpreter::value::Value;
-use crate::interpreter::operator::expect_int;
+use crate::interpreter::operator::{expect_int, expect_string};
 use crate::ast::identifier::Identifier;
+use crate::cardgame::PlayerOutcome;
+use crate::cardgame::observer::{Phase, player_from_value};
 use super::playing_field::ENDGAME_VARIABLE;
 
 use std::sync::Arc;
@@ -13,6 +15,7 @@ use std::collections::HashMap;
 
 pub const TURN_TRANSITIONS_RES_PATH: &str = "res://card_game/playing_field/util/card_game_turn_transitions.gd";
 pub const PLAY_FULL_GAME_METHOD: &str = "play_full_game";
+pub const PLAY_REST_OF_GAME_METHOD: &str = "play_rest_of_game";
 
 const STATS_CALCULATOR: &str = "StatsCalculator";
 const CARD_GAME_API: &str = "CardGameApi";
@@ -23,6 +26,17 @@ const CARD_PLAYER_TOP: &str = "TOP";
 pub(super) fn turn_transitions_class(node: Arc<Class>) -> Class {
   let mut methods = HashMap::new();
   methods.insert(Identifier::new(PLAY_FULL_GAME_METHOD), Method::rust_static_method(PLAY_FULL_GAME_METHOD, play_full_game));
+  methods.insert(Identifier::new(PLAY_REST_OF_GAME_METHOD), Method::rust_static_method(PLAY_REST_OF_GAME_METHOD, play_rest_of_game));
+  methods.insert(Identifier::new("begin_turn"), Method::rust_static_method("begin_turn", |state, args| {
+    let (playing_field, player) = args.expect_two_args("begin_turn")?;
+    begin_turn(state, &playing_field, expect_string("begin_turn", &player)?)?;
+    Ok(Value::Null)
+  }));
+  methods.insert(Identifier::new("end_turn"), Method::rust_static_method("end_turn", |state, args| {
+    let (playing_field, player) = args.expect_two_args("end_turn")?;
+    end_turn(state, &playing_field, expect_string("end_turn", &player)?)?;
+    Ok(Value::Null)
+  }));
 
   ClassBuilder::default()
     .name("CardGameTurnTransitions")
@@ -35,7 +49,9 @@ pub(super) fn turn_transitions_class(node: Arc<Class>) -> Class {
 // NOTE: Intentional divergence from GDScript. The GDScript method
 // takes one argument: playing_field. This method takes a second
 // argument, indicating the max turn count, so we can prevent infinite
-// loops.
+// loops. If the turn limit is reached, this method returns normally
+// with the endgame variable still unset, and the caller should treat
+// the game as a draw.
 fn play_full_game(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, EvalError> {
   args.expect_arity_within(1, 2, "play_full_game")?;
   let playing_field;
@@ -63,12 +79,20 @@ fn play_full_game(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value,
   let mut turn_iter = 0;
   while !check_for_endgame(state, &playing_field)? {
     state.call_function_on(&card_game_phases, "start_of_full_turn", vec![playing_field.clone()])?;
+    let turn_number = if state.observers().is_inactive() {
+      0
+    } else {
+      expect_int("play_full_game", &playing_field.get_value("turn_number", state.superglobal_state())?)?
+    };
+    state.observers().notify(|observer| observer.on_turn_start(turn_number));
     run_turn_for(state, &playing_field, CARD_PLAYER_BOTTOM)?;
     run_turn_for(state, &playing_field, CARD_PLAYER_TOP)?;
     state.call_function_on(&card_game_phases, "end_of_full_turn", vec![playing_field.clone()])?;
+    state.observers().notify(|observer| observer.on_turn_end(turn_number));
     turn_iter += 1;
     if let Some(max_turns) = max_turns && turn_iter >= max_turns {
-      return Err(EvalError::LoopLimitExceeded { limit: max_turns });
+      tracing::debug!("Turn limit {max_turns} reached, game is a draw");
+      break;
     }
   }
   // In Godot, this method never returns (it awaits a signal that will
@@ -77,6 +101,43 @@ fn play_full_game(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value,
   Ok(Value::Null)
 }
 
+// Used by the Monte Carlo agent to play out hypothetical games on a
+// virtual playing field. Like play_full_game, the endgame variable is
+// only checked between full turns. Returns the winner, or null if the
+// game was cut off by the "max_turns" option.
+fn play_rest_of_game(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, EvalError> {
+  args.expect_arity_within(1, 2, PLAY_REST_OF_GAME_METHOD)?;
+  let playing_field = args[0].clone();
+  let max_turns = match args.0.get(1) {
+    Some(opts) => expect_int(PLAY_REST_OF_GAME_METHOD, &state.call_function_on(opts, "get", vec![Value::from("max_turns"), Value::from(-1)])?)?,
+    None => -1,
+  };
+  let card_game_phases = get_global(state, CARD_GAME_PHASES)?;
+
+  // Finish the current turn
+  let turn_player = playing_field.get_value("turn_player", state.superglobal_state())?;
+  let turn_player = expect_string(PLAY_REST_OF_GAME_METHOD, &turn_player)?.to_owned();
+  let player_agent = state.call_function_on(&playing_field, "player_agent", vec![Value::from(turn_player.as_str())])?;
+  state.call_function_on(&player_agent, "run_one_turn", vec![playing_field.clone()])?;
+  end_turn(state, &playing_field, &turn_player)?;
+  if turn_player == CARD_PLAYER_BOTTOM {
+    run_turn_for(state, &playing_field, CARD_PLAYER_TOP)?;
+  }
+  state.call_function_on(&card_game_phases, "end_of_full_turn", vec![playing_field.clone()])?;
+
+  while !check_for_endgame(state, &playing_field)? {
+    let turn_number = expect_int(PLAY_REST_OF_GAME_METHOD, &playing_field.get_value("turn_number", state.superglobal_state())?)?;
+    if max_turns >= 0 && turn_number >= max_turns {
+      return Ok(Value::Null);
+    }
+    state.call_function_on(&card_game_phases, "start_of_full_turn", vec![playing_field.clone()])?;
+    run_turn_for(state, &playing_field, CARD_PLAYER_BOTTOM)?;
+    run_turn_for(state, &playing_field, CARD_PLAYER_TOP)?;
+    state.call_function_on(&card_game_phases, "end_of_full_turn", vec![playing_field.clone()])?;
+  }
+  playing_field.get_value(ENDGAME_VARIABLE, state.superglobal_state())
+}
+
 fn draw_initial_hand(state: &EvaluatorState, playing_field: &Value, player: &str) -> Result<(), EvalError> {
   let stats_calculator = get_global(state, STATS_CALCULATOR)?;
   let card_game_api = get_global(state, CARD_GAME_API)?;
@@ -91,21 +152,57 @@ fn draw_initial_hand(state: &EvaluatorState, playing_field: &Value, player: &str
 }
 
 fn run_turn_for(state: &EvaluatorState, playing_field: &Value, player: &str) -> Result<(), EvalError> {
+  begin_turn(state, playing_field, player)?;
+
+  // Player agent turn
+  notify_phase_start(state, Phase::Play, player)?;
+  let player_agent = state.call_function_on(playing_field, "player_agent", vec![Value::from(player)])?;
+  state.call_function_on(&player_agent, "run_one_turn", vec![playing_field.clone()])?;
+  notify_play_phase_end(state, playing_field, player)?;
+
+  end_turn(state, playing_field, player)
+}
+
+fn begin_turn(state: &EvaluatorState, playing_field: &Value, player: &str) -> Result<(), EvalError> {
   let card_game_phases = get_global(state, CARD_GAME_PHASES)?;
   playing_field.set_value("turn_player", Value::from(player), state.superglobal_state())?;
+  notify_phase_start(state, Phase::Draw, player)?;
   state.call_function_on(&card_game_phases, "draw_phase", vec![playing_field.clone(), Value::from(player)])?;
+  notify_phase_start(state, Phase::Attack, player)?;
   state.call_function_on(&card_game_phases, "attack_phase", vec![playing_field.clone(), Value::from(player)])?;
+  notify_phase_start(state, Phase::Morale, player)?;
   state.call_function_on(&card_game_phases, "morale_phase", vec![playing_field.clone(), Value::from(player)])?;
+  notify_phase_start(state, Phase::Standby, player)?;
   state.call_function_on(&card_game_phases, "standby_phase", vec![playing_field.clone(), Value::from(player)])?;
+  Ok(())
+}
 
-  // Player agent turn
-  let player_agent = state.call_function_on(playing_field, "player_agent", vec![Value::from(player)])?;
-  state.call_function_on(&player_agent, "run_one_turn", vec![playing_field.clone()])?;
-
+fn end_turn(state: &EvaluatorState, playing_field: &Value, player: &str) -> Result<(), EvalError> {
+  let card_game_phases = get_global(state, CARD_GAME_PHASES)?;
+  notify_phase_start(state, Phase::End, player)?;
   state.call_function_on(&card_game_phases, "end_phase", vec![playing_field.clone(), Value::from(player)])?;
   Ok(())
 }
 
+fn notify_phase_start(state: &EvaluatorState, phase: Phase, player: &str) -> Result<(), EvalError> {
+  if state.observers().is_inactive() {
+    return Ok(());
+  }
+  let player = player_from_value(&Value::from(player))?;
+  state.observers().notify(|observer| observer.on_phase_start(phase, player));
+  Ok(())
+}
+
+fn notify_play_phase_end(state: &EvaluatorState, playing_field: &Value, player: &str) -> Result<(), EvalError> {
+  if state.observers().is_inactive() {
+    return Ok(());
+  }
+  let player = player_from_value(&Value::from(player))?;
+  let player_state = PlayerOutcome::read_from(state, playing_field, player)?;
+  state.observers().notify(|observer| observer.on_play_phase_end(player, &player_state));
+  Ok(())
+}
+
 fn check_for_endgame(state: &EvaluatorState, playing_field: &Value) -> Result<bool, EvalError> {
   let endgame_value = playing_field.get_value(ENDGAME_VARIABLE, state.superglobal_state())?;
   Ok(matches!(endgame_value, Value::String(_)))
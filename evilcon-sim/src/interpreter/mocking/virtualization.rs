@@ -0,0 +1,85 @@
+use crate::interpreter::class::{Class, ClassBuilder};
+use crate::interpreter::method::{Method, MethodArgs};
+use crate::interpreter::value::Value;
+use crate::interpreter::error::EvalError;
+use crate::interpreter::eval::EvaluatorState;
+use crate::interpreter::operator::expect_array;
+use crate::ast::identifier::Identifier;
+use super::PLAYING_FIELD_RES_PATH;
+use super::stats_panel::STATS_PANEL_FIELDS;
+
+use std::sync::Arc;
+use std::collections::HashMap;
+
+pub(super) const VIRTUALIZATION_RES_PATH: &str = "res://card_game/playing_field/virtual_playing_field/virtualization.gd";
+
+const NULL_AI_AGENT_RES_PATH: &str = "res://card_game/playing_field/player_agent/null_ai_agent.gd";
+
+/// Getters on the playing field for each of a player's card strips.
+const CARD_STRIP_GETTERS: [&str; 5] = ["get_deck", "get_discard_pile", "get_hand", "get_minion_strip", "get_effect_strip"];
+
+// Our mocked PlayingField never plays animations, so it serves as the
+// VirtualPlayingField as well. to_virtual copies the game state onto
+// a fresh instance of it.
+pub(super) fn virtualization_class(node: Arc<Class>) -> Class {
+  let mut methods = HashMap::new();
+  methods.insert(Identifier::new("to_virtual"), Method::rust_static_method("to_virtual", to_virtual));
+
+  ClassBuilder::default()
+    .name("Virtualization")
+    .parent(node)
+    .methods(methods)
+    .build()
+}
+
+fn to_virtual(state: &mut EvaluatorState, args: MethodArgs) -> Result<Value, EvalError> {
+  let playing_field = args.expect_one_arg("to_virtual")?;
+  let superglobals = Arc::clone(state.superglobal_state());
+  let new_field = state.call_function_on_class(&get_file(state, PLAYING_FIELD_RES_PATH)?, "new", Vec::new())?;
+  for var_name in ["turn_number", "turn_player"] {
+    new_field.set_value(var_name, playing_field.get_value(var_name, &superglobals)?, &superglobals)?;
+  }
+  let event_logger = playing_field.get_value("event_logger", &superglobals)?;
+  new_field.set_value("event_logger", state.call_function_on(&event_logger, "deepclone", Vec::new())?, &superglobals)?;
+
+  // As in GDScript, the player agents are not copied.
+  let null_ai_agent = get_file(state, NULL_AI_AGENT_RES_PATH)?;
+  for player in ["BOTTOM", "TOP"] {
+    let agent = state.call_function_on_class(&null_ai_agent, "new", Vec::new())?;
+    state.call_function_on(&new_field, "replace_player_agent", vec![Value::from(player), agent])?;
+  }
+
+  for player in ["BOTTOM", "TOP"] {
+    for getter in CARD_STRIP_GETTERS {
+      let source = state.call_function_on(&playing_field, getter, vec![Value::from(player)])?;
+      let destination = state.call_function_on(&new_field, getter, vec![Value::from(player)])?;
+      copy_cards(state, &source, &destination)?;
+    }
+    // Copy the stats panel's backing fields directly, so that the
+    // per-game rules stored on it come along, and so that clamping
+    // doesn't depend on the order of assignment.
+    let source = state.call_function_on(&playing_field, "get_stats", vec![Value::from(player)])?;
+    let destination = state.call_function_on(&new_field, "get_stats", vec![Value::from(player)])?;
+    for field in STATS_PANEL_FIELDS {
+      destination.set_value_raw(field, source.get_value_raw(field, &superglobals)?)?;
+    }
+  }
+  Ok(new_field)
+}
+
+fn copy_cards(state: &EvaluatorState, source: &Value, destination: &Value) -> Result<(), EvalError> {
+  let source_cards = state.call_function_on(source, "cards", Vec::new())?;
+  let source_cards = state.call_function_on(&source_cards, "card_array", Vec::new())?;
+  let source_cards = expect_array("to_virtual", &source_cards)?.borrow().clone();
+  let cards = source_cards.iter()
+    .map(|card| state.call_function_on(card, "deepclone", Vec::new()))
+    .collect::<Result<Vec<_>, _>>()?;
+  let destination_cards = state.call_function_on(destination, "cards", Vec::new())?;
+  state.call_function_on(&destination_cards, "replace_cards", vec![Value::new_array(cards)])?;
+  Ok(())
+}
+
+fn get_file(state: &EvaluatorState, path: &str) -> Result<Arc<Class>, EvalError> {
+  state.superglobal_state().get_file(path)
+    .ok_or_else(|| EvalError::UndefinedClass(path.to_owned()))
+}
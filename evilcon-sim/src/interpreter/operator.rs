@@ -109,7 +109,7 @@ fn do_type_check(bootstrapping: &BootstrappedTypes, lhs: Value, rhs: Value) -> R
 fn do_elem_check(lhs: Value, rhs: Value) -> Result<bool, EvalError> {
   if let Value::String(rhs) = &rhs {
     let lhs = expect_string("in", &lhs)?;
-    Ok(rhs.contains(&lhs))
+    Ok(rhs.contains(lhs))
   } else {
     Ok(rhs.try_iter()?.any(|elem| elem == lhs))
   }
@@ -162,7 +162,7 @@ pub fn expect_dict<'v>(function_name: &str, value: &'v Value) -> Result<&'v RefC
 
 pub fn expect_string<'v>(function_name: &str, value: &'v Value) -> Result<&'v str, EvalError> {
   match value {
-    Value::String(s) => Ok(&s),
+    Value::String(s) => Ok(s),
     value => Err(EvalError::type_error(function_name, "string", value.to_owned())),
   }
 }
@@ -181,12 +181,12 @@ where F1: FnOnce(i64, i64) -> Result<i64, EvalError>,
 
 pub fn do_comparison_op(lhs: &Value, rhs: &Value) -> Result<Ordering, EvalError> {
   match (lhs, rhs) {
-    (Value::Int(lhs), Value::Int(rhs)) => Ok(lhs.cmp(&rhs)),
-    (Value::Float(lhs), Value::Float(rhs)) => Ok(lhs.cmp(&rhs)),
-    (Value::Int(lhs), Value::Float(rhs)) => Ok(OrderedFloat(*lhs as f64).cmp(&rhs)),
+    (Value::Int(lhs), Value::Int(rhs)) => Ok(lhs.cmp(rhs)),
+    (Value::Float(lhs), Value::Float(rhs)) => Ok(lhs.cmp(rhs)),
+    (Value::Int(lhs), Value::Float(rhs)) => Ok(OrderedFloat(*lhs as f64).cmp(rhs)),
     (Value::Float(lhs), Value::Int(rhs)) => Ok(lhs.cmp(&OrderedFloat(*rhs as f64))),
-    (Value::String(lhs), Value::String(rhs)) => Ok(lhs.cmp(&rhs)),
-    (Value::Bool(lhs), Value::Bool(rhs)) => Ok(lhs.cmp(&rhs)),
+    (Value::String(lhs), Value::String(rhs)) => Ok(lhs.cmp(rhs)),
+    (Value::Bool(lhs), Value::Bool(rhs)) => Ok(lhs.cmp(rhs)),
     (Value::Null, Value::Null) => Ok(Ordering::Equal),
     (Value::ArrayRef(_lhs), Value::ArrayRef(_rhs)) => {
       // I hope I don't need this one :(
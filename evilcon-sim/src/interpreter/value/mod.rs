@@ -126,10 +126,14 @@ impl Value {
     Value::Float(f.into())
   }
 
+  // Values are confined to the single thread running their game, so
+  // the `Arc` never actually crosses threads.
+  #[allow(clippy::arc_with_non_send_sync)]
   pub fn new_array(values: Vec<Value>) -> Self {
     Value::ArrayRef(Arc::new(RefCell::new(values)))
   }
 
+  #[allow(clippy::arc_with_non_send_sync)]
   pub fn new_dict(values: OrderMap<HashKey, Value>) -> Self {
     Value::DictRef(Arc::new(RefCell::new(values)))
   }
@@ -192,7 +196,7 @@ impl Value {
         .with_enclosing_class(Some(cls.clone()));
       return constant.get(&const_context).map(|x| x.clone().into());
     } else if let Value::ObjectRef(obj) = self {
-      let obj = RefCell::borrow(&obj);
+      let obj = RefCell::borrow(obj);
       if let Some(simple_name) = obj.dict.get(name).cloned() {
         return Ok(simple_name);
       }
@@ -275,7 +279,7 @@ impl Value {
   pub fn get_class(&self, bootstrapping: &BootstrappedTypes) -> Option<Arc<Class>> {
     match self {
       Value::Int(_) => Some(Arc::clone(bootstrapping.int())),
-      Value::ObjectRef(obj) => Some(RefCell::borrow(&obj).class.clone()),
+      Value::ObjectRef(obj) => Some(RefCell::borrow(obj).class.clone()),
       Value::String(_) => Some(Arc::clone(bootstrapping.string())),
       Value::ArrayRef(_) => Some(Arc::clone(bootstrapping.array())),
       Value::DictRef(_) => Some(Arc::clone(bootstrapping.dictionary())),
@@ -294,11 +298,11 @@ impl Value {
         Ok(ValueIter { inner: Box::new(elems) })
       }
       Value::ArrayRef(arr) => {
-        let elems = RefCell::borrow(&arr).clone();
+        let elems = RefCell::borrow(arr).clone();
         Ok(ValueIter { inner: Box::new(elems.into_iter()) })
       }
       Value::DictRef(d) => {
-        let entries = RefCell::borrow(&d).clone();
+        let entries = RefCell::borrow(d).clone();
         Ok(ValueIter { inner: Box::new(entries.into_keys().map(Value::from)) })
       }
       _ => {
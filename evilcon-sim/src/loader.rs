@@ -24,7 +24,7 @@ use std::fmt::{self, Formatter, Debug};
 use std::collections::HashMap;
 use std::io;
 
-pub const GODOT_PROJECT_ROOT: LazyLock<PathBuf> = LazyLock::new(|| {
+pub static GODOT_PROJECT_ROOT: LazyLock<PathBuf> = LazyLock::new(|| {
   let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
   manifest_dir.ancestors()
     .find(|ancestor| ancestor.join("project.godot").exists())
@@ -80,6 +80,12 @@ enum ExtendedClass<'a> {
   ExistingFile,
 }
 
+impl Default for GdScriptLoader {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl GdScriptLoader {
   pub fn new() -> GdScriptLoader {
     GdScriptLoader {
@@ -92,9 +98,9 @@ impl GdScriptLoader {
     let path = path.as_ref();
     tracing::debug!("Loading file {}...", path.display());
 
-    let file_contents = read_to_string(&path)?;
+    let file_contents = read_to_string(path)?;
     let file = read_from_string(&file_contents)?;
-    let path = normalize_path(&path)?;
+    let path = normalize_path(path)?;
 
     if let Some(class_name) = &file.class_name {
       self.class_names.insert(class_name.clone(), path.clone());
@@ -113,9 +119,9 @@ impl GdScriptLoader {
     let path = path.as_ref();
     tracing::debug!("Loading file {}...", path.display());
 
-    let file_contents = read_to_string(&path)?;
+    let file_contents = read_to_string(path)?;
     let file = read_from_string(&file_contents)?;
-    let path = normalize_path(&path)?;
+    let path = normalize_path(path)?;
 
     if let Some(class_name) = &file.class_name {
       self.class_names.insert(class_name.clone(), path.clone());
@@ -191,17 +197,17 @@ impl GdScriptLoader {
     Ok(graph)
   }
 
-  fn resolve_extends_clause(&self, superglobals: &SuperglobalState, clause: &ExtendsClause) -> Result<ExtendedClass, DependencyError> {
+  fn resolve_extends_clause(&self, superglobals: &SuperglobalState, clause: &ExtendsClause) -> Result<ExtendedClass<'_>, DependencyError> {
     resolve_extends_clause_in_superglobals(superglobals, clause)
       .or_else(|| self.resolve_extends_clause_in_known_files(clause))
       .ok_or_else(|| no_such_class(clause))
   }
 
-  fn resolve_extends_clause_in_known_files(&self, clause: &ExtendsClause) -> Option<ExtendedClass> {
+  fn resolve_extends_clause_in_known_files(&self, clause: &ExtendsClause) -> Option<ExtendedClass<'_>> {
     match clause {
       ExtendsClause::Id(class_name) => {
         self.class_names.get(class_name)
-          .map(|class_path| ExtendedClass::LoadedFile(class_path))
+          .map(ExtendedClass::LoadedFile)
       }
       ExtendsClause::Path(class_path) => {
         self.files.get_key_value(class_path.as_ref())
@@ -15,22 +15,30 @@ fn main() -> anyhow::Result<ExitCode> {
       let res = runner::validate_user_deck(&deck);
       Ok(res.to_exit_code())
     }
-    cli::Command::PlayFromCode { code } => {
-      runner::play_from_code(&code)?;
+    cli::Command::PlayFromCode { code, trace_out, agents, rules } => {
+      runner::play_from_code(&code, &agents.resolve()?, &rules.resolve()?, trace_out.as_deref())?;
       Ok(ExitCode::SUCCESS)
     }
-    cli::Command::PlaySequential { seed, count, bottom_deck, top_deck } => {
+    cli::Command::PlaySequential { bottom_deck, top_deck, trace_out, batch, agents, rules } => {
       let env = CardGameEnv { bottom_deck, top_deck };
-      runner::play_sequential(&env, seed, count)?;
+      runner::play_sequential(&env, &agents.resolve()?, &rules.resolve()?, &batch, trace_out.as_deref())?;
       Ok(ExitCode::SUCCESS)
     }
-    cli::Command::PlayParallel { seed, count, thread_count, bottom_deck, top_deck } => {
+    cli::Command::PlayParallel { thread_count, bottom_deck, top_deck, batch, sprt, agents, rules } => {
       let env = CardGameEnv { bottom_deck, top_deck };
-      runner::play_parallel(env, seed, count, thread_count)?;
+      runner::play_parallel(env, agents.resolve()?, rules.resolve()?, &batch, thread_count, &sprt)?;
       Ok(ExitCode::SUCCESS)
     }
-    cli::Command::RunGeneticAlgorithm { thread_count, generations, additional_args } => {
-      runner::run_genetic_algorithm(thread_count, generations, additional_args)?;
+    cli::Command::Tournament { decks, games_per_pair, seed, thread_count, ladder, rules } => {
+      runner::run_tournament(&decks, games_per_pair, seed, thread_count, rules.resolve()?, ladder.as_deref())?;
+      Ok(ExitCode::SUCCESS)
+    }
+    cli::Command::Ladder { command: cli::LadderCommand::Show { ladder } } => {
+      runner::show_ladder(&ladder)?;
+      Ok(ExitCode::SUCCESS)
+    }
+    cli::Command::RunGeneticAlgorithm { thread_count, generations, checkpoint, resume, stats_out, additional_args, rules } => {
+      runner::run_genetic_algorithm(thread_count, generations, additional_args, rules.resolve()?, checkpoint, resume, stats_out)?;
       Ok(ExitCode::SUCCESS)
     }
   }
@@ -70,8 +70,7 @@ fn parse_function_decl(
   let body = parse_body(parser, named_child(node, "body")?)?;
   let is_static = {
     let mut cursor = node.walk();
-    let is_static = node.children(&mut cursor).any(|child| child.kind() == "static_keyword");
-    is_static
+    node.children(&mut cursor).any(|child| child.kind() == "static_keyword")
   };
   Ok(FunctionDecl {
     name,
@@ -11,7 +11,7 @@ use tree_sitter::Node;
 
 use std::sync::Arc;
 
-pub const ARGS_KIND: &'static str = "arguments";
+pub const ARGS_KIND: &str = "arguments";
 
 pub(super) fn parse_expr(
   parser: &GdscriptParser,
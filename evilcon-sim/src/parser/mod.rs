@@ -55,10 +55,7 @@ where I: Iterator<Item = Node<'tree>> {
   // Skip any leading comments
   skip_while(nodes, |node| node.kind() == COMMENT_KIND);
 
-  loop {
-    let Some(next_kind) = nodes.peek().map(|node| node.kind()) else {
-      break;
-    };
+  while let Some(next_kind) = nodes.peek().map(|node| node.kind()) {
     if next_kind != "extends_statement" && next_kind != "class_name_statement" {
       break; // Done with prologue
     }
@@ -3,18 +3,86 @@
 //! directly.
 
 use crate::driver;
-use crate::cardgame::{GameEngine, GameEngineError, CardGameEnv, GameWinner, CardId};
-use crate::cardgame::deck::{DeckValidator, Deck};
-use crate::cardgame::code::deserialize_game_code;
+use crate::cardgame::{GameEngine, GameEngineError, CardGameEnv, GameWinner, GameResult, GameOutcome, GameRules, PlayerAgents, CardId, derive_game_seed};
+use crate::cardgame::card_stats::CardStatsCollector;
+use crate::cardgame::deck::{DeckValidator, Deck, read_named_deck_list_from_path};
+use crate::cardgame::code::{serialize_game_code, deserialize_game_code};
 use crate::cardgame::genetic::{GeneticAlgorithm, GeneticAlgorithmArgs};
+use crate::cardgame::genetic::checkpoint::Checkpoint;
+use crate::cardgame::genetic::stats::StatsWriter;
+use crate::cardgame::ladder::Ladder;
+use crate::cardgame::yaml_file::YamlFile;
+use crate::cardgame::significance::{Sprt, SprtDecision, WinRateSummary};
+use crate::cardgame::tempo::TempoCollector;
+use crate::cardgame::tournament::play_tournament;
+use crate::cardgame::trace::{GameTrace, TraceRecorder, write_traces_to_path};
+use crate::interpreter::mocking::codex::CodexDataFile;
 
+use clap::Args;
 use threadpool::ThreadPool;
 
 use std::process::ExitCode;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, LazyLock};
 use std::sync::mpsc;
 use std::thread;
 
+/// Command line arguments for the commands which play a batch of
+/// games between two decks.
+#[derive(Debug, Clone, Args)]
+pub struct BatchArgs {
+  /// Random seed as a u64. If not provided, generator will be
+  /// randomly seeded.
+  #[arg(long)]
+  pub seed: Option<u64>,
+  /// Number of runs to perform.
+  #[arg(short = 'n', long, default_value_t = 1)]
+  pub count: u32,
+  /// Alternate which deck goes first from game to game. Results
+  /// are reported per deck and per seat.
+  #[arg(long)]
+  pub swap_sides: bool,
+  /// Ladder file in which to record the results of these games. The
+  /// file is created if it does not exist.
+  #[arg(long)]
+  pub ladder: Option<PathBuf>,
+  /// Confidence level of the reported win rate intervals, from 0 to
+  /// 1. (default = 0.95)
+  #[arg(long, default_value_t = 0.95)]
+  pub confidence: f64,
+  /// Report per-card statistics (draws, plays, win rates when
+  /// played, and fort damage dealt) after the batch.
+  #[arg(long)]
+  pub card_stats: bool,
+  /// Report game length and tempo statistics (fort damage, Evil
+  /// Points, and cards in hand over time) after the batch.
+  #[arg(long)]
+  pub tempo_stats: bool,
+}
+
+/// Command line arguments for stopping a batch of games early, by a
+/// sequential probability ratio test of the BOTTOM deck's win rate.
+#[derive(Debug, Clone, Args)]
+pub struct SprtArgs {
+  /// Keep playing games until a sequential probability ratio test
+  /// decides between an even matchup and a BOTTOM deck win rate of
+  /// `--sprt-p1`, up to the number of runs given by `--count`.
+  #[arg(long)]
+  pub until_significant: bool,
+  /// BOTTOM deck win rate under the alternative hypothesis.
+  /// (default = 0.55)
+  #[arg(long, default_value_t = 0.55)]
+  pub sprt_p1: f64,
+  /// Chance of accepting `--sprt-p1` when the matchup is even.
+  /// (default = 0.05)
+  #[arg(long, default_value_t = 0.05)]
+  pub sprt_alpha: f64,
+  /// Chance of accepting an even matchup when the win rate is
+  /// `--sprt-p1`. (default = 0.05)
+  #[arg(long, default_value_t = 0.05)]
+  pub sprt_beta: f64,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ValidationResult {
   Ok,
@@ -22,6 +90,24 @@ pub enum ValidationResult {
   CriticalError,
 }
 
+impl SprtArgs {
+  /// The test to run, or `None` if games should not stop early.
+  pub fn resolve(&self) -> anyhow::Result<Option<Sprt>> {
+    if !self.until_significant {
+      return Ok(None);
+    }
+    if !(0.0 < self.sprt_p1 && self.sprt_p1 < 1.0) || self.sprt_p1 == 0.5 {
+      anyhow::bail!("--sprt-p1 must be strictly between 0 and 1, and not 0.5, but was {}", self.sprt_p1);
+    }
+    for (name, value) in [("--sprt-alpha", self.sprt_alpha), ("--sprt-beta", self.sprt_beta)] {
+      if !(0.0 < value && value < 0.5) {
+        anyhow::bail!("{name} must be strictly between 0 and 0.5, but was {value}");
+      }
+    }
+    Ok(Some(Sprt { p1: self.sprt_p1, alpha: self.sprt_alpha, beta: self.sprt_beta }))
+  }
+}
+
 impl ValidationResult {
   pub fn to_status_code(self) -> u8 {
     match self {
@@ -44,120 +130,392 @@ pub fn validate_user_deck(deck: &Deck) -> ValidationResult {
   res
 }
 
-pub fn play_from_code(code_str: &str) -> anyhow::Result<()> {
+pub fn play_from_code(code_str: &str, agents: &PlayerAgents, rules: &GameRules, trace_out: Option<&Path>) -> anyhow::Result<()> {
   let (seed, env) = deserialize_game_code(code_str)?;
+  if env.bottom_deck.len() != rules.deck_size {
+    anyhow::bail!(
+      "Game code has decks of size {}, but the rules expect {}; pass matching rules with --deck-size or --rules",
+      env.bottom_deck.len(),
+      rules.deck_size,
+    );
+  }
   tracing::info!("Running with user-provided seed: {seed}");
   tracing::info!("Player BOTTOM deck = {}", env.bottom_deck);
   tracing::info!("Player TOP deck = {}", env.top_deck);
+  log_agents(agents);
 
   validate_deck("BOTTOM", env.bottom_deck.as_ref());
   validate_deck("TOP", env.top_deck.as_ref());
 
   let superglobals = driver::load_all_files()?;
   let engine = GameEngine::new(superglobals);
-  let outcome = engine.play_game_seeded(&env, seed)?;
+  let mut traces = Vec::new();
+  let outcome = play_game_with_trace(&engine, &env, agents, rules, seed, trace_out.is_some().then_some(&mut traces));
+  if let Some(trace_out) = trace_out {
+    write_traces(trace_out, &traces)?;
+  }
+  let outcome = outcome?;
   tracing::info!("Game Winner: {}", outcome);
+  log_outcome_details(&outcome);
   Ok(())
 }
 
-pub fn play_sequential(env: &CardGameEnv<Deck>, user_seed: Option<u64>, run_count: u32) -> anyhow::Result<()> {
+/// Plays the card game one or more times in a single thread. If
+/// `trace_out` is given, a trace of every game is written to that
+/// file, including any game that ended in an error.
+pub fn play_sequential(
+  env: &CardGameEnv<Deck>,
+  agents: &PlayerAgents,
+  rules: &GameRules,
+  batch: &BatchArgs,
+  trace_out: Option<&Path>,
+) -> anyhow::Result<()> {
   let superglobals = driver::load_all_files()?;
-  let engine = GameEngine::new(superglobals);
+  let mut engine = GameEngine::new(superglobals);
+  let card_stats = batch.card_stats.then(|| add_card_stats_collector(&mut engine));
+  let tempo_stats = batch.tempo_stats.then(|| add_tempo_collector(&mut engine));
 
   validate_deck("BOTTOM", env.bottom_deck.as_ref());
   validate_deck("TOP", env.top_deck.as_ref());
 
+  log_agents(agents);
+  let run_count = batch.count;
   tracing::info!("Running sequentially {} game(s)", run_count);
 
-  let mut bottom_wins = 0;
-  let mut top_wins = 0;
+  let swapped_env = env.clone().swap_sides();
+  let swapped_agents = agents.clone().swap_sides();
+
+  let master_seed = resolve_seed(batch.seed);
+  let mut tally = MatchupTally::default();
+  let mut results = Vec::with_capacity(run_count as usize);
+  let mut traces = Vec::new();
   for i in 0..run_count {
     let _span_guard = tracing::info_span!("run", index = i + 1).entered();
     tracing::info!("Run {} of {}", i + 1, run_count);
-    let seed = resolve_seed(user_seed);
+    let swapped = batch.swap_sides && is_swapped_game(i);
+    let (env, agents) = if swapped { (&swapped_env, &swapped_agents) } else { (env, agents) };
+    let seed = derive_game_seed(master_seed, u64::from(i));
+    log_game_seed(seed, env);
     tracing::debug!("Player BOTTOM deck = {}", env.bottom_deck);
     tracing::debug!("Player TOP deck = {}", env.top_deck);
-    let outcome = engine.play_game_seeded(&env, seed)?;
+    let outcome = match play_game_with_trace(&engine, env, agents, rules, seed, trace_out.is_some().then_some(&mut traces)) {
+      Ok(outcome) => outcome,
+      Err(err) => {
+        if let Some(trace_out) = trace_out {
+          write_traces(trace_out, &traces)?;
+        }
+        return Err(err.into());
+      }
+    };
     tracing::info!("Game {} Winner: {}", i + 1, outcome);
-    match outcome {
-      GameWinner::Bottom => bottom_wins += 1,
-      GameWinner::Top => top_wins += 1,
-    }
+    log_outcome_details(&outcome);
+    tally.record(swapped, Some(outcome.result));
+    results.push(Some(outcome.result));
+  }
+  tally.log_results(run_count, batch.swap_sides, batch.confidence);
+  if let Some(ladder_path) = &batch.ladder {
+    record_in_ladder(ladder_path, env, batch.swap_sides, &results)?;
+  }
+  if let Some(card_stats) = &card_stats {
+    log_card_stats(card_stats)?;
+  }
+  if let Some(tempo_stats) = &tempo_stats {
+    tempo_stats.stats().log(rules.turn_limit);
   }
-  tracing::info!("Player BOTTOM won {bottom_wins} time(s) of {run_count}");
-  tracing::info!("Player TOP won {top_wins} time(s) of {run_count}");
+  if let Some(trace_out) = trace_out {
+    write_traces(trace_out, &traces)?;
+  }
+  Ok(())
+}
+
+/// Plays a single game. If `traces` is given, the game is traced and
+/// its trace is appended to the list, whether or not the game ends
+/// in an error.
+fn play_game_with_trace(
+  engine: &GameEngine,
+  env: &CardGameEnv<Deck>,
+  agents: &PlayerAgents,
+  rules: &GameRules,
+  seed: u64,
+  traces: Option<&mut Vec<GameTrace>>,
+) -> Result<GameOutcome, GameEngineError> {
+  let Some(traces) = traces else {
+    return engine.play_game_seeded(env, agents, rules, seed);
+  };
+  let recorder = TraceRecorder::new();
+  let outcome = engine.play_game_observed(env, agents, rules, seed, vec![Box::new(recorder.clone())]);
+  traces.push(GameTrace {
+    game_code: serialize_game_code(seed, env).unwrap_or_else(|_| "(failed to get game code)".to_owned()),
+    result: outcome.as_ref().ok().map(|outcome| outcome.to_string()),
+    error: outcome.as_ref().err().map(|err| err.to_string()),
+    events: recorder.take_events(),
+  });
+  outcome
+}
+
+fn write_traces(trace_out: &Path, traces: &[GameTrace]) -> anyhow::Result<()> {
+  write_traces_to_path(traces, trace_out)?;
+  tracing::info!("Wrote {} game trace(s) to {}", traces.len(), trace_out.display());
+  Ok(())
+}
+
+fn add_card_stats_collector(engine: &mut GameEngine) -> CardStatsCollector {
+  let collector = CardStatsCollector::new();
+  engine.add_observer_factory(Arc::new(collector.clone()));
+  collector
+}
+
+fn add_tempo_collector(engine: &mut GameEngine) -> TempoCollector {
+  let collector = TempoCollector::new();
+  engine.add_observer_factory(Arc::new(collector.clone()));
+  collector
+}
+
+fn log_card_stats(collector: &CardStatsCollector) -> anyhow::Result<()> {
+  let codex = CodexDataFile::read_from_default_file()?;
+  collector.stats().log(&codex);
   Ok(())
 }
 
-pub fn play_parallel(env: CardGameEnv<Deck>, user_seed: Option<u64>, run_count: u32, thread_count: Option<usize>) -> anyhow::Result<()> {
+/// Plays a batch of games between two decks on a thread pool.
+///
+/// If `--until-significant` is given, games are scheduled a few at a
+/// time, and a sequential probability ratio test of the BOTTOM
+/// deck's win rate is checked after each round, stopping once it
+/// accepts either hypothesis. The batch size is then the maximum
+/// number of games.
+pub fn play_parallel(
+  env: CardGameEnv<Deck>,
+  agents: PlayerAgents,
+  rules: GameRules,
+  batch: &BatchArgs,
+  thread_count: Option<usize>,
+  sprt_args: &SprtArgs,
+) -> anyhow::Result<()> {
+  let sprt = sprt_args.resolve()?;
+  let swapped_env = Arc::new(env.clone().swap_sides());
+  let swapped_agents = Arc::new(agents.clone().swap_sides());
   let env = Arc::new(env);
+  let agents = Arc::new(agents);
+  let rules = Arc::new(rules);
+  let run_count = batch.count;
+  let swap_sides = batch.swap_sides;
 
   let superglobals = driver::load_all_files()?;
-  let engine = GameEngine::new(superglobals);
+  let mut engine = GameEngine::new(superglobals);
+  let card_stats = batch.card_stats.then(|| add_card_stats_collector(&mut engine));
+  let tempo_stats = batch.tempo_stats.then(|| add_tempo_collector(&mut engine));
 
   validate_deck("BOTTOM", env.bottom_deck.as_ref());
   validate_deck("TOP", env.top_deck.as_ref());
 
+  log_agents(&agents);
   let thread_count = thread_count.unwrap_or_else(get_cpu_cores);
-  tracing::info!("Running {run_count} game(s) on {thread_count} thread(s)");
+  if let Some(sprt) = &sprt {
+    tracing::info!("Running up to {run_count} game(s) on {thread_count} thread(s), until a sequential test decides between a BOTTOM win rate of 50% and {:.1}% (alpha = {}, beta = {})",
+                   sprt.p1 * 100.0, sprt.alpha, sprt.beta);
+  } else {
+    tracing::info!("Running {run_count} game(s) on {thread_count} thread(s)");
+  }
   let pool = ThreadPool::new(thread_count);
+  // An even number of games per round keeps the seats balanced when
+  // swapping sides.
+  let round_size = if sprt.is_some() { 2 * thread_count as u32 } else { run_count };
 
-  let (tx, rx) = mpsc::channel::<(u32, Result<GameWinner, GameEngineError>)>();
-  for i in 0..run_count {
-    let tx = tx.clone();
-    let seed = resolve_seed(user_seed);
-    let env = Arc::clone(&env);
-    let engine = engine.clone();
-    pool.execute(move || {
-      let _span_guard = tracing::info_span!("run", index = i + 1).entered();
-      tracing::info!("Run {} of {}", i + 1, run_count);
-      tracing::debug!("Player BOTTOM deck = {}", env.bottom_deck);
-      tracing::debug!("Player TOP deck = {}", env.top_deck);
-      let outcome_or_err = engine.play_game_seeded(&env, seed);
-      match &outcome_or_err {
-        Ok(outcome) => {
-          tracing::info!("Game {} Winner: {}", i + 1, outcome);
+  let master_seed = resolve_seed(batch.seed);
+  let (tx, rx) = mpsc::channel::<(u32, Result<GameOutcome, GameEngineError>)>();
+  let mut tally = MatchupTally::default();
+  let mut results = Vec::with_capacity(run_count as usize);
+  let mut scheduled = 0;
+  let mut decision = SprtDecision::Continue;
+  while scheduled < run_count {
+    let round_end = u32::min(scheduled + round_size, run_count);
+    for i in scheduled..round_end {
+      let tx = tx.clone();
+      let seed = derive_game_seed(master_seed, u64::from(i));
+      let (env, agents) = if swap_sides && is_swapped_game(i) {
+        (Arc::clone(&swapped_env), Arc::clone(&swapped_agents))
+      } else {
+        (Arc::clone(&env), Arc::clone(&agents))
+      };
+      let rules = Arc::clone(&rules);
+      let engine = engine.clone();
+      pool.execute(move || {
+        let _span_guard = tracing::info_span!("run", index = i + 1).entered();
+        tracing::info!("Run {} of {}", i + 1, run_count);
+        log_game_seed(seed, &env);
+        tracing::debug!("Player BOTTOM deck = {}", env.bottom_deck);
+        tracing::debug!("Player TOP deck = {}", env.top_deck);
+        let outcome_or_err = engine.play_game_seeded(&env, &agents, &rules, seed);
+        match &outcome_or_err {
+          Ok(outcome) => {
+            tracing::info!("Game {} Winner: {}", i + 1, outcome);
+          }
+          Err(err) => {
+            tracing::error!("Game {i} Error: {err}");
+          }
         }
-        Err(err) => {
-          tracing::error!("Game {i} Error: {err}");
+        if let Err(err) = tx.send((i, outcome_or_err)) {
+          tracing::error!("Channel error in game thread: {err}");
         }
+      });
+    }
+
+    // Collect results
+    results.resize(round_end as usize, None);
+    for (i, result) in rx.iter().take((round_end - scheduled) as usize) {
+      let swapped = swap_sides && is_swapped_game(i);
+      let result = result.ok().map(|outcome| outcome.result);
+      tally.record(swapped, result);
+      results[i as usize] = result;
+    }
+    scheduled = round_end;
+
+    if let Some(sprt) = &sprt {
+      let summary = tally.bottom_deck_summary(batch.confidence);
+      decision = sprt.decide(summary.wins, summary.losses);
+      if decision != SprtDecision::Continue {
+        break;
       }
-      if let Err(err) = tx.send((i, outcome_or_err)) {
-        tracing::error!("Channel error in game thread: {err}");
-      }
-    });
+    }
   }
-
-  // Collect results
-  let mut bottom_wins = 0;
-  let mut top_wins = 0;
-  let mut error_outcomes = 0;
-  for (_i, result) in rx.iter().take(run_count as usize) {
-    match result {
-      Ok(GameWinner::Bottom) => bottom_wins += 1,
-      Ok(GameWinner::Top) => top_wins += 1,
-      Err(_) => error_outcomes += 1,
+  if let Some(sprt) = &sprt {
+    let summary = tally.bottom_deck_summary(batch.confidence);
+    let ratio = sprt.log_likelihood_ratio(summary.wins, summary.losses);
+    match decision {
+      SprtDecision::AcceptAlternative => {
+        tracing::info!("Stopped after {scheduled} game(s): the test {decision} of {:.1}%, with log-likelihood ratio {ratio:.3} >= upper bound {:.3}",
+                       sprt.p1 * 100.0, sprt.upper_bound());
+      }
+      SprtDecision::AcceptNull => {
+        tracing::info!("Stopped after {scheduled} game(s): the test {decision}, with log-likelihood ratio {ratio:.3} <= lower bound {:.3}",
+                       sprt.lower_bound());
+      }
+      SprtDecision::Continue => {
+        tracing::info!("Stopped after the maximum of {run_count} game(s): the test is {decision}, with log-likelihood ratio {ratio:.3} between {:.3} and {:.3}",
+                       sprt.lower_bound(), sprt.upper_bound());
+      }
     }
   }
-
-  tracing::info!("Player BOTTOM won {bottom_wins} time(s) of {run_count}");
-  tracing::info!("Player TOP won {top_wins} time(s) of {run_count}");
-  tracing::info!("Game errored on {error_outcomes} time(s) of {run_count}");
+  tally.log_results(scheduled, swap_sides, batch.confidence);
+  if let Some(ladder_path) = &batch.ladder {
+    record_in_ladder(ladder_path, &env, swap_sides, &results)?;
+  }
+  if let Some(card_stats) = &card_stats {
+    log_card_stats(card_stats)?;
+  }
+  if let Some(tempo_stats) = &tempo_stats {
+    tempo_stats.stats().log(rules.turn_limit);
+  }
   Ok(())
 }
 
-pub fn run_genetic_algorithm(thread_count: Option<usize>, generations: usize, args: GeneticAlgorithmArgs) -> anyhow::Result<()> {
+pub fn run_genetic_algorithm(
+  thread_count: Option<usize>,
+  generations: usize,
+  args: GeneticAlgorithmArgs,
+  rules: GameRules,
+  checkpoint_path: Option<PathBuf>,
+  resume: Option<PathBuf>,
+  stats_out: Option<PathBuf>,
+) -> anyhow::Result<()> {
   let thread_size = thread_count.unwrap_or_else(get_cpu_cores);
   let thread_pool = ThreadPool::new(thread_size);
-  let mut genetic_algorithm = GeneticAlgorithm::new(&thread_pool, args.clone())?;
-  let best_decks = genetic_algorithm.run_genetic_algorithm(generations);
+  let resuming = resume.is_some();
+  let mut genetic_algorithm = match resume {
+    Some(resume_path) => {
+      let checkpoint = Checkpoint::read_from_path(&resume_path)?;
+      tracing::info!("Loaded checkpoint from {}", resume_path.display());
+      for setting in checkpoint.differing_settings(&args, &rules, &args.load_constraints()?) {
+        tracing::warn!("The command line value of {setting} differs from the checkpoint; using the checkpoint's value");
+      }
+      GeneticAlgorithm::resume(&thread_pool, checkpoint)?
+    }
+    None => GeneticAlgorithm::new(&thread_pool, args, rules)?,
+  };
+  if let Some(checkpoint_path) = checkpoint_path {
+    genetic_algorithm.set_checkpoint_path(checkpoint_path);
+  }
+  if let Some(stats_out) = stats_out {
+    genetic_algorithm.set_stats_writer(StatsWriter::open(&stats_out, resuming)?);
+  }
+  let best_decks = genetic_algorithm.run_genetic_algorithm(generations)?;
   tracing::info!("Genetic algorithm completed");
-  for deck in &best_decks[..args.elite_deck_count()] {
+  for deck in &best_decks[..genetic_algorithm.args().elite_deck_count()] {
     tracing::info!("Elite deck: {}", genetic_algorithm.validator().pretty_to_string(deck.as_ref()));
   }
   Ok(())
 }
 
+pub fn run_tournament(
+  decks_path: &Path,
+  games_per_pair: usize,
+  user_seed: Option<u64>,
+  thread_count: Option<usize>,
+  rules: GameRules,
+  ladder_path: Option<&Path>,
+) -> anyhow::Result<()> {
+  let decks = read_named_deck_list_from_path(decks_path)?;
+  if decks.len() < 2 {
+    anyhow::bail!("A tournament needs at least two decks, but {} contains {}", decks_path.display(), decks.len());
+  }
+  for (index, named) in decks.iter().enumerate() {
+    tracing::info!("Deck {} ({}) = {}", index + 1, named.name, named.deck);
+    validate_deck(&named.name, named.deck.as_ref());
+  }
+
+  let superglobals = driver::load_all_files()?;
+  let engine = GameEngine::new(superglobals);
+  let thread_count = thread_count.unwrap_or_else(get_cpu_cores);
+  let pair_count = decks.len() * (decks.len() - 1) / 2;
+  tracing::info!("Running {games_per_pair} game(s) for each of {pair_count} pair(s) on {thread_count} thread(s)");
+  let pool = ThreadPool::new(thread_count);
+
+  let master_seed = resolve_seed(user_seed);
+  let results = play_tournament(&engine, &pool, &decks, &Arc::new(rules), games_per_pair, master_seed);
+  results.log_results();
+
+  if let Some(ladder_path) = ladder_path {
+    let mut ladder = Ladder::read_or_default(ladder_path)?;
+    let keys = decks.iter()
+      .map(|named| ladder.register(&named.deck, Some(&named.name)))
+      .collect::<Vec<_>>();
+    for (bottom, top, result) in results.game_results() {
+      ladder.record_game(&keys[bottom], &keys[top], result);
+    }
+    ladder.write_to_path(ladder_path)?;
+    tracing::info!("Recorded games in ladder {}", ladder_path.display());
+  }
+  Ok(())
+}
+
+/// Records a batch of games between two decks in a ladder file. The
+/// results are given in the order in which the games were scheduled,
+/// with `None` for games that errored.
+fn record_in_ladder(ladder_path: &Path, env: &CardGameEnv<Deck>, swap_sides: bool, results: &[Option<GameResult>]) -> anyhow::Result<()> {
+  let mut ladder = Ladder::read_or_default(ladder_path)?;
+  let bottom_key = ladder.register(&env.bottom_deck, None);
+  let top_key = ladder.register(&env.top_deck, None);
+  for (i, result) in results.iter().enumerate() {
+    let Some(result) = result else { continue; };
+    if swap_sides && is_swapped_game(i as u32) {
+      ladder.record_game(&top_key, &bottom_key, *result);
+    } else {
+      ladder.record_game(&bottom_key, &top_key, *result);
+    }
+  }
+  ladder.write_to_path(ladder_path)?;
+  tracing::info!("Recorded games in ladder {}", ladder_path.display());
+  Ok(())
+}
+
+pub fn show_ladder(ladder_path: &Path) -> anyhow::Result<()> {
+  let ladder = Ladder::read_from_path(ladder_path)?;
+  tracing::info!("Ladder {} contains {} deck(s)", ladder_path.display(), ladder.decks.len());
+  ladder.log_standings();
+  Ok(())
+}
+
 fn validate_deck(deck_name: &str, deck: &[CardId]) -> ValidationResult {
   static VALIDATOR: LazyLock<DeckValidator> = LazyLock::new(|| {
     DeckValidator::load_default()
@@ -184,6 +542,100 @@ fn validate_deck(deck_name: &str, deck: &[CardId]) -> ValidationResult {
   validation_result
 }
 
+/// When swapping sides, odd-numbered games are played with the decks
+/// exchanged.
+fn is_swapped_game(index: u32) -> bool {
+  index % 2 == 1
+}
+
+/// Results of a batch of games between two decks, in terms of the
+/// seats in which those games were actually played.
+#[derive(Debug, Clone, Default)]
+struct MatchupTally {
+  /// Games played with the decks as given.
+  as_given: SeatingTally,
+  /// Games played with the decks exchanged.
+  swapped: SeatingTally,
+}
+
+#[derive(Debug, Clone, Default)]
+struct SeatingTally {
+  bottom_wins: u32,
+  top_wins: u32,
+  draws: u32,
+  errors: u32,
+}
+
+impl MatchupTally {
+  /// Records the result of a single game, or `None` if it errored.
+  fn record(&mut self, swapped: bool, result: Option<GameResult>) {
+    let seating = if swapped { &mut self.swapped } else { &mut self.as_given };
+    match result {
+      Some(GameResult::Win(GameWinner::Bottom)) => seating.bottom_wins += 1,
+      Some(GameResult::Win(GameWinner::Top)) => seating.top_wins += 1,
+      Some(GameResult::Draw { .. }) => seating.draws += 1,
+      None => seating.errors += 1,
+    }
+  }
+
+  /// The win rate of the deck given as BOTTOM, over the decisive
+  /// games from either seat.
+  fn bottom_deck_summary(&self, confidence: f64) -> WinRateSummary {
+    let wins = self.as_given.bottom_wins + self.swapped.top_wins;
+    let losses = self.as_given.top_wins + self.swapped.bottom_wins;
+    WinRateSummary::new(u64::from(wins), u64::from(losses), confidence)
+  }
+
+  fn log_results(&self, run_count: u32, swap_sides: bool, confidence: f64) {
+    if swap_sides {
+      self.as_given.log_results("With decks as given");
+      self.swapped.log_results("With sides swapped");
+      tracing::info!("Deck BOTTOM won {} time(s) of {run_count} ({} going first, {} going second)",
+                     self.as_given.bottom_wins + self.swapped.top_wins, self.as_given.bottom_wins, self.swapped.top_wins);
+      tracing::info!("Deck TOP won {} time(s) of {run_count} ({} going first, {} going second)",
+                     self.as_given.top_wins + self.swapped.bottom_wins, self.swapped.bottom_wins, self.as_given.top_wins);
+      tracing::info!("Seat BOTTOM won {} time(s) and seat TOP won {} time(s) of {run_count}",
+                     self.as_given.bottom_wins + self.swapped.bottom_wins, self.as_given.top_wins + self.swapped.top_wins);
+    }
+    let draws = self.as_given.draws + self.swapped.draws;
+    let errors = self.as_given.errors + self.swapped.errors;
+    if !swap_sides {
+      tracing::info!("Player BOTTOM won {} time(s) of {run_count}", self.as_given.bottom_wins);
+      tracing::info!("Player TOP won {} time(s) of {run_count}", self.as_given.top_wins);
+    }
+    tracing::info!("Game was a draw {draws} time(s) of {run_count}");
+    tracing::info!("Game errored on {errors} time(s) of {run_count}");
+    self.bottom_deck_summary(confidence).log("Deck BOTTOM");
+    if swap_sides {
+      let seat_wins = self.as_given.bottom_wins + self.swapped.bottom_wins;
+      let seat_losses = self.as_given.top_wins + self.swapped.top_wins;
+      WinRateSummary::new(u64::from(seat_wins), u64::from(seat_losses), confidence).log("Seat BOTTOM");
+    }
+  }
+}
+
+impl SeatingTally {
+  fn log_results(&self, label: &str) {
+    let total = self.bottom_wins + self.top_wins + self.draws + self.errors;
+    tracing::info!("{label}: BOTTOM won {}, TOP won {}, {} draw(s), {} error(s) of {total}",
+                   self.bottom_wins, self.top_wins, self.draws, self.errors);
+  }
+}
+
+fn log_agents(agents: &PlayerAgents) {
+  tracing::info!("Player BOTTOM agent = {}", agents.bottom);
+  tracing::info!("Player TOP agent = {}", agents.top);
+}
+
+fn log_outcome_details(outcome: &GameOutcome) {
+  for (player_name, player) in [("BOTTOM", &outcome.bottom), ("TOP", &outcome.top)] {
+    tracing::debug!("Player {player_name}: fort defense = {}/{}, evil points = {}, destiny song = {}",
+                    player.fort_defense, player.max_fort_defense, player.evil_points, player.destiny_song);
+    tracing::debug!("Player {player_name}: deck = {}, hand = {}, discard pile = {}, minions = {}, effects = {}",
+                    player.cards.deck, player.cards.hand, player.cards.discard_pile, player.cards.minions, player.cards.effects);
+  }
+}
+
 fn get_cpu_cores() -> usize {
   match thread::available_parallelism() {
     Ok(count) => count.into(),
@@ -195,15 +647,21 @@ fn get_cpu_cores() -> usize {
 }
 
 /// If user seed was provided, return it. If not, generate one with
-/// system-provided entropy.
+/// system-provided entropy. The result is a master seed, from which
+/// the seed of each game is derived.
 fn resolve_seed(user_seed: Option<u64>) -> u64 {
   let seed;
   if let Some(user_seed) = user_seed {
     seed = user_seed;
-    tracing::info!("Running with user-provided seed: {seed}");
+    tracing::info!("Running with user-provided master seed: {seed}");
   } else {
     seed = rand::random::<u64>();
-    tracing::info!("Running with random seed: {seed}");
+    tracing::info!("Running with random master seed: {seed}");
   };
   seed
 }
+
+fn log_game_seed(seed: u64, env: &CardGameEnv<Deck>) {
+  let game_code = serialize_game_code(seed, env).unwrap_or_else(|_| "(failed to get game code)".to_owned());
+  tracing::info!(%game_code, "Game seed: {seed}");
+}